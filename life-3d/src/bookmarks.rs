@@ -0,0 +1,494 @@
+// Named camera positions that can be saved to disk and recalled, and paths
+// that fly the camera between them, so that the same shot can be shown
+// again and again.
+
+use std::{fmt, fs, io, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    camera::{ActiveCamera, ArcballCamera, ThirdPersonCamera},
+    math::{Quaternion, Vec3},
+};
+
+#[derive(Debug)]
+pub enum BookmarkError {
+    Io(io::Error),
+    Parse(toml::de::Error),
+    Serialize(toml::ser::Error),
+    // A path refers to a bookmark that does not exist.
+    UnknownBookmark { path: String, bookmark: String },
+    // A path's keyframes have to be in order, and there must be at least one.
+    InvalidPath { path: String, reason: &'static str },
+}
+
+impl fmt::Display for BookmarkError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BookmarkError::Io(error) => write!(f, "{}", error),
+            BookmarkError::Parse(error) => write!(f, "invalid bookmarks file: {}", error),
+            BookmarkError::Serialize(error) => write!(f, "failed to save bookmarks: {}", error),
+            BookmarkError::UnknownBookmark { path, bookmark } => {
+                write!(
+                    f,
+                    "path `{}` uses the unknown bookmark `{}`",
+                    path, bookmark
+                )
+            }
+            BookmarkError::InvalidPath { path, reason } => {
+                write!(f, "path `{}` is invalid: {}", path, reason)
+            }
+        }
+    }
+}
+
+impl std::error::Error for BookmarkError {}
+
+impl From<io::Error> for BookmarkError {
+    fn from(error: io::Error) -> Self {
+        BookmarkError::Io(error)
+    }
+}
+
+// Which kind of camera the bookmark was taken with, and the way it was
+// facing.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum CameraPose {
+    Orbit { yaw: f32, pitch: f32 },
+    Arcball { orientation: Quaternion },
+    Fly { yaw: f32, pitch: f32 },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Bookmark {
+    pub name: String,
+    // For flying cameras, the point `distance` units in front of them.
+    pub target: Vec3,
+    pub distance: f32,
+    pub pose: CameraPose,
+    // The vertical field of view, in degrees.
+    pub fov: f32,
+}
+
+impl Bookmark {
+    pub fn capture(name: &str, camera: &ActiveCamera, fov: f32) -> Bookmark {
+        let (target, distance, pose) = match camera {
+            ActiveCamera::ThirdPerson(camera) => (
+                camera.target(),
+                camera.distance(),
+                CameraPose::Orbit {
+                    yaw: camera.yaw(),
+                    pitch: camera.pitch(),
+                },
+            ),
+            ActiveCamera::Arcball(camera) => (
+                camera.target(),
+                camera.distance(),
+                CameraPose::Arcball {
+                    orientation: camera.orientation(),
+                },
+            ),
+            ActiveCamera::Fly(camera, distance) => (
+                camera.position() + camera.front() * *distance,
+                *distance,
+                CameraPose::Fly {
+                    yaw: camera.yaw(),
+                    pitch: camera.pitch(),
+                },
+            ),
+        };
+
+        Bookmark {
+            name: name.to_string(),
+            target,
+            distance,
+            pose,
+            fov,
+        }
+    }
+
+    // A camera of the same kind as the one the bookmark was taken with.
+    pub fn camera(&self) -> ActiveCamera {
+        match self.pose {
+            CameraPose::Orbit { yaw, pitch } => ActiveCamera::ThirdPerson(ThirdPersonCamera::new(
+                self.target,
+                self.distance,
+                yaw,
+                pitch,
+            )),
+            CameraPose::Arcball { orientation } => ActiveCamera::Arcball(
+                ArcballCamera::with_orientation(self.target, self.distance, orientation),
+            ),
+            CameraPose::Fly { yaw, pitch } => {
+                // The flying camera looks the opposite way to an orbiting
+                // camera sitting in the same spot.
+                let orbit = ThirdPersonCamera::new(self.target, self.distance, yaw - 180.0, -pitch);
+                ActiveCamera::Fly(orbit.to_fly(), self.distance)
+            }
+        }
+    }
+
+    fn orientation(&self) -> Quaternion {
+        self.camera().to_arcball().orientation()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Keyframe {
+    pub bookmark: String,
+    // Seconds since the start of the path.
+    pub time: f32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CameraPath {
+    pub name: String,
+    pub keyframes: Vec<Keyframe>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Bookmarks {
+    #[serde(default)]
+    pub bookmarks: Vec<Bookmark>,
+    #[serde(default)]
+    pub paths: Vec<CameraPath>,
+}
+
+impl Bookmarks {
+    // Loads the bookmarks from `path`. A missing file just means that there
+    // are no bookmarks yet.
+    pub fn load(path: &Path) -> Result<Bookmarks, BookmarkError> {
+        match fs::read_to_string(path) {
+            Ok(contents) => Bookmarks::from_toml(&contents),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(Bookmarks::default()),
+            Err(error) => Err(error.into()),
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), BookmarkError> {
+        fs::write(path, self.to_toml()?)?;
+        Ok(())
+    }
+
+    pub fn from_toml(contents: &str) -> Result<Bookmarks, BookmarkError> {
+        toml::from_str(contents).map_err(BookmarkError::Parse)
+    }
+
+    pub fn to_toml(&self) -> Result<String, BookmarkError> {
+        toml::to_string_pretty(self).map_err(BookmarkError::Serialize)
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Bookmark> {
+        self.bookmarks.iter().find(|bookmark| bookmark.name == name)
+    }
+
+    // Adds the bookmark, replacing any other one with the same name.
+    pub fn insert(&mut self, bookmark: Bookmark) {
+        match self.bookmarks.iter_mut().find(|b| b.name == bookmark.name) {
+            Some(existing) => *existing = bookmark,
+            None => self.bookmarks.push(bookmark),
+        }
+    }
+
+    // Plays the path called `name`.
+    pub fn flythrough(&self, name: &str) -> Option<Result<Flythrough, BookmarkError>> {
+        self.paths
+            .iter()
+            .find(|path| path.name == name)
+            .map(|path| Flythrough::new(path, self))
+    }
+
+    // Plays the first path in the file, or if there are none, visits every
+    // bookmark in turn, `seconds_per_bookmark` apart.
+    pub fn default_flythrough(
+        &self,
+        seconds_per_bookmark: f32,
+    ) -> Option<Result<Flythrough, BookmarkError>> {
+        if let Some(path) = self.paths.first() {
+            return Some(Flythrough::new(path, self));
+        }
+
+        if self.bookmarks.is_empty() {
+            return None;
+        }
+
+        let path = CameraPath {
+            name: "all bookmarks".to_string(),
+            keyframes: self
+                .bookmarks
+                .iter()
+                .enumerate()
+                .map(|(i, bookmark)| Keyframe {
+                    bookmark: bookmark.name.clone(),
+                    time: i as f32 * seconds_per_bookmark,
+                })
+                .collect(),
+        };
+
+        Some(Flythrough::new(&path, self))
+    }
+}
+
+// A camera path being played back. The camera only depends on how long the
+// path has been playing, never on how that time was split into frames.
+pub struct Flythrough {
+    keyframes: Vec<(f32, Bookmark, Quaternion)>,
+    elapsed: f32,
+}
+
+impl Flythrough {
+    pub fn new(path: &CameraPath, bookmarks: &Bookmarks) -> Result<Flythrough, BookmarkError> {
+        let invalid = |reason| BookmarkError::InvalidPath {
+            path: path.name.clone(),
+            reason,
+        };
+
+        if path.keyframes.is_empty() {
+            return Err(invalid("it has no keyframes"));
+        }
+
+        if path
+            .keyframes
+            .windows(2)
+            .any(|pair| pair[1].time < pair[0].time)
+        {
+            return Err(invalid("the keyframes are not in order"));
+        }
+
+        let keyframes = path
+            .keyframes
+            .iter()
+            .map(|keyframe| {
+                let bookmark = bookmarks.get(&keyframe.bookmark).ok_or_else(|| {
+                    BookmarkError::UnknownBookmark {
+                        path: path.name.clone(),
+                        bookmark: keyframe.bookmark.clone(),
+                    }
+                })?;
+
+                Ok((keyframe.time, bookmark.clone(), bookmark.orientation()))
+            })
+            .collect::<Result<Vec<_>, BookmarkError>>()?;
+
+        Ok(Flythrough {
+            keyframes,
+            elapsed: 0.0,
+        })
+    }
+
+    pub fn advance(&mut self, delta_time: f32) {
+        self.elapsed += delta_time;
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.elapsed >= self.duration()
+    }
+
+    pub fn duration(&self) -> f32 {
+        self.keyframes.last().map_or(0.0, |(time, ..)| *time)
+    }
+
+    // The camera and field of view at the current time. Once the path is
+    // over, this is exactly the last bookmark, camera kind and all.
+    pub fn sample(&self) -> (ActiveCamera, f32) {
+        let next = self
+            .keyframes
+            .iter()
+            .position(|(time, ..)| *time > self.elapsed);
+
+        let (from, to) = match next {
+            None => {
+                let (_, last, _) = self.keyframes.last().unwrap();
+                return (last.camera(), last.fov);
+            }
+            Some(0) => {
+                let (_, first, _) = &self.keyframes[0];
+                return (first.camera(), first.fov);
+            }
+            Some(next) => (&self.keyframes[next - 1], &self.keyframes[next]),
+        };
+
+        let (from_time, from, from_orientation) = from;
+        let (to_time, to, to_orientation) = to;
+
+        let t = (self.elapsed - from_time) / (to_time - from_time);
+        // Ease in and out of every keyframe, so the camera slows down to
+        // show each bookmark.
+        let t = t * t * (3.0 - 2.0 * t);
+        let lerp = |a: f32, b: f32| a + (b - a) * t;
+
+        let camera = ArcballCamera::with_orientation(
+            from.target + (to.target - from.target) * t,
+            lerp(from.distance, to.distance),
+            from_orientation.slerp(to_orientation, t),
+        );
+
+        (ActiveCamera::Arcball(camera), lerp(from.fov, to.fov))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Bookmark, BookmarkError, Bookmarks, CameraPath, Flythrough, Keyframe};
+    use crate::{
+        camera::{ActiveCamera, ThirdPersonCamera},
+        math::{Mat4, Vec3},
+    };
+
+    fn approx_eq(a: &Mat4, b: &Mat4) -> bool {
+        let a = a.data.iter().flatten();
+        let b = b.data.iter().flatten();
+        a.zip(b).all(|(a, b)| (a - b).abs() < 1e-3)
+    }
+
+    fn orbit(yaw: f32, pitch: f32, distance: f32) -> ActiveCamera {
+        ActiveCamera::ThirdPerson(ThirdPersonCamera::new(
+            Vec3::new(1.0, -2.0, 0.5),
+            distance,
+            yaw,
+            pitch,
+        ))
+    }
+
+    fn bookmarks() -> Bookmarks {
+        let mut bookmarks = Bookmarks::default();
+        bookmarks.insert(Bookmark::capture("1", &orbit(0.0, 10.0, 5.0), 45.0));
+        bookmarks.insert(Bookmark::capture("2", &orbit(90.0, 60.0, 10.0), 60.0));
+
+        let mut arcball = orbit(200.0, -30.0, 3.0);
+        arcball.toggle_arcball();
+        bookmarks.insert(Bookmark::capture("3", &arcball, 45.0));
+
+        bookmarks.paths.push(CameraPath {
+            name: "tour".to_string(),
+            keyframes: vec![
+                Keyframe {
+                    bookmark: "1".to_string(),
+                    time: 0.0,
+                },
+                Keyframe {
+                    bookmark: "2".to_string(),
+                    time: 2.0,
+                },
+                Keyframe {
+                    bookmark: "3".to_string(),
+                    time: 5.0,
+                },
+            ],
+        });
+
+        bookmarks
+    }
+
+    #[test]
+    fn bookmarks_restore_every_camera_kind() {
+        let mut camera = orbit(30.0, 20.0, 4.0);
+
+        for _ in 0..2 {
+            let bookmark = Bookmark::capture("test", &camera, 45.0);
+            let restored = bookmark.camera();
+
+            assert_eq!(restored.is_flying(), camera.is_flying());
+            assert!(approx_eq(&restored.view_matrix(), &camera.view_matrix()));
+
+            camera.toggle_fly();
+        }
+
+        camera.toggle_arcball();
+        let restored = Bookmark::capture("test", &camera, 45.0).camera();
+        assert!(matches!(restored, ActiveCamera::Arcball(_)));
+        assert!(approx_eq(&restored.view_matrix(), &camera.view_matrix()));
+    }
+
+    #[test]
+    fn bookmarks_survive_a_round_trip_through_toml() {
+        let bookmarks = bookmarks();
+        let toml = bookmarks.to_toml().unwrap();
+
+        assert_eq!(Bookmarks::from_toml(&toml).unwrap(), bookmarks);
+    }
+
+    #[test]
+    fn inserting_replaces_bookmarks_with_the_same_name() {
+        let mut bookmarks = bookmarks();
+        bookmarks.insert(Bookmark::capture("2", &orbit(0.0, 0.0, 1.0), 30.0));
+
+        assert_eq!(bookmarks.bookmarks.len(), 3);
+        assert_eq!(bookmarks.get("2").unwrap().fov, 30.0);
+    }
+
+    #[test]
+    fn flythrough_passes_through_every_keyframe() {
+        let bookmarks = bookmarks();
+        let mut flythrough = bookmarks.flythrough("tour").unwrap().unwrap();
+
+        for (time, name) in [(0.0, "1"), (2.0, "2"), (5.0, "3")] {
+            flythrough.advance(time - flythrough.elapsed);
+
+            let (camera, fov) = flythrough.sample();
+            let bookmark = bookmarks.get(name).unwrap();
+
+            assert!(approx_eq(
+                &camera.view_matrix(),
+                &bookmark.camera().view_matrix()
+            ));
+            assert_eq!(fov, bookmark.fov);
+        }
+
+        assert!(flythrough.is_finished());
+        assert!(matches!(flythrough.sample().0, ActiveCamera::Arcball(_)));
+    }
+
+    #[test]
+    fn flythrough_is_independent_of_frame_rate() {
+        let bookmarks = bookmarks();
+        let mut slow = bookmarks.flythrough("tour").unwrap().unwrap();
+        let mut fast = bookmarks.flythrough("tour").unwrap().unwrap();
+
+        for _ in 0..30 {
+            slow.advance(0.1);
+        }
+        for _ in 0..300 {
+            fast.advance(0.01);
+        }
+
+        let (slow_camera, slow_fov) = slow.sample();
+        let (fast_camera, fast_fov) = fast.sample();
+        assert!(approx_eq(
+            &slow_camera.view_matrix(),
+            &fast_camera.view_matrix()
+        ));
+        assert!((slow_fov - fast_fov).abs() < 1e-3);
+    }
+
+    #[test]
+    fn flythrough_checks_its_path() {
+        let mut bookmarks = bookmarks();
+        bookmarks.paths[0].keyframes[1].bookmark = "missing".to_string();
+
+        assert!(matches!(
+            Flythrough::new(&bookmarks.paths[0], &bookmarks),
+            Err(BookmarkError::UnknownBookmark { .. })
+        ));
+
+        bookmarks.paths[0].keyframes[1].bookmark = "2".to_string();
+        bookmarks.paths[0].keyframes[1].time = 10.0;
+
+        assert!(matches!(
+            Flythrough::new(&bookmarks.paths[0], &bookmarks),
+            Err(BookmarkError::InvalidPath { .. })
+        ));
+    }
+
+    #[test]
+    fn default_flythrough_visits_every_bookmark() {
+        let mut bookmarks = bookmarks();
+        bookmarks.paths.clear();
+
+        let flythrough = bookmarks.default_flythrough(3.0).unwrap().unwrap();
+        assert_eq!(flythrough.duration(), 6.0);
+
+        assert!(Bookmarks::default().default_flythrough(3.0).is_none());
+    }
+}
@@ -9,6 +9,9 @@ pub enum BufferType {
 pub struct Buffer {
     buffer: GLuint,
     buffer_type: BufferType,
+    // How many bytes the buffer's storage can hold, for buffers that are
+    // updated with `upload_dynamic`.
+    capacity: usize,
 }
 
 impl Buffer {
@@ -20,6 +23,7 @@ impl Buffer {
             Buffer {
                 buffer,
                 buffer_type,
+                capacity: 0,
             }
         }
     }
@@ -62,6 +66,37 @@ impl Buffer {
     }
 }
 
+impl Buffer {
+    // Replaces the contents of a buffer that changes every now and then.
+    // Rather than waiting for the GPU to finish with the old contents, the
+    // old storage gets orphaned and the driver hands out a fresh block of
+    // the same size. The storage only grows (to the next power of two), so
+    // a live set that shrinks and grows again does not reallocate.
+    pub fn upload_dynamic<T>(&mut self, data: &[T]) {
+        let size = std::mem::size_of_val(data);
+        let target = self.get_target();
+
+        unsafe {
+            gl::BindBuffer(target, self.buffer);
+
+            if size > self.capacity {
+                self.capacity = size.next_power_of_two();
+            }
+
+            gl::BufferData(
+                target,
+                self.capacity as isize,
+                std::ptr::null(),
+                gl::DYNAMIC_DRAW,
+            );
+
+            if size > 0 {
+                gl::BufferSubData(target, 0, size as isize, data.as_ptr() as *const _);
+            }
+        }
+    }
+}
+
 impl Drop for Buffer {
     fn drop(&mut self) {
         unsafe {
@@ -70,6 +105,61 @@ impl Drop for Buffer {
     }
 }
 
+// A texture with one byte per pixel, read as the red channel in shaders.
+pub struct Texture {
+    texture: GLuint,
+}
+
+impl Texture {
+    pub fn with_r8(width: usize, height: usize, pixels: &[u8]) -> Texture {
+        assert_eq!(pixels.len(), width * height);
+
+        unsafe {
+            let mut texture = 0;
+            gl::GenTextures(1, &mut texture);
+            gl::BindTexture(gl::TEXTURE_2D, texture);
+
+            // Rows of single bytes aren't padded to four.
+            gl::PixelStorei(gl::UNPACK_ALIGNMENT, 1);
+            gl::TexImage2D(
+                gl::TEXTURE_2D,
+                0,
+                gl::R8 as i32,
+                width as i32,
+                height as i32,
+                0,
+                gl::RED,
+                gl::UNSIGNED_BYTE,
+                pixels.as_ptr() as *const _,
+            );
+            gl::PixelStorei(gl::UNPACK_ALIGNMENT, 4);
+
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::NEAREST as i32);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::NEAREST as i32);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE as i32);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE as i32);
+            gl::BindTexture(gl::TEXTURE_2D, 0);
+
+            Texture { texture }
+        }
+    }
+
+    pub fn bind(&self, unit: GLuint) {
+        unsafe {
+            gl::ActiveTexture(gl::TEXTURE0 + unit);
+            gl::BindTexture(gl::TEXTURE_2D, self.texture);
+        }
+    }
+}
+
+impl Drop for Texture {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteTextures(1, &self.texture);
+        }
+    }
+}
+
 pub struct VertexArray {
     vertex_array: GLuint,
 }
@@ -78,6 +168,123 @@ pub unsafe trait BufferAttributes {
     unsafe fn vertex_attributes();
 }
 
+// The GLSL types that instance structs can be made of.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GlslType {
+    Float,
+    Int,
+    Uint,
+    Vec2,
+    Vec3,
+    Vec4,
+}
+
+impl GlslType {
+    pub fn name(self) -> &'static str {
+        match self {
+            GlslType::Float => "float",
+            GlslType::Int => "int",
+            GlslType::Uint => "uint",
+            GlslType::Vec2 => "vec2",
+            GlslType::Vec3 => "vec3",
+            GlslType::Vec4 => "vec4",
+        }
+    }
+
+    // The alignment and the size of the type in a std430 block.
+    pub fn std430_layout(self) -> (usize, usize) {
+        match self {
+            GlslType::Float | GlslType::Int | GlslType::Uint => (4, 4),
+            GlslType::Vec2 => (8, 8),
+            GlslType::Vec3 => (16, 12),
+            GlslType::Vec4 => (16, 16),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct InstanceField {
+    pub name: &'static str,
+    pub glsl_type: GlslType,
+    // Where the field is in the Rust struct, from `offset_of!`.
+    pub offset: usize,
+}
+
+// Per-instance data that gets uploaded to a shader storage buffer, the same
+// way `BufferAttributes` describes a vertex. `FIELDS` is what the shader
+// sees, and `glsl_declaration` writes the matching GLSL struct, so that the
+// two can't drift apart.
+//
+// Safety: `FIELDS` must describe the actual fields of the Rust struct, and
+// the struct has to be laid out the way std430 lays out the GLSL struct (see
+// `std430_mismatch`), or the shader reads garbage.
+//
+// Clippy only looks for that in doc comments, which this crate doesn't use.
+#[allow(clippy::missing_safety_doc)]
+pub unsafe trait InstanceData: Copy {
+    const GLSL_NAME: &'static str;
+    const FIELDS: &'static [InstanceField];
+
+    fn glsl_declaration() -> String {
+        let fields: String = Self::FIELDS
+            .iter()
+            .map(|field| format!("    {} {};\n", field.glsl_type.name(), field.name))
+            .collect();
+
+        format!("struct {} {{\n{}}};\n", Self::GLSL_NAME, fields)
+    }
+}
+
+// The offsets of the members of a std430 struct, followed by its array
+// stride.
+pub fn std430_offsets(members: &[GlslType]) -> (Vec<usize>, usize) {
+    let mut offsets = Vec::new();
+    let mut end: usize = 0;
+    let mut struct_alignment: usize = 1;
+
+    for member in members {
+        let (alignment, size) = member.std430_layout();
+        let offset = end.next_multiple_of(alignment);
+
+        offsets.push(offset);
+        end = offset + size;
+        struct_alignment = struct_alignment.max(alignment);
+    }
+
+    (offsets, end.next_multiple_of(struct_alignment))
+}
+
+// Describes where the Rust layout of `I` differs from the std430 one, if it
+// does at all.
+pub fn std430_mismatch<I: InstanceData>() -> Option<String> {
+    let types: Vec<GlslType> = I::FIELDS.iter().map(|field| field.glsl_type).collect();
+    let (offsets, stride) = std430_offsets(&types);
+
+    for (field, expected) in I::FIELDS.iter().zip(offsets) {
+        if field.offset != expected {
+            return Some(format!(
+                "{}.{} is at byte {}, but std430 puts it at byte {}",
+                I::GLSL_NAME,
+                field.name,
+                field.offset,
+                expected
+            ));
+        }
+    }
+
+    let size = std::mem::size_of::<I>();
+    if size != stride {
+        return Some(format!(
+            "{} is {} bytes long, but std430 arrays of it have a stride of {}",
+            I::GLSL_NAME,
+            size,
+            stride
+        ));
+    }
+
+    None
+}
+
 impl VertexArray {
     pub fn new() -> VertexArray {
         unsafe {
@@ -114,3 +321,25 @@ impl Drop for VertexArray {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{std430_offsets, GlslType};
+
+    #[test]
+    fn std430_rules() {
+        use GlslType::*;
+
+        // A lone vec3 array has a 16 byte stride, which is exactly what
+        // tightly packed `[f32; 3]`s get wrong.
+        assert_eq!(std430_offsets(&[Vec3]), (vec![0], 16));
+        // A float can tuck into the vec3's padding...
+        assert_eq!(std430_offsets(&[Vec3, Float]), (vec![0, 12], 16));
+        // ...but a vec2 can't.
+        assert_eq!(std430_offsets(&[Vec3, Vec2]), (vec![0, 16], 32));
+        assert_eq!(std430_offsets(&[Float, Vec3]), (vec![0, 16], 32));
+        // Unlike std140, scalar-only structs are not rounded up to 16 bytes.
+        assert_eq!(std430_offsets(&[Float, Uint, Int]), (vec![0, 4, 8], 12));
+        assert_eq!(std430_offsets(&[Vec2, Float]), (vec![0, 8], 16));
+    }
+}
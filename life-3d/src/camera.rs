@@ -1,4 +1,4 @@
-use crate::math::Mat4;
+use crate::math::{Mat4, Quaternion, Vec2};
 
 use super::math::Vec3;
 
@@ -42,11 +42,42 @@ impl Camera {
         }
     }
 
+    pub fn position(&self) -> Vec3 {
+        self.position
+    }
+
+    pub fn front(&self) -> Vec3 {
+        self.front
+    }
+
+    // Moves the camera along its own axes: `direction.x` goes along `right`,
+    // `direction.y` along `up` and `direction.z` along `front`.
     pub fn move_relative(&mut self, direction: Vec3) {
-        self.position = self.position.clone() + direction * self.front.clone();
+        self.position = self.position
+            + self.right * direction.x
+            + self.up * direction.y
+            + self.front * direction.z;
     }
 }
 
+// The direction that a yaw and pitch (in degrees) point towards, shared by
+// the orbiting and flying cameras.
+fn direction_from_angles(yaw: f32, pitch: f32) -> Vec3 {
+    Vec3 {
+        x: yaw.to_radians().cos() * pitch.to_radians().cos(),
+        y: pitch.to_radians().sin(),
+        z: yaw.to_radians().sin() * pitch.to_radians().cos(),
+    }
+}
+
+fn angles_from_direction(direction: Vec3) -> (f32, f32) {
+    let direction = direction.normalize();
+    let yaw = direction.z.atan2(direction.x).to_degrees();
+    let pitch = direction.y.clamp(-1.0, 1.0).asin().to_degrees();
+
+    (yaw, pitch.clamp(-89.0, 89.0))
+}
+
 pub struct ThirdPersonCamera {
     camera: Camera,
     target: Vec3,
@@ -79,6 +110,42 @@ impl ThirdPersonCamera {
         self.camera.view_matrix()
     }
 
+    pub fn target(&self) -> Vec3 {
+        self.target
+    }
+
+    pub fn distance(&self) -> f32 {
+        self.distance
+    }
+
+    pub fn yaw(&self) -> f32 {
+        self.yaw
+    }
+
+    pub fn pitch(&self) -> f32 {
+        self.pitch
+    }
+
+    pub fn position(&self) -> Vec3 {
+        self.camera.position
+    }
+
+    // A flying camera at the same spot, looking the same way.
+    pub fn to_fly(&self) -> FlyCamera {
+        FlyCamera::new(self.camera.position, self.yaw + 180.0, -self.pitch)
+    }
+
+    // An arcball camera showing exactly the same view as this one.
+    pub fn to_arcball(&self) -> ArcballCamera {
+        // Start from the arcball's resting position (looking down -Z) and
+        // swing it up by the pitch, then around by the yaw.
+        let orientation =
+            Quaternion::new(&Vec3::new(0.0, 1.0, 0.0), (90.0 - self.yaw).to_radians())
+                * Quaternion::new(&Vec3::new(1.0, 0.0, 0.0), -self.pitch.to_radians());
+
+        ArcballCamera::with_orientation(self.target, self.distance, orientation)
+    }
+
     pub fn rotate_camera(&mut self, yaw: f32, pitch: f32) {
         self.yaw += yaw;
         self.pitch += pitch;
@@ -86,13 +153,33 @@ impl ThirdPersonCamera {
 
         self.update_vectors();
     }
-    
+
     pub fn move_camera(&mut self, distance: f32) {
         self.distance += distance;
 
         self.update_vectors();
     }
 
+    pub fn set_target(&mut self, target: Vec3) {
+        self.target = target;
+
+        self.update_vectors();
+    }
+
+    pub fn set_distance(&mut self, distance: f32) {
+        self.distance = distance;
+
+        self.update_vectors();
+    }
+
+    // Slides the target (and the camera with it) across the screen, `right`
+    // and `up` world units along the camera's own axes.
+    pub fn pan(&mut self, right: f32, up: f32) {
+        self.target = self.target + self.camera.right * right + self.camera.up * up;
+
+        self.update_vectors();
+    }
+
     fn update_vectors(&mut self) {
         self.camera.position = (Vec3 {
             x: self.yaw.to_radians().cos() * self.pitch.to_radians().cos(),
@@ -105,3 +192,711 @@ impl ThirdPersonCamera {
         self.camera.update_up_and_right();
     }
 }
+
+// How long it takes for the arcball to swing to a new orientation, in
+// seconds.
+const ARCBALL_TRANSITION_TIME: f32 = 0.5;
+
+struct Transition {
+    from: Quaternion,
+    to: Quaternion,
+    elapsed: f32,
+    duration: f32,
+}
+
+// A camera that orbits `target` with no restrictions on its orientation, so
+// it can look straight down or roll around a structure. Mouse drags are
+// mapped onto a virtual sphere (Shoemake's arcball with Holroyd's
+// hyperbolic sheet), so dragging always feels like grabbing the scene.
+pub struct ArcballCamera {
+    target: Vec3,
+    distance: f32,
+    // Rotates the camera's local axes into world space. With the identity,
+    // the camera sits on +Z looking down -Z.
+    orientation: Quaternion,
+    transition: Option<Transition>,
+}
+
+impl ArcballCamera {
+    pub fn new(target: Vec3, distance: f32) -> ArcballCamera {
+        ArcballCamera::with_orientation(target, distance, Quaternion::identity())
+    }
+
+    pub fn with_orientation(target: Vec3, distance: f32, orientation: Quaternion) -> ArcballCamera {
+        ArcballCamera {
+            target,
+            distance,
+            orientation: orientation.normalize(),
+            transition: None,
+        }
+    }
+
+    pub fn view_matrix(&self) -> Mat4 {
+        let target = self.target;
+
+        Mat4::translate(0.0, 0.0, -self.distance)
+            * self.orientation.conjugate().to_rotation_matrix()
+            * Mat4::translate(-target.x, -target.y, -target.z)
+    }
+
+    pub fn position(&self) -> Vec3 {
+        self.target
+            + self
+                .orientation
+                .rotate_vector(&Vec3::new(0.0, 0.0, self.distance))
+    }
+
+    pub fn target(&self) -> Vec3 {
+        self.target
+    }
+
+    pub fn distance(&self) -> f32 {
+        self.distance
+    }
+
+    pub fn orientation(&self) -> Quaternion {
+        self.orientation
+    }
+
+    // Maps a point in normalized device coordinates onto the arcball.
+    fn project_onto_sphere(point: Vec2) -> Vec3 {
+        let length_squared = point.x * point.x + point.y * point.y;
+
+        // Inside the ball we use the sphere itself, outside of it a
+        // hyperbolic sheet, so dragging past the edge keeps rotating smoothly.
+        let z = if length_squared <= 0.5 {
+            (1.0 - length_squared).sqrt()
+        } else {
+            0.5 / length_squared.sqrt()
+        };
+
+        Vec3::new(point.x, point.y, z).normalize()
+    }
+
+    // The rotation, in view space, of dragging the scene from `from` to `to`
+    // (both in normalized device coordinates) across the arcball.
+    pub fn drag_rotation(from: Vec2, to: Vec2) -> Quaternion {
+        let from = Self::project_onto_sphere(from);
+        let to = Self::project_onto_sphere(to);
+
+        let axis = from.cross(&to);
+        if axis.len() < 1e-6 {
+            return Quaternion::identity();
+        }
+
+        let angle = from.dot(&to).clamp(-1.0, 1.0).acos();
+        Quaternion::new(&axis.normalize(), angle)
+    }
+
+    // Rotates the camera as if the mouse dragged the scene by `rotation`, as
+    // worked out by `drag_rotation`.
+    pub fn drag(&mut self, rotation: Quaternion) {
+        // The drag rotates the scene in view space, so the camera has to
+        // turn the opposite way.
+        self.transition = None;
+        self.orientation = (self.orientation * rotation.conjugate()).normalize();
+    }
+
+    pub fn move_camera(&mut self, distance: f32) {
+        self.distance += distance;
+    }
+
+    pub fn set_target(&mut self, target: Vec3) {
+        self.target = target;
+    }
+
+    pub fn set_distance(&mut self, distance: f32) {
+        self.distance = distance;
+    }
+
+    // Turns the camera around the target, about its own up and right axes,
+    // like `ThirdPersonCamera::rotate_camera` does about the world's.
+    pub fn rotate(&mut self, yaw: f32, pitch: f32) {
+        let yaw = Quaternion::new(&Vec3::new(0.0, 1.0, 0.0), -yaw.to_radians());
+        let pitch = Quaternion::new(&Vec3::new(1.0, 0.0, 0.0), -pitch.to_radians());
+
+        self.transition = None;
+        self.orientation = (self.orientation * yaw * pitch).normalize();
+    }
+
+    // Same as `ThirdPersonCamera::pan`.
+    pub fn pan(&mut self, right: f32, up: f32) {
+        let right = self.orientation.rotate_vector(&Vec3::new(right, 0.0, 0.0));
+        let up = self.orientation.rotate_vector(&Vec3::new(0.0, up, 0.0));
+
+        self.target = self.target + right + up;
+    }
+
+    // Smoothly swings the camera to `orientation` over the next few frames.
+    // `update` has to be called every frame for this to have an effect.
+    pub fn rotate_to(&mut self, orientation: Quaternion) {
+        self.transition = Some(Transition {
+            from: self.orientation,
+            to: orientation.normalize(),
+            elapsed: 0.0,
+            duration: ARCBALL_TRANSITION_TIME,
+        });
+    }
+
+    // Swings the camera to look straight down at the target.
+    pub fn look_from_above(&mut self) {
+        self.rotate_to(Quaternion::new(
+            &Vec3::new(1.0, 0.0, 0.0),
+            -std::f32::consts::FRAC_PI_2,
+        ));
+    }
+
+    pub fn update(&mut self, delta_time: f32) {
+        if let Some(transition) = &mut self.transition {
+            transition.elapsed += delta_time;
+
+            let t = (transition.elapsed / transition.duration).min(1.0);
+            // Ease in and out, so the camera does not jerk into motion.
+            let t = t * t * (3.0 - 2.0 * t);
+            self.orientation = transition.from.slerp(&transition.to, t);
+
+            if transition.elapsed >= transition.duration {
+                self.transition = None;
+            }
+        }
+    }
+
+    // A third person camera showing the same view as this one, as closely
+    // as it can. Any roll is lost, and the pitch gets clamped.
+    pub fn to_third_person(&self) -> ThirdPersonCamera {
+        let direction = self.orientation.rotate_vector(&Vec3::new(0.0, 0.0, 1.0));
+        let (yaw, pitch) = angles_from_direction(direction);
+
+        ThirdPersonCamera::new(self.target, self.distance, yaw, pitch)
+    }
+
+    // A flying camera at the same spot, looking at the target. Like with
+    // `to_third_person`, any roll is lost.
+    pub fn to_fly(&self) -> FlyCamera {
+        let position = self.position();
+        let (yaw, pitch) = angles_from_direction(self.target - position);
+
+        FlyCamera::new(position, yaw, pitch)
+    }
+}
+
+// How far away a camera with the given vertical field of view (in degrees)
+// needs to be for a sphere of `radius` to fit on the screen.
+pub fn fit_distance(radius: f32, fov: f32, aspect_ratio: f32) -> f32 {
+    let half_vertical = fov.to_radians() / 2.0;
+    let half_horizontal = (half_vertical.tan() * aspect_ratio).atan();
+
+    radius / half_vertical.min(half_horizontal).sin()
+}
+
+// Multipliers for the flying speed, while holding shift or control.
+pub const FLY_FAST_MULTIPLIER: f32 = 4.0;
+pub const FLY_SLOW_MULTIPLIER: f32 = 0.25;
+
+// A free camera for flying through the arena, looking around with a yaw and
+// a pitch (in degrees) like the third person camera.
+pub struct FlyCamera {
+    camera: Camera,
+    yaw: f32,
+    pitch: f32,
+    // Units per second.
+    speed: f32,
+}
+
+impl FlyCamera {
+    pub fn new(position: Vec3, yaw: f32, pitch: f32) -> FlyCamera {
+        let pitch = pitch.clamp(-89.0, 89.0);
+
+        FlyCamera {
+            camera: Camera::new(&position, &direction_from_angles(yaw, pitch)),
+            yaw,
+            pitch,
+            speed: 2.0,
+        }
+    }
+
+    pub fn view_matrix(&self) -> Mat4 {
+        self.camera.view_matrix()
+    }
+
+    pub fn position(&self) -> Vec3 {
+        self.camera.position()
+    }
+
+    pub fn front(&self) -> Vec3 {
+        self.camera.front()
+    }
+
+    pub fn yaw(&self) -> f32 {
+        self.yaw
+    }
+
+    pub fn pitch(&self) -> f32 {
+        self.pitch
+    }
+
+    pub fn speed(&self) -> f32 {
+        self.speed
+    }
+
+    pub fn set_speed(&mut self, speed: f32) {
+        self.speed = speed.max(0.0);
+    }
+
+    pub fn look(&mut self, yaw: f32, pitch: f32) {
+        self.yaw += yaw;
+        self.pitch = (self.pitch + pitch).clamp(-89.0, 89.0);
+
+        self.camera.front = direction_from_angles(self.yaw, self.pitch);
+        self.camera.update_up_and_right();
+    }
+
+    // Flies towards `direction` (right, up, front, as in
+    // `Camera::move_relative`) at the camera's speed times `multiplier`.
+    pub fn fly(&mut self, direction: Vec3, multiplier: f32, delta_time: f32) {
+        if direction.len() == 0.0 {
+            return;
+        }
+
+        self.camera
+            .move_relative(direction.normalize() * self.speed * multiplier * delta_time);
+    }
+
+    // An orbiting camera at the same spot, looking the same way, orbiting a
+    // target `distance` units in front of it.
+    pub fn to_third_person(&self, distance: f32) -> ThirdPersonCamera {
+        let target = self.position() + self.front() * distance;
+
+        ThirdPersonCamera::new(target, distance, self.yaw - 180.0, -self.pitch)
+    }
+}
+
+// The cameras the user can switch between, each keeping the view of the
+// previous one.
+pub enum ActiveCamera {
+    ThirdPerson(ThirdPersonCamera),
+    Arcball(ArcballCamera),
+    // Remembers how far away the orbit target was, to put it back there
+    // when switching back to orbiting.
+    Fly(FlyCamera, f32),
+}
+
+impl ActiveCamera {
+    pub fn view_matrix(&self) -> Mat4 {
+        match self {
+            ActiveCamera::ThirdPerson(camera) => camera.view_matrix(),
+            ActiveCamera::Arcball(camera) => camera.view_matrix(),
+            ActiveCamera::Fly(camera, _) => camera.view_matrix(),
+        }
+    }
+
+    pub fn position(&self) -> Vec3 {
+        match self {
+            ActiveCamera::ThirdPerson(camera) => camera.position(),
+            ActiveCamera::Arcball(camera) => camera.position(),
+            ActiveCamera::Fly(camera, _) => camera.position(),
+        }
+    }
+
+    // Zooms the orbiting cameras, and flies the flying one forwards (for
+    // negative distances) or backwards.
+    pub fn move_camera(&mut self, distance: f32) {
+        match self {
+            ActiveCamera::ThirdPerson(camera) => camera.move_camera(distance),
+            ActiveCamera::Arcball(camera) => camera.move_camera(distance),
+            ActiveCamera::Fly(camera, _) => {
+                camera.camera.move_relative(Vec3::new(0.0, 0.0, -distance))
+            }
+        }
+    }
+
+    // Orbits the orbiting cameras, and turns the flying one's head. Both
+    // angles are in degrees.
+    pub fn rotate(&mut self, yaw: f32, pitch: f32) {
+        match self {
+            ActiveCamera::ThirdPerson(camera) => camera.rotate_camera(yaw, pitch),
+            ActiveCamera::Arcball(camera) => camera.rotate(yaw, pitch),
+            ActiveCamera::Fly(camera, _) => camera.look(yaw, -pitch),
+        }
+    }
+
+    // Moves an orbiting camera closer or further away. A flying camera
+    // flies forwards or backwards by the difference instead.
+    pub fn set_distance(&mut self, distance: f32) {
+        match self {
+            ActiveCamera::ThirdPerson(camera) => camera.set_distance(distance),
+            ActiveCamera::Arcball(camera) => camera.set_distance(distance),
+            ActiveCamera::Fly(camera, previous) => {
+                camera
+                    .camera
+                    .move_relative(Vec3::new(0.0, 0.0, *previous - distance));
+                *previous = distance;
+            }
+        }
+    }
+
+    // Pans the orbiting cameras, and strafes the flying one.
+    pub fn pan(&mut self, right: f32, up: f32) {
+        match self {
+            ActiveCamera::ThirdPerson(camera) => camera.pan(right, up),
+            ActiveCamera::Arcball(camera) => camera.pan(right, up),
+            ActiveCamera::Fly(camera, _) => camera.camera.move_relative(Vec3::new(right, up, 0.0)),
+        }
+    }
+
+    // How far the camera is from the point it orbits, or would orbit if it
+    // stopped flying.
+    pub fn distance(&self) -> f32 {
+        match self {
+            ActiveCamera::ThirdPerson(camera) => camera.distance(),
+            ActiveCamera::Arcball(camera) => camera.distance(),
+            ActiveCamera::Fly(_, distance) => *distance,
+        }
+    }
+
+    // Orbits around `target` from `distance` away, keeping the direction the
+    // camera looks in. A flying camera goes back to orbiting.
+    pub fn focus_on(&mut self, target: Vec3, distance: f32) {
+        if self.is_flying() {
+            self.toggle_fly();
+        }
+
+        match self {
+            ActiveCamera::ThirdPerson(camera) => {
+                camera.set_target(target);
+                camera.set_distance(distance);
+            }
+            ActiveCamera::Arcball(camera) => {
+                camera.set_target(target);
+                camera.set_distance(distance);
+            }
+            ActiveCamera::Fly(..) => unreachable!(),
+        }
+    }
+
+    // Frames the box between `min` and `max` so that it fits on screen.
+    // `fov` is the vertical field of view in degrees, as in
+    // `Mat4::perspective`.
+    pub fn zoom_to_fit(&mut self, min: Vec3, max: Vec3, fov: f32, aspect_ratio: f32) {
+        let center = (min + max) / 2.0;
+        let radius = (max - min).len() / 2.0;
+
+        self.focus_on(center, fit_distance(radius, fov, aspect_ratio));
+    }
+
+    pub fn is_flying(&self) -> bool {
+        matches!(self, ActiveCamera::Fly(..))
+    }
+
+    pub fn update(&mut self, delta_time: f32) {
+        if let ActiveCamera::Arcball(camera) = self {
+            camera.update(delta_time);
+        }
+    }
+
+    pub fn toggle_arcball(&mut self) {
+        *self = match self {
+            ActiveCamera::ThirdPerson(camera) => ActiveCamera::Arcball(camera.to_arcball()),
+            ActiveCamera::Arcball(camera) => ActiveCamera::ThirdPerson(camera.to_third_person()),
+            ActiveCamera::Fly(camera, distance) => {
+                ActiveCamera::Arcball(camera.to_third_person(*distance).to_arcball())
+            }
+        };
+    }
+
+    // An arcball camera with the same view, which can represent all of the
+    // others.
+    pub fn to_arcball(&self) -> ArcballCamera {
+        match self {
+            ActiveCamera::ThirdPerson(camera) => camera.to_arcball(),
+            ActiveCamera::Arcball(camera) => {
+                ArcballCamera::with_orientation(camera.target, camera.distance, camera.orientation)
+            }
+            ActiveCamera::Fly(camera, distance) => camera.to_third_person(*distance).to_arcball(),
+        }
+    }
+
+    // Switches between flying and orbiting, keeping the camera where it is.
+    pub fn toggle_fly(&mut self) {
+        *self = match self {
+            ActiveCamera::ThirdPerson(camera) => {
+                ActiveCamera::Fly(camera.to_fly(), camera.distance())
+            }
+            ActiveCamera::Arcball(camera) => ActiveCamera::Fly(camera.to_fly(), camera.distance()),
+            ActiveCamera::Fly(camera, distance) => {
+                ActiveCamera::ThirdPerson(camera.to_third_person(*distance))
+            }
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{fit_distance, ActiveCamera, ArcballCamera, FlyCamera, ThirdPersonCamera};
+    use crate::math::{Mat4, Quaternion, Vec2, Vec3, Vec4};
+
+    fn approx_eq(a: &Mat4, b: &Mat4) -> bool {
+        let a = a.data.iter().flatten();
+        let b = b.data.iter().flatten();
+        a.zip(b).all(|(a, b)| (a - b).abs() < 1e-3)
+    }
+
+    fn approx_eq_vec3(a: Vec3, b: Vec3) -> bool {
+        (a - b).len() < 1e-3
+    }
+
+    #[test]
+    fn arcball_matches_third_person_view() {
+        for (yaw, pitch) in [(0.0, 0.0), (45.0, 30.0), (-120.0, -60.0), (200.0, 85.0)] {
+            let third_person = ThirdPersonCamera::new(Vec3::new(1.0, 2.0, 3.0), 5.0, yaw, pitch);
+            let arcball = third_person.to_arcball();
+
+            assert!(approx_eq(
+                &third_person.view_matrix(),
+                &arcball.view_matrix()
+            ));
+
+            let back = arcball.to_third_person();
+            assert!(approx_eq(&third_person.view_matrix(), &back.view_matrix()));
+        }
+    }
+
+    #[test]
+    fn arcball_view_matrix() {
+        let camera = ArcballCamera::new(Vec3::new(0.0, 0.0, 0.0), 5.0);
+
+        assert!(approx_eq_vec3(camera.position(), Vec3::new(0.0, 0.0, 5.0)));
+
+        // The target ends up straight in front of the camera.
+        let target = camera.view_matrix() * Vec4::new(0.0, 0.0, 0.0, 1.0);
+        assert!(approx_eq_vec3(target.xyz(), Vec3::new(0.0, 0.0, -5.0)));
+    }
+
+    #[test]
+    fn arcball_can_look_straight_down() {
+        let mut camera = ArcballCamera::new(Vec3::new(0.0, 0.0, 0.0), 5.0);
+        camera.look_from_above();
+        camera.update(10.0);
+
+        assert!(approx_eq_vec3(camera.position(), Vec3::new(0.0, 5.0, 0.0)));
+
+        // Keep going over the top, which the third person camera can't do.
+        camera.drag(ArcballCamera::drag_rotation(
+            Vec2::new(0.0, 0.0),
+            Vec2::new(0.0, -0.5),
+        ));
+        assert!(camera.position().z < 0.0);
+        assert!(camera.position().y > 0.0);
+    }
+
+    #[test]
+    fn arcball_drag_orbits_the_camera() {
+        let mut camera = ArcballCamera::new(Vec3::new(0.0, 0.0, 0.0), 5.0);
+
+        // Dragging the scene to the right moves the camera to the left.
+        camera.drag(ArcballCamera::drag_rotation(
+            Vec2::new(0.0, 0.0),
+            Vec2::new(0.5, 0.0),
+        ));
+        assert!(camera.position().x < 0.0);
+        assert!((camera.position().len() - 5.0).abs() < 1e-3);
+
+        // Dragging back undoes it.
+        camera.drag(ArcballCamera::drag_rotation(
+            Vec2::new(0.5, 0.0),
+            Vec2::new(0.0, 0.0),
+        ));
+        assert!(approx_eq_vec3(camera.position(), Vec3::new(0.0, 0.0, 5.0)));
+    }
+
+    #[test]
+    fn arcball_transition_is_smooth() {
+        let mut camera = ArcballCamera::new(Vec3::new(0.0, 0.0, 0.0), 5.0);
+        camera.look_from_above();
+
+        let mut previous = camera.position();
+        for _ in 0..40 {
+            camera.update(0.025);
+            let current = camera.position();
+
+            // A quarter turn at distance 5 is about 7.9 units long, spread
+            // over 20 frames of the transition.
+            assert!((current - previous).len() < 0.7);
+            assert!((current.len() - 5.0).abs() < 1e-3);
+            previous = current;
+        }
+
+        assert!(approx_eq_vec3(camera.position(), Vec3::new(0.0, 5.0, 0.0)));
+    }
+
+    #[test]
+    fn switching_to_fly_keeps_the_view() {
+        let mut camera = ActiveCamera::ThirdPerson(ThirdPersonCamera::new(
+            Vec3::new(1.0, 0.0, -2.0),
+            4.0,
+            30.0,
+            20.0,
+        ));
+        let view = camera.view_matrix();
+
+        camera.toggle_fly();
+        assert!(camera.is_flying());
+        assert!(approx_eq(&camera.view_matrix(), &view));
+
+        camera.toggle_fly();
+        assert!(!camera.is_flying());
+        assert!(approx_eq(&camera.view_matrix(), &view));
+
+        camera.toggle_arcball();
+        camera.toggle_fly();
+        assert!(approx_eq(&camera.view_matrix(), &view));
+    }
+
+    #[test]
+    fn flying_moves_relative_to_the_view() {
+        // Looking down -Z, so right is +X.
+        let mut camera = FlyCamera::new(Vec3::new(0.0, 0.0, 0.0), -90.0, 0.0);
+        camera.set_speed(2.0);
+
+        camera.fly(Vec3::new(0.0, 0.0, 1.0), 1.0, 0.5);
+        assert!(approx_eq_vec3(camera.position(), Vec3::new(0.0, 0.0, -1.0)));
+
+        camera.fly(Vec3::new(1.0, 0.0, 0.0), 4.0, 0.5);
+        assert!(approx_eq_vec3(camera.position(), Vec3::new(4.0, 0.0, -1.0)));
+
+        camera.fly(Vec3::new(0.0, -1.0, 0.0), 0.25, 1.0);
+        assert!(approx_eq_vec3(
+            camera.position(),
+            Vec3::new(4.0, -0.5, -1.0)
+        ));
+
+        // Moving diagonally is not faster than moving straight.
+        let before = camera.position();
+        camera.fly(Vec3::new(1.0, 0.0, 1.0), 1.0, 1.0);
+        assert!(((camera.position() - before).len() - 2.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn fly_camera_pitch_is_clamped() {
+        let mut camera = FlyCamera::new(Vec3::new(0.0, 0.0, 0.0), 0.0, 0.0);
+        camera.look(10.0, 200.0);
+
+        assert_eq!(camera.pitch(), 89.0);
+        assert_eq!(camera.yaw(), 10.0);
+        assert!(camera.front().y > 0.99);
+    }
+
+    #[test]
+    fn panning_moves_the_target_across_the_screen() {
+        let mut third_person = ThirdPersonCamera::new(Vec3::new(0.0, 0.0, 0.0), 5.0, 90.0, 0.0);
+        let mut arcball = third_person.to_arcball();
+
+        // The camera sits on +Z looking down -Z, so right is +X.
+        third_person.pan(1.0, 2.0);
+        arcball.pan(1.0, 2.0);
+
+        assert!(approx_eq_vec3(
+            third_person.target(),
+            Vec3::new(1.0, 2.0, 0.0)
+        ));
+        assert!(approx_eq_vec3(arcball.target(), Vec3::new(1.0, 2.0, 0.0)));
+        assert!(approx_eq(
+            &third_person.view_matrix(),
+            &arcball.view_matrix()
+        ));
+        assert!(approx_eq_vec3(
+            third_person.position(),
+            Vec3::new(1.0, 2.0, 5.0)
+        ));
+    }
+
+    #[test]
+    fn zoom_to_fit_frames_the_box() {
+        let mut camera = ActiveCamera::ThirdPerson(ThirdPersonCamera::new(
+            Vec3::new(0.0, 0.0, 0.0),
+            5.0,
+            0.0,
+            0.0,
+        ));
+        camera.toggle_fly();
+
+        let min = Vec3::new(1.0, 1.0, 1.0);
+        let max = Vec3::new(3.0, 3.0, 3.0);
+        camera.zoom_to_fit(min, max, 45.0, 16.0 / 9.0);
+
+        let ActiveCamera::ThirdPerson(orbit) = &camera else {
+            panic!("zooming to fit should stop flying");
+        };
+        assert!(approx_eq_vec3(orbit.target(), Vec3::new(2.0, 2.0, 2.0)));
+
+        // Every corner of the box ends up inside the view frustum.
+        let projection = Mat4::perspective(16.0 / 9.0, 0.1, 100.0, 45.0);
+        for corner in 0..8 {
+            let pick = |bit: i32, axis_min: f32, axis_max: f32| {
+                if corner & bit == 0 {
+                    axis_min
+                } else {
+                    axis_max
+                }
+            };
+            let corner = Vec4::new(
+                pick(1, min.x, max.x),
+                pick(2, min.y, max.y),
+                pick(4, min.z, max.z),
+                1.0,
+            );
+            let clip = projection * (camera.view_matrix() * corner);
+            let ndc = clip.perspective_divide();
+
+            assert!(ndc.x.abs() <= 1.0 && ndc.y.abs() <= 1.0, "{:?}", ndc);
+        }
+    }
+
+    #[test]
+    fn fit_distance_uses_the_narrowest_field_of_view() {
+        let wide = fit_distance(1.0, 90.0, 2.0);
+        assert!((wide - 2.0f32.sqrt()).abs() < 1e-3);
+
+        // When the window is taller than it is wide, the horizontal field of
+        // view is the one that limits.
+        let tall = fit_distance(1.0, 90.0, 0.5);
+        assert!(tall > wide);
+    }
+
+    #[test]
+    fn arcball_rotation_matches_third_person() {
+        let mut third_person = ThirdPersonCamera::new(Vec3::new(0.0, 0.0, 0.0), 5.0, 90.0, 0.0);
+        let mut arcball = third_person.to_arcball();
+
+        third_person.rotate_camera(25.0, 0.0);
+        arcball.rotate(25.0, 0.0);
+        assert!(approx_eq(
+            &third_person.view_matrix(),
+            &arcball.view_matrix()
+        ));
+
+        third_person.rotate_camera(0.0, 40.0);
+        arcball.rotate(0.0, 40.0);
+        assert!(approx_eq(
+            &third_person.view_matrix(),
+            &arcball.view_matrix()
+        ));
+    }
+
+    #[test]
+    fn slerp_endpoints_and_midpoint() {
+        let axis = Vec3::new(0.0, 1.0, 0.0);
+        let from = Quaternion::identity();
+        let to = Quaternion::new(&axis, 1.0);
+
+        assert_eq!(from.slerp(&to, 0.0), from);
+        assert!((from.slerp(&to, 1.0).dot(&to) - 1.0).abs() < 1e-5);
+        assert!((from.slerp(&to, 0.5).dot(&Quaternion::new(&axis, 0.5)) - 1.0).abs() < 1e-5);
+
+        // -to is the same rotation as to, and slerp must not take the long
+        // way around to get there.
+        let negated = Quaternion::new(&axis, 1.0 - 2.0 * std::f32::consts::PI);
+        let halfway = from.slerp(&negated, 0.5);
+        assert!((halfway.dot(&Quaternion::new(&axis, 0.5)).abs() - 1.0).abs() < 1e-5);
+    }
+}
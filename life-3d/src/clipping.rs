@@ -0,0 +1,280 @@
+// Hides parts of the arena so that what is inside can be seen: a clipping
+// plane for each axis, and a slice mode that only shows a single layer, with
+// the layers next to it as faint ghosts. The cell under the cursor is hidden
+// too, as the cursor gets drawn in its place.
+
+use crate::game::DEFAULT_ARENA_SIZE;
+
+// How many layers above and below the slice are drawn as ghosts.
+pub const GHOST_LAYERS: usize = 2;
+pub const GHOST_OPACITY: f32 = 0.15;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClipSide {
+    // Hides the cells past the plane along the axis.
+    Above,
+    // Hides the cells before the plane.
+    Below,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClipPlane {
+    // The last layer that is still shown.
+    pub position: usize,
+    pub hides: ClipSide,
+}
+
+impl ClipPlane {
+    fn hides(&self, coordinate: usize) -> bool {
+        match self.hides {
+            ClipSide::Above => coordinate > self.position,
+            ClipSide::Below => coordinate < self.position,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Slice {
+    pub layer: usize,
+    // Until the slice is stepped by hand, it stays on the cursor's layer.
+    pub follows_cursor: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct Clipping {
+    // How many layers there are along each axis.
+    arena_size: usize,
+    // One for each of x, y and z.
+    planes: [Option<ClipPlane>; 3],
+    slice: Option<Slice>,
+    // The cell the cursor is on.
+    hidden_cell: Option<[usize; 3]>,
+    // The plane that was changed last, which is the one `step` moves.
+    selected_axis: usize,
+    // Goes up with every change, so that renderers know to rebuild.
+    revision: u64,
+}
+
+impl Default for Clipping {
+    fn default() -> Clipping {
+        Clipping::new(DEFAULT_ARENA_SIZE)
+    }
+}
+
+impl Clipping {
+    // Nothing clipped, in an arena `arena_size` cells across.
+    pub fn new(arena_size: usize) -> Clipping {
+        Clipping {
+            arena_size,
+            planes: [None; 3],
+            slice: None,
+            hidden_cell: None,
+            selected_axis: 0,
+            revision: 0,
+        }
+    }
+
+    fn step_layer(&self, layer: usize, delta: i32) -> usize {
+        (layer as i32 + delta).clamp(0, self.arena_size as i32 - 1) as usize
+    }
+
+    pub fn revision(&self) -> u64 {
+        self.revision
+    }
+
+    pub fn plane(&self, axis: usize) -> Option<ClipPlane> {
+        self.planes[axis]
+    }
+
+    pub fn slice(&self) -> Option<Slice> {
+        self.slice
+    }
+
+    // Goes from no plane, to a plane at `position` hiding everything above
+    // it, to one hiding everything below it, and back to no plane.
+    pub fn cycle_plane(&mut self, axis: usize, position: usize) {
+        self.planes[axis] = match self.planes[axis] {
+            None => Some(ClipPlane {
+                position,
+                hides: ClipSide::Above,
+            }),
+            Some(ClipPlane {
+                position,
+                hides: ClipSide::Above,
+            }) => Some(ClipPlane {
+                position,
+                hides: ClipSide::Below,
+            }),
+            Some(ClipPlane {
+                hides: ClipSide::Below,
+                ..
+            }) => None,
+        };
+
+        self.selected_axis = axis;
+        self.revision += 1;
+    }
+
+    pub fn toggle_slice(&mut self, cursor_y: usize) {
+        self.slice = match self.slice {
+            Some(_) => None,
+            None => Some(Slice {
+                layer: cursor_y,
+                follows_cursor: true,
+            }),
+        };
+
+        self.revision += 1;
+    }
+
+    // Moves the slice in slice mode, or else the plane that was changed
+    // last, by `delta` layers.
+    pub fn step(&mut self, delta: i32) {
+        if let Some(mut slice) = self.slice {
+            slice.layer = self.step_layer(slice.layer, delta);
+            slice.follows_cursor = false;
+            self.slice = Some(slice);
+        } else if let Some(mut plane) = self.planes[self.selected_axis] {
+            plane.position = self.step_layer(plane.position, delta);
+            self.planes[self.selected_axis] = Some(plane);
+        } else {
+            return;
+        }
+
+        self.revision += 1;
+    }
+
+    // Keeps the slice on the cursor's layer, unless it was moved by hand.
+    pub fn follow_cursor(&mut self, cursor_y: usize) {
+        if let Some(slice) = &mut self.slice {
+            if slice.follows_cursor && slice.layer != cursor_y {
+                slice.layer = cursor_y;
+                self.revision += 1;
+            }
+        }
+    }
+
+    // Hides the cell at `cell` instead of whichever one was hidden before.
+    pub fn hide_cell(&mut self, cell: Option<[usize; 3]>) {
+        if self.hidden_cell != cell {
+            self.hidden_cell = cell;
+            self.revision += 1;
+        }
+    }
+
+    fn is_clipped(&self, position: [usize; 3]) -> bool {
+        self.hidden_cell == Some(position)
+            || self
+                .planes
+                .iter()
+                .zip(position)
+                .any(|(plane, coordinate)| plane.is_some_and(|plane| plane.hides(coordinate)))
+    }
+
+    // Whether a cell at the coordinates is drawn normally.
+    pub fn is_visible(&self, x: usize, y: usize, z: usize) -> bool {
+        !self.is_clipped([x, y, z]) && self.slice.is_none_or(|slice| slice.layer == y)
+    }
+
+    // Whether a cell at the coordinates is drawn as a ghost, around the
+    // slice.
+    pub fn is_ghost(&self, x: usize, y: usize, z: usize) -> bool {
+        !self.is_clipped([x, y, z])
+            && self
+                .slice
+                .is_some_and(|slice| slice.layer != y && slice.layer.abs_diff(y) <= GHOST_LAYERS)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ClipSide, Clipping, GHOST_LAYERS};
+
+    #[test]
+    fn planes_hide_one_side() {
+        let mut clipping = Clipping::default();
+        assert!(clipping.is_visible(100, 100, 100));
+
+        clipping.cycle_plane(0, 10);
+        assert_eq!(clipping.plane(0).unwrap().hides, ClipSide::Above);
+        assert!(clipping.is_visible(10, 50, 50));
+        assert!(!clipping.is_visible(11, 50, 50));
+
+        clipping.cycle_plane(0, 99);
+        assert_eq!(clipping.plane(0).unwrap().position, 10);
+        assert!(clipping.is_visible(11, 50, 50));
+        assert!(!clipping.is_visible(9, 50, 50));
+
+        clipping.step(-5);
+        assert!(clipping.is_visible(5, 50, 50));
+
+        clipping.cycle_plane(2, 20);
+        assert!(!clipping.is_visible(50, 50, 21));
+        assert!(!clipping.is_visible(0, 50, 0));
+
+        clipping.cycle_plane(0, 0);
+        assert_eq!(clipping.plane(0), None);
+        assert!(clipping.is_visible(0, 50, 0));
+    }
+
+    #[test]
+    fn slice_and_ghosts() {
+        let mut clipping = Clipping::default();
+        assert!(!clipping.is_ghost(0, 0, 0));
+
+        clipping.toggle_slice(40);
+        assert!(clipping.is_visible(3, 40, 3));
+        assert!(!clipping.is_visible(3, 41, 3));
+        assert!(!clipping.is_ghost(3, 40, 3));
+        assert!(clipping.is_ghost(3, 40 - GHOST_LAYERS, 3));
+        assert!(!clipping.is_ghost(3, 40 + GHOST_LAYERS + 1, 3));
+
+        // Planes hide ghosts too.
+        clipping.cycle_plane(1, 40);
+        assert!(!clipping.is_ghost(3, 41, 3));
+        assert!(clipping.is_ghost(3, 39, 3));
+    }
+
+    #[test]
+    fn hidden_cell() {
+        let mut clipping = Clipping::default();
+        clipping.hide_cell(Some([3, 4, 5]));
+        assert!(!clipping.is_visible(3, 4, 5));
+        assert!(clipping.is_visible(3, 4, 6));
+
+        let revision = clipping.revision();
+        clipping.hide_cell(Some([3, 4, 5]));
+        assert_eq!(clipping.revision(), revision);
+
+        clipping.hide_cell(Some([3, 4, 6]));
+        assert!(clipping.is_visible(3, 4, 5));
+        assert!(clipping.revision() > revision);
+    }
+
+    #[test]
+    fn slice_follows_the_cursor_until_stepped() {
+        let mut clipping = Clipping::new(64);
+        clipping.toggle_slice(40);
+
+        let revision = clipping.revision();
+        clipping.follow_cursor(40);
+        assert_eq!(clipping.revision(), revision);
+
+        clipping.follow_cursor(42);
+        assert_eq!(clipping.slice().unwrap().layer, 42);
+        assert!(clipping.revision() > revision);
+
+        clipping.step(1);
+        clipping.follow_cursor(10);
+        assert_eq!(clipping.slice().unwrap().layer, 43);
+
+        clipping.step(1000);
+        assert_eq!(clipping.slice().unwrap().layer, 63);
+
+        // Turning it back on puts it back on the cursor.
+        clipping.toggle_slice(10);
+        clipping.toggle_slice(10);
+        assert_eq!(clipping.slice().unwrap().layer, 10);
+        assert!(clipping.slice().unwrap().follows_cursor);
+    }
+}
@@ -0,0 +1,228 @@
+// The ways cells can be coloured, and the palette of colours each of them
+// uses. The palette can be changed in a file next to the bookmarks.
+
+use std::{fmt, fs, io, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+use crate::game::GameOfLife;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ColorMode {
+    // Every cell the same colour.
+    Plain,
+    // How many generations the cell has been alive for.
+    Age,
+    // How many live neighbours the cell has right now.
+    Neighbours,
+    // How high up the cell is.
+    Layer,
+    // Whether the cell was born this generation, or survived from the last.
+    Births,
+}
+
+impl ColorMode {
+    pub fn next(self) -> ColorMode {
+        match self {
+            ColorMode::Plain => ColorMode::Age,
+            ColorMode::Age => ColorMode::Neighbours,
+            ColorMode::Neighbours => ColorMode::Layer,
+            ColorMode::Layer => ColorMode::Births,
+            ColorMode::Births => ColorMode::Plain,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum PaletteError {
+    Io(io::Error),
+    Parse(toml::de::Error),
+}
+
+impl fmt::Display for PaletteError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PaletteError::Io(error) => write!(f, "{}", error),
+            PaletteError::Parse(error) => write!(f, "invalid palette file: {}", error),
+        }
+    }
+}
+
+impl std::error::Error for PaletteError {}
+
+impl From<io::Error> for PaletteError {
+    fn from(error: io::Error) -> Self {
+        PaletteError::Io(error)
+    }
+}
+
+// Red, green and blue, from 0 to 1.
+pub type Color = [f32; 3];
+
+// Cells with this many neighbours or more get `many_neighbours`.
+const MANY_NEIGHBOURS: u32 = 8;
+
+// How many steps cells take to fade from `young` to `old`. A smooth fade
+// would recolour every young cell every generation, which would keep the
+// mesher rebuilding their chunks and stop it merging cells of nearly the
+// same age.
+pub const AGE_BANDS: u32 = 4;
+
+// Anything left out of the palette file keeps its default.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Palette {
+    pub mode: ColorMode,
+    pub plain: Color,
+    // Cells fade from `young` to `old` over `old_age` generations, in
+    // `AGE_BANDS` steps.
+    pub young: Color,
+    pub old: Color,
+    pub old_age: u16,
+    pub few_neighbours: Color,
+    pub many_neighbours: Color,
+    pub bottom: Color,
+    pub top: Color,
+    pub born: Color,
+    pub survived: Color,
+}
+
+impl Default for Palette {
+    fn default() -> Palette {
+        Palette {
+            mode: ColorMode::Age,
+            plain: [0.9, 0.9, 0.9],
+            young: [1.0, 0.85, 0.3],
+            old: [0.6, 0.15, 0.55],
+            old_age: 20,
+            few_neighbours: [0.2, 0.45, 1.0],
+            many_neighbours: [1.0, 0.25, 0.2],
+            bottom: [0.1, 0.6, 0.4],
+            top: [0.95, 0.95, 0.6],
+            born: [0.4, 1.0, 0.4],
+            survived: [0.35, 0.5, 0.9],
+        }
+    }
+}
+
+fn opaque([r, g, b]: Color) -> [f32; 4] {
+    [r, g, b, 1.0]
+}
+
+fn mix(from: Color, to: Color, t: f32) -> [f32; 4] {
+    let t = t.clamp(0.0, 1.0);
+    opaque([0, 1, 2].map(|i| from[i] + (to[i] - from[i]) * t))
+}
+
+impl Palette {
+    // Loads the palette from `path`, or the default palette if there is no
+    // such file.
+    pub fn load(path: &Path) -> Result<Palette, PaletteError> {
+        match fs::read_to_string(path) {
+            Ok(contents) => Palette::from_toml(&contents),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(Palette::default()),
+            Err(error) => Err(error.into()),
+        }
+    }
+
+    pub fn from_toml(contents: &str) -> Result<Palette, PaletteError> {
+        toml::from_str(contents).map_err(PaletteError::Parse)
+    }
+
+    // The colour of the live cell at the coordinates, in the current mode.
+    pub fn cell_color(&self, game: &GameOfLife, x: usize, y: usize, z: usize) -> [f32; 4] {
+        match self.mode {
+            ColorMode::Plain => opaque(self.plain),
+            ColorMode::Age => {
+                let band = game.age(x, y, z) as u32 * AGE_BANDS / self.old_age.max(1) as u32;
+                mix(self.young, self.old, band as f32 / AGE_BANDS as f32)
+            }
+            ColorMode::Neighbours => mix(
+                self.few_neighbours,
+                self.many_neighbours,
+                game.living_neighbours(x, y, z) as f32 / MANY_NEIGHBOURS as f32,
+            ),
+            ColorMode::Layer => mix(self.bottom, self.top, y as f32 / (game.size() - 1) as f32),
+            ColorMode::Births => {
+                if game.age(x, y, z) == 0 {
+                    opaque(self.born)
+                } else {
+                    opaque(self.survived)
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ColorMode, Palette};
+    use crate::game::{Cell, GameOfLife};
+
+    fn assert_close(color: [f32; 4], expected: [f32; 3]) {
+        for i in 0..3 {
+            assert!((color[i] - expected[i]).abs() < 1e-6, "{:?}", color);
+        }
+    }
+
+    #[test]
+    fn partial_palette_file() {
+        let palette = Palette::from_toml("mode = \"layer\"\ntop = [1.0, 0.0, 0.0]\n").unwrap();
+
+        assert_eq!(palette.mode, ColorMode::Layer);
+        assert_eq!(palette.top, [1.0, 0.0, 0.0]);
+        assert_eq!(palette.bottom, Palette::default().bottom);
+
+        assert!(Palette::from_toml("mode = \"rainbow\"").is_err());
+    }
+
+    #[test]
+    fn every_mode_comes_around() {
+        let mut mode = ColorMode::Plain;
+        for _ in 0..5 {
+            mode = mode.next();
+        }
+
+        assert_eq!(mode, ColorMode::Plain);
+    }
+
+    #[test]
+    fn cell_colors() {
+        let mut game = Box::new(GameOfLife::new());
+        for (x, z) in [(4, 4), (4, 5), (5, 4), (5, 5)] {
+            game.set_cell(x, 0, z, Cell::Alive);
+        }
+
+        let mut palette = Palette {
+            old_age: 2,
+            ..Palette::default()
+        };
+
+        palette.mode = ColorMode::Births;
+        assert_eq!(palette.cell_color(&game, 4, 0, 4)[..3], palette.born);
+        palette.mode = ColorMode::Age;
+        assert_eq!(palette.cell_color(&game, 4, 0, 4)[..3], palette.young);
+
+        game.update_game();
+        palette.mode = ColorMode::Births;
+        assert_eq!(palette.cell_color(&game, 4, 0, 4)[..3], palette.survived);
+
+        // Halfway to old.
+        palette.mode = ColorMode::Age;
+        let halfway = [0, 1, 2].map(|i| (palette.young[i] + palette.old[i]) / 2.0);
+        assert_close(palette.cell_color(&game, 4, 0, 4), halfway);
+
+        // And no further than old.
+        game.update_game();
+        game.update_game();
+        assert_close(palette.cell_color(&game, 4, 0, 4), palette.old);
+
+        palette.mode = ColorMode::Layer;
+        assert_eq!(palette.cell_color(&game, 4, 0, 4)[..3], palette.bottom);
+        assert_close(
+            palette.cell_color(&game, 4, game.size() - 1, 4),
+            palette.top,
+        );
+    }
+}
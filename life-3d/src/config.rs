@@ -0,0 +1,680 @@
+// How the game is set up: the window, the arena, the view, the colours and
+// the saved session. Everything can be set in a config file next to the
+// bindings, and then overridden with options on the command line.
+
+use std::{
+    fmt, fs, io,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use serde::{de::IntoDeserializer, Deserialize};
+
+use crate::{
+    colors::ColorMode,
+    game::{Boundary, Rule, DEFAULT_ARENA_SIZE, RULES},
+    mesher::CHUNK_SIZE,
+};
+
+// Where the config is read from, relative to the working directory, unless
+// `--config` says otherwise.
+pub const DEFAULT_CONFIG_FILE: &str = "config.toml";
+
+// Bigger arenas take too long a generation, and too much memory with every
+// snapshot of them.
+pub const MAX_ARENA_SIZE: usize = 256;
+
+// Options that set up a new arena. Giving any of them means starting a new
+// game, or they would be thrown away for the saved one.
+const ARENA_OPTIONS: &[&str] = &["--arena-size", "--rule", "--boundary", "--pattern"];
+
+// The options and what they are followed by, for `--help`.
+const OPTIONS: &[(&str, &str)] = &[
+    (
+        "--config <file>",
+        "read the config from <file> instead of config.toml",
+    ),
+    ("--width <pixels>", "the width of the window"),
+    ("--height <pixels>", "the height of the window"),
+    (
+        "--fullscreen",
+        "fill the screen instead of opening a window",
+    ),
+    (
+        "--windowed",
+        "open a window, even if the config says fullscreen",
+    ),
+    ("--vsync", "wait for the screen before drawing each frame"),
+    ("--no-vsync", "draw frames as fast as possible"),
+    ("--arena-size <cells>", "how many cells across the arena is"),
+    ("--rule <rule>", "the rule to start with, like B5/S35"),
+    (
+        "--boundary <wrap|dead>",
+        "what is past the edges of the arena",
+    ),
+    ("--pattern <file>", "start with the live cells in <file>"),
+    ("--cell-size <units>", "how big each cell is drawn"),
+    ("--fov <degrees>", "the vertical field of view"),
+    (
+        "--near <units>",
+        "how close things can be and still be drawn",
+    ),
+    (
+        "--far <units>",
+        "how far away things can be and still be drawn",
+    ),
+    (
+        "--palette <file>",
+        "read the colours from <file> instead of palette.toml",
+    ),
+    (
+        "--color-mode <mode>",
+        "plain, age, neighbours, layer or births",
+    ),
+    (
+        "--debug-opengl",
+        "print what OpenGL has to say about errors",
+    ),
+    (
+        "--reload-shaders",
+        "read the shaders from src/shaders and rebuild them when they change",
+    ),
+    (
+        "--fresh",
+        "start a new game instead of carrying on from the last one, which any of the arena options also do",
+    ),
+    (
+        "--autosave <seconds>",
+        "how often to save in case of a crash, or 0 not to",
+    ),
+    ("--help", "print this and quit"),
+];
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(io::Error),
+    Parse(toml::de::Error),
+    UnknownOption(String),
+    // An option that needs a value, but came last.
+    MissingValue(&'static str),
+    BadValue {
+        option: &'static str,
+        value: String,
+        reason: String,
+    },
+    // A setting that was read fine, but can't be used. `setting` is named the
+    // way the config file has it.
+    Invalid {
+        setting: &'static str,
+        reason: String,
+    },
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Io(error) => write!(f, "{}", error),
+            ConfigError::Parse(error) => write!(f, "invalid config file: {}", error),
+            ConfigError::UnknownOption(option) => {
+                write!(f, "there is no {} option, see --help", option)
+            }
+            ConfigError::MissingValue(option) => write!(f, "{} needs a value after it", option),
+            ConfigError::BadValue {
+                option,
+                value,
+                reason,
+            } => write!(f, "{:?} doesn't work for {}: {}", value, option, reason),
+            ConfigError::Invalid { setting, reason } => write!(f, "{} {}", setting, reason),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+impl From<io::Error> for ConfigError {
+    fn from(error: io::Error) -> Self {
+        ConfigError::Io(error)
+    }
+}
+
+// Leaving out the width or the height sizes the window from the screen.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WindowConfig {
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub fullscreen: bool,
+    pub vsync: bool,
+    pub debug_opengl: bool,
+    // For working on the shaders, without building the game again.
+    pub reload_shaders: bool,
+}
+
+impl Default for WindowConfig {
+    fn default() -> WindowConfig {
+        WindowConfig {
+            width: None,
+            height: None,
+            fullscreen: false,
+            vsync: true,
+            debug_opengl: false,
+            reload_shaders: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ArenaConfig {
+    pub size: usize,
+    pub rule: Rule,
+    pub boundary: Boundary,
+    // A file for `Pattern::load`.
+    pub pattern: Option<PathBuf>,
+}
+
+impl Default for ArenaConfig {
+    fn default() -> ArenaConfig {
+        ArenaConfig {
+            size: DEFAULT_ARENA_SIZE,
+            rule: RULES[0],
+            boundary: Boundary::Wrap,
+            pattern: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ViewConfig {
+    pub cell_size: f32,
+    // In degrees, from the bottom of the screen to the top.
+    pub fov: f32,
+    // The near and far clipping planes.
+    pub near: f32,
+    pub far: f32,
+}
+
+impl Default for ViewConfig {
+    fn default() -> ViewConfig {
+        ViewConfig {
+            cell_size: 0.1,
+            fov: 45.0,
+            near: 0.1,
+            far: 100.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ColorConfig {
+    pub palette: PathBuf,
+    // Overrides the mode in the palette file.
+    pub mode: Option<ColorMode>,
+}
+
+impl Default for ColorConfig {
+    fn default() -> ColorConfig {
+        ColorConfig {
+            palette: PathBuf::from("palette.toml"),
+            mode: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SessionConfig {
+    // Whether to carry on from where the last game was left, instead of
+    // starting from the arena settings.
+    pub restore: bool,
+    // How many seconds between saves to the recovery file, or 0 for none.
+    pub autosave: f32,
+}
+
+impl Default for SessionConfig {
+    fn default() -> SessionConfig {
+        SessionConfig {
+            restore: true,
+            autosave: 60.0,
+        }
+    }
+}
+
+// Anything left out of the config file keeps its default.
+#[derive(Debug, Clone, PartialEq, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub window: WindowConfig,
+    pub arena: ArenaConfig,
+    pub view: ViewConfig,
+    pub colors: ColorConfig,
+    pub session: SessionConfig,
+}
+
+// Whether the command line asks for `--help`.
+pub fn wants_help(args: &[String]) -> bool {
+    args.iter().any(|arg| arg == "--help" || arg == "-h")
+}
+
+// What `--help` prints.
+pub fn usage() -> String {
+    let width = OPTIONS
+        .iter()
+        .map(|(option, _)| option.len())
+        .max()
+        .unwrap();
+    let mut usage = String::from("Usage: life-3d [options]\n\nOptions:\n");
+
+    for (option, description) in OPTIONS {
+        usage += &format!("    {:width$}  {}\n", option, description, width = width);
+    }
+
+    usage
+}
+
+// The file given with `--config`, or else the default one.
+pub fn config_path(args: &[String]) -> Result<PathBuf, ConfigError> {
+    let mut path = PathBuf::from(DEFAULT_CONFIG_FILE);
+    let mut args = args.iter();
+
+    while let Some(arg) = args.next() {
+        if let Some(value) = arg.strip_prefix("--config=") {
+            path = PathBuf::from(value);
+        } else if arg == "--config" {
+            let value = args.next().ok_or(ConfigError::MissingValue("--config"))?;
+            path = PathBuf::from(value);
+        }
+    }
+
+    Ok(path)
+}
+
+// Reads a number, or anything else `FromStr` can read, for `option`.
+fn parse_value<T>(option: &'static str, value: &str) -> Result<T, ConfigError>
+where
+    T: FromStr,
+    T::Err: fmt::Display,
+{
+    value
+        .parse()
+        .map_err(|error: T::Err| ConfigError::BadValue {
+            option,
+            value: value.to_string(),
+            reason: error.to_string(),
+        })
+}
+
+// Reads a value named the same way as in the config file, like a boundary or
+// a colour mode, for `option`.
+fn deserialize_value<T>(option: &'static str, value: &str) -> Result<T, ConfigError>
+where
+    T: for<'de> Deserialize<'de>,
+{
+    T::deserialize(value.into_deserializer()).map_err(|error: serde::de::value::Error| {
+        ConfigError::BadValue {
+            option,
+            value: value.to_string(),
+            reason: error.to_string(),
+        }
+    })
+}
+
+impl Config {
+    // Loads the config from `path`, or the default config if there is no
+    // such file.
+    pub fn load(path: &Path) -> Result<Config, ConfigError> {
+        match fs::read_to_string(path) {
+            Ok(contents) => Config::from_toml(&contents),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(Config::default()),
+            Err(error) => Err(error.into()),
+        }
+    }
+
+    pub fn from_toml(contents: &str) -> Result<Config, ConfigError> {
+        toml::from_str(contents).map_err(ConfigError::Parse)
+    }
+
+    // Overrides the settings with the options on the command line, without
+    // the program's name. Values can follow their option either as the next
+    // argument, or after an equals sign.
+    pub fn apply_args(&mut self, args: &[String]) -> Result<(), ConfigError> {
+        let mut args = args.iter();
+
+        while let Some(arg) = args.next() {
+            let (name, inline_value) = match arg.split_once('=') {
+                Some((name, value)) => (name, Some(value.to_string())),
+                None => (arg.as_str(), None),
+            };
+
+            let Some(&(usage, _)) = OPTIONS
+                .iter()
+                .find(|(usage, _)| usage.split(' ').next() == Some(name))
+            else {
+                return Err(ConfigError::UnknownOption(arg.clone()));
+            };
+            let option = usage.split(' ').next().unwrap();
+
+            // Only the options with something after them in `OPTIONS` take
+            // a value.
+            let value = if usage.contains(' ') {
+                match inline_value {
+                    Some(value) => value,
+                    None => args
+                        .next()
+                        .cloned()
+                        .ok_or(ConfigError::MissingValue(option))?,
+                }
+            } else if inline_value.is_some() {
+                return Err(ConfigError::UnknownOption(arg.clone()));
+            } else {
+                String::new()
+            };
+
+            match option {
+                // Already read by `config_path`.
+                "--config" | "--help" => {}
+                "--width" => self.window.width = Some(parse_value(option, &value)?),
+                "--height" => self.window.height = Some(parse_value(option, &value)?),
+                "--fullscreen" => self.window.fullscreen = true,
+                "--windowed" => self.window.fullscreen = false,
+                "--vsync" => self.window.vsync = true,
+                "--no-vsync" => self.window.vsync = false,
+                "--debug-opengl" => self.window.debug_opengl = true,
+                "--reload-shaders" => self.window.reload_shaders = true,
+                "--arena-size" => self.arena.size = parse_value(option, &value)?,
+                "--rule" => self.arena.rule = parse_value(option, &value)?,
+                "--boundary" => self.arena.boundary = deserialize_value(option, &value)?,
+                "--pattern" => self.arena.pattern = Some(PathBuf::from(value)),
+                "--cell-size" => self.view.cell_size = parse_value(option, &value)?,
+                "--fov" => self.view.fov = parse_value(option, &value)?,
+                "--near" => self.view.near = parse_value(option, &value)?,
+                "--far" => self.view.far = parse_value(option, &value)?,
+                "--palette" => self.colors.palette = PathBuf::from(value),
+                "--color-mode" => self.colors.mode = Some(deserialize_value(option, &value)?),
+                "--fresh" => self.session.restore = false,
+                "--autosave" => self.session.autosave = parse_value(option, &value)?,
+                _ => unreachable!("{} is in OPTIONS but isn't handled", option),
+            }
+
+            if ARENA_OPTIONS.contains(&option) {
+                self.session.restore = false;
+            }
+        }
+
+        Ok(())
+    }
+
+    // Checks for settings that were read fine, but don't make sense.
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let invalid = |setting, reason: String| Err(ConfigError::Invalid { setting, reason });
+
+        for (setting, size) in [
+            ("window.width", self.window.width),
+            ("window.height", self.window.height),
+        ] {
+            if size == Some(0) {
+                return invalid(setting, "has to be at least 1 pixel".to_string());
+            }
+        }
+
+        let size = self.arena.size;
+        if !size.is_multiple_of(CHUNK_SIZE) || !(CHUNK_SIZE..=MAX_ARENA_SIZE).contains(&size) {
+            return invalid(
+                "arena.size",
+                format!(
+                    "has to be a multiple of {} from {} to {}, but it is {}",
+                    CHUNK_SIZE, CHUNK_SIZE, MAX_ARENA_SIZE, size
+                ),
+            );
+        }
+
+        let view = &self.view;
+        // Also false for NaN, which parses as a number.
+        let positive = |value: f32| value > 0.0 && value.is_finite();
+
+        if !positive(view.cell_size) {
+            return invalid(
+                "view.cell_size",
+                format!("has to be more than 0, but it is {}", view.cell_size),
+            );
+        }
+
+        if !(view.fov > 0.0 && view.fov < 180.0) {
+            return invalid(
+                "view.fov",
+                format!(
+                    "has to be between 0 and 180 degrees, but it is {}",
+                    view.fov
+                ),
+            );
+        }
+
+        if !positive(view.near) {
+            return invalid(
+                "view.near",
+                format!("has to be more than 0, but it is {}", view.near),
+            );
+        }
+
+        if !(view.far > view.near && view.far.is_finite()) {
+            return invalid(
+                "view.far",
+                format!(
+                    "has to be further than view.near ({}), but it is {}",
+                    view.near, view.far
+                ),
+            );
+        }
+
+        let autosave = self.session.autosave;
+        if !(autosave >= 0.0 && autosave.is_finite()) {
+            return invalid(
+                "session.autosave",
+                format!("has to be 0 or more seconds, but it is {}", autosave),
+            );
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::{config_path, usage, Config, ConfigError, OPTIONS};
+    use crate::{
+        colors::ColorMode,
+        game::{Boundary, Rule, RULES},
+    };
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn partial_config_file() {
+        let config = Config::from_toml(
+            "[arena]\nsize = 64\nrule = \"B6/S567\"\nboundary = \"dead\"\n\n\
+             [view]\nfov = 60.0\n",
+        )
+        .unwrap();
+
+        assert_eq!(config.arena.size, 64);
+        assert_eq!(config.arena.rule, RULES[2]);
+        assert_eq!(config.arena.boundary, Boundary::Dead);
+        assert_eq!(config.view.fov, 60.0);
+        assert_eq!(config.view.far, Config::default().view.far);
+        assert_eq!(config.window, Config::default().window);
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn mistakes_in_the_file() {
+        for contents in [
+            "[window]\nfulscreen = true",
+            "[arena]\nrule = \"B5\"",
+            "[arena]\nboundary = \"torus\"",
+            "[view]\nfov = \"wide\"",
+            "[camera]\nfov = 45.0",
+        ] {
+            assert!(
+                matches!(Config::from_toml(contents), Err(ConfigError::Parse(_))),
+                "{:?}",
+                contents
+            );
+        }
+    }
+
+    #[test]
+    fn options_override_the_file() {
+        let mut config = Config::from_toml("[window]\nfullscreen = true\nvsync = false").unwrap();
+        config
+            .apply_args(&args(&[
+                "--windowed",
+                "--width",
+                "800",
+                "--height=600",
+                "--rule=B5/S45",
+                "--boundary",
+                "dead",
+                "--color-mode",
+                "layer",
+                "--pattern",
+                "glider.txt",
+                "--debug-opengl",
+                "--reload-shaders",
+                "--config",
+                "other.toml",
+                "--fresh",
+                "--autosave=30",
+            ]))
+            .unwrap();
+
+        assert!(!config.session.restore);
+        assert_eq!(config.session.autosave, 30.0);
+        assert!(!config.window.fullscreen);
+        assert!(!config.window.vsync);
+        assert!(config.window.debug_opengl);
+        assert!(config.window.reload_shaders);
+        assert_eq!(
+            (config.window.width, config.window.height),
+            (Some(800), Some(600))
+        );
+        assert_eq!(config.arena.rule, "B5/S45".parse::<Rule>().unwrap());
+        assert_eq!(config.arena.boundary, Boundary::Dead);
+        assert_eq!(config.colors.mode, Some(ColorMode::Layer));
+        assert_eq!(config.arena.pattern, Some(PathBuf::from("glider.txt")));
+    }
+
+    #[test]
+    fn arena_options_start_a_new_game() {
+        for option in [
+            "--arena-size=64",
+            "--rule=B5/S45",
+            "--boundary=dead",
+            "--pattern=glider.txt",
+        ] {
+            let mut config = Config::default();
+            config.apply_args(&args(&[option])).unwrap();
+            assert!(!config.session.restore, "{}", option);
+        }
+
+        let mut config = Config::default();
+        config
+            .apply_args(&args(&["--fov=60", "--autosave=10"]))
+            .unwrap();
+        assert!(config.session.restore);
+    }
+
+    #[test]
+    fn mistakes_in_the_options() {
+        let mut config = Config::default();
+
+        assert!(matches!(
+            config.apply_args(&args(&["--fullscren"])),
+            Err(ConfigError::UnknownOption(option)) if option == "--fullscren"
+        ));
+        assert!(matches!(
+            config.apply_args(&args(&["--vsync=no"])),
+            Err(ConfigError::UnknownOption(_))
+        ));
+        assert!(matches!(
+            config.apply_args(&args(&["--fov"])),
+            Err(ConfigError::MissingValue("--fov"))
+        ));
+        assert!(matches!(
+            config.apply_args(&args(&["--arena-size", "big"])),
+            Err(ConfigError::BadValue {
+                option: "--arena-size",
+                ..
+            })
+        ));
+        assert!(matches!(
+            config.apply_args(&args(&["--color-mode=rainbow"])),
+            Err(ConfigError::BadValue { option: "--color-mode", reason, .. })
+                if reason.contains("neighbours")
+        ));
+        assert!(matches!(
+            config.apply_args(&args(&["--rule", "B5"])),
+            Err(ConfigError::BadValue {
+                option: "--rule",
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn validation() {
+        let check = |options: &[&str]| {
+            let mut config = Config::default();
+            config.apply_args(&args(options)).unwrap();
+            match config.validate() {
+                Ok(()) => None,
+                Err(ConfigError::Invalid { setting, .. }) => Some(setting),
+                Err(error) => panic!("{}", error),
+            }
+        };
+
+        assert_eq!(check(&[]), None);
+        assert_eq!(check(&["--arena-size", "32"]), None);
+        assert_eq!(check(&["--arena-size", "100"]), Some("arena.size"));
+        assert_eq!(check(&["--arena-size", "0"]), Some("arena.size"));
+        assert_eq!(check(&["--arena-size", "1024"]), Some("arena.size"));
+        assert_eq!(check(&["--width", "0"]), Some("window.width"));
+        assert_eq!(check(&["--cell-size", "-1"]), Some("view.cell_size"));
+        assert_eq!(check(&["--fov", "180"]), Some("view.fov"));
+        assert_eq!(check(&["--near", "0"]), Some("view.near"));
+        assert_eq!(check(&["--near", "5", "--far", "2"]), Some("view.far"));
+        assert_eq!(check(&["--autosave", "0"]), None);
+        assert_eq!(check(&["--autosave", "-5"]), Some("session.autosave"));
+    }
+
+    #[test]
+    fn config_file_option() {
+        assert_eq!(
+            config_path(&args(&[])).unwrap(),
+            PathBuf::from("config.toml")
+        );
+        assert_eq!(
+            config_path(&args(&["--fov", "30", "--config", "a.toml"])).unwrap(),
+            PathBuf::from("a.toml")
+        );
+        assert_eq!(
+            config_path(&args(&["--config=b.toml"])).unwrap(),
+            PathBuf::from("b.toml")
+        );
+        assert!(config_path(&args(&["--config"])).is_err());
+    }
+
+    #[test]
+    fn every_option_has_help() {
+        let usage = usage();
+
+        for (option, _) in OPTIONS {
+            assert!(usage.contains(option));
+        }
+    }
+}
@@ -0,0 +1,523 @@
+// Turns raw mouse input into smooth camera motion. Everything in here is
+// independent of the frame rate: the same input gives the same camera
+// movement whether the game runs at 30 or at 240 frames per second.
+
+use crate::{
+    camera::{ActiveCamera, ArcballCamera},
+    math::{Quaternion, Vec2, Vec3},
+};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CameraSettings {
+    // Degrees of rotation per pixel of mouse movement.
+    pub rotate_sensitivity: f32,
+    // How much closer one notch of the scroll wheel gets the camera, as a
+    // fraction of the current distance.
+    pub zoom_step: f32,
+    // How long (in seconds) the camera takes to catch up with about two
+    // thirds of the input. Zero makes the camera follow the mouse exactly.
+    pub smoothing: f32,
+    // How long (in seconds) a flicked camera takes to lose about two thirds
+    // of its speed. Zero stops the camera as soon as the mouse is let go.
+    pub inertia: f32,
+    pub min_distance: f32,
+    pub max_distance: f32,
+}
+
+impl Default for CameraSettings {
+    fn default() -> CameraSettings {
+        CameraSettings {
+            rotate_sensitivity: 0.3,
+            zoom_step: 0.1,
+            smoothing: 0.05,
+            inertia: 0.2,
+            min_distance: 0.5,
+            max_distance: 50.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CameraInput {
+    // The mouse moved by this many pixels while rotating.
+    Rotate { dx: f32, dy: f32 },
+    // The mouse moved across an arcball camera's virtual trackball, from
+    // `from` to `to` in normalized device coordinates. Other cameras ignore
+    // this.
+    Drag { from: Vec2, to: Vec2 },
+    // The mouse moved by this many pixels while panning.
+    Pan { dx: f32, dy: f32 },
+    // The scroll wheel moved by this many notches, positive is towards the
+    // screen (zooming in).
+    Zoom(f32),
+    // The mouse button was let go of, so the camera coasts from here on.
+    Release,
+}
+
+pub struct CameraController {
+    settings: CameraSettings,
+    // Needed to work out how far a pixel is at the target's distance.
+    viewport_height: f32,
+    fov: f32,
+
+    // Input that the camera has not caught up with yet. Rotation is in
+    // degrees (yaw, pitch), panning in world units (right, up), and zoom is
+    // the logarithm of the factor the distance gets multiplied by. Trackball
+    // drags are kept as the view space rotation they add up to.
+    pending_rotation: Vec2,
+    pending_drag: Quaternion,
+    pending_pan: Vec2,
+    pending_zoom: f32,
+
+    // Degrees per second, measured while dragging and kept going afterwards.
+    rotation_velocity: Vec2,
+    // Like `rotation_velocity`, for trackball drags: the axis scaled by the
+    // radians per second around it.
+    drag_velocity: Vec3,
+    dragging: bool,
+}
+
+// The fraction of the remaining distance to cover in `delta_time`, when
+// covering about two thirds of it every `time_constant` seconds. Splitting
+// the same time into more frames gives exactly the same total.
+fn exponential_blend(delta_time: f32, time_constant: f32) -> f32 {
+    if time_constant <= 0.0 {
+        1.0
+    } else {
+        1.0 - (-delta_time / time_constant).exp()
+    }
+}
+
+impl CameraController {
+    pub fn new(settings: CameraSettings, viewport_height: f32, fov: f32) -> CameraController {
+        CameraController {
+            settings,
+            viewport_height,
+            fov,
+            pending_rotation: Vec2::new(0.0, 0.0),
+            pending_drag: Quaternion::identity(),
+            pending_pan: Vec2::new(0.0, 0.0),
+            pending_zoom: 0.0,
+            rotation_velocity: Vec2::new(0.0, 0.0),
+            drag_velocity: Vec3::new(0.0, 0.0, 0.0),
+            dragging: false,
+        }
+    }
+
+    pub fn settings(&self) -> &CameraSettings {
+        &self.settings
+    }
+
+    pub fn set_viewport_height(&mut self, viewport_height: f32) {
+        self.viewport_height = viewport_height;
+    }
+
+    pub fn set_fov(&mut self, fov: f32) {
+        self.fov = fov;
+    }
+
+    // Whether the camera is still moving from earlier input.
+    pub fn is_moving(&self) -> bool {
+        let rest = 1e-4;
+
+        self.pending_rotation.len() > rest
+            || self.pending_drag.to_axis_angle().1 > rest
+            || self.pending_pan.len() > rest
+            || self.pending_zoom.abs() > rest
+            || self.rotation_velocity.len() > rest
+            || self.drag_velocity.len() > rest
+    }
+
+    // Drops any motion that has not been applied yet, for when the camera
+    // gets replaced or teleported.
+    pub fn stop(&mut self) {
+        self.pending_rotation = Vec2::new(0.0, 0.0);
+        self.pending_drag = Quaternion::identity();
+        self.pending_pan = Vec2::new(0.0, 0.0);
+        self.pending_zoom = 0.0;
+        self.rotation_velocity = Vec2::new(0.0, 0.0);
+        self.drag_velocity = Vec3::new(0.0, 0.0, 0.0);
+        self.dragging = false;
+    }
+
+    fn start_dragging(&mut self) {
+        if !self.dragging {
+            self.dragging = true;
+            self.rotation_velocity = Vec2::new(0.0, 0.0);
+            self.drag_velocity = Vec3::new(0.0, 0.0, 0.0);
+        }
+    }
+
+    pub fn handle_input(&mut self, camera: &ActiveCamera, input: CameraInput) {
+        match input {
+            CameraInput::Rotate { dx, dy } => {
+                self.start_dragging();
+                self.pending_rotation =
+                    self.pending_rotation + Vec2::new(dx, dy) * self.settings.rotate_sensitivity;
+            }
+            CameraInput::Drag { from, to } => {
+                self.start_dragging();
+                self.pending_drag =
+                    (ArcballCamera::drag_rotation(from, to) * self.pending_drag).normalize();
+            }
+            CameraInput::Pan { dx, dy } => {
+                // Drag the target along with the mouse, so whatever is under
+                // it stays under it.
+                let units_per_pixel = 2.0 * camera.distance() * (self.fov.to_radians() / 2.0).tan()
+                    / self.viewport_height.max(1.0);
+
+                self.pending_pan = self.pending_pan + Vec2::new(-dx, dy) * units_per_pixel;
+            }
+            CameraInput::Zoom(notches) => {
+                let step = (1.0 - self.settings.zoom_step).clamp(0.01, 1.0);
+                self.pending_zoom += notches * step.ln();
+            }
+            CameraInput::Release => {
+                self.dragging = false;
+            }
+        }
+    }
+
+    pub fn update(&mut self, camera: &mut ActiveCamera, delta_time: f32) {
+        if delta_time <= 0.0 {
+            return;
+        }
+
+        let blend = exponential_blend(delta_time, self.settings.smoothing);
+
+        let mut rotation = self.pending_rotation * blend;
+        self.pending_rotation = self.pending_rotation - rotation;
+
+        if self.dragging {
+            // Smooth the measured speed too, so a single jittery frame right
+            // before letting go does not decide where the camera coasts.
+            let velocity = rotation * (1.0 / delta_time);
+            self.rotation_velocity = self.rotation_velocity
+                + (velocity - self.rotation_velocity) * exponential_blend(delta_time, 0.05);
+        } else if self.settings.inertia > 0.0 {
+            // Integrate the exponentially decaying speed over the frame
+            // exactly, rather than stepping it.
+            let decay = (-delta_time / self.settings.inertia).exp();
+            rotation = rotation + self.rotation_velocity * (self.settings.inertia * (1.0 - decay));
+            self.rotation_velocity = self.rotation_velocity * decay;
+        } else {
+            self.rotation_velocity = Vec2::new(0.0, 0.0);
+        }
+
+        if rotation.len() > 0.0 {
+            camera.rotate(rotation.x, rotation.y);
+        }
+
+        // The same again for trackball drags, but on rotations rather than
+        // angles. A fraction of a rotation is the same axis turned by a
+        // fraction of the angle, which is what slerping from the identity
+        // gives.
+        let drag = Quaternion::identity().slerp(&self.pending_drag, blend);
+        self.pending_drag = (self.pending_drag * drag.conjugate()).normalize();
+
+        let (axis, angle) = drag.to_axis_angle();
+        let mut spin = axis * angle;
+
+        if self.dragging {
+            let velocity = spin * (1.0 / delta_time);
+            self.drag_velocity = self.drag_velocity
+                + (velocity - self.drag_velocity) * exponential_blend(delta_time, 0.05);
+        } else if self.settings.inertia > 0.0 {
+            let decay = (-delta_time / self.settings.inertia).exp();
+            spin = spin + self.drag_velocity * (self.settings.inertia * (1.0 - decay));
+            self.drag_velocity = self.drag_velocity * decay;
+        } else {
+            self.drag_velocity = Vec3::new(0.0, 0.0, 0.0);
+        }
+
+        if let ActiveCamera::Arcball(camera) = camera {
+            if spin.len() > 0.0 {
+                camera.drag(Quaternion::new(&spin.normalize(), spin.len()));
+            }
+        }
+
+        let pan = self.pending_pan * blend;
+        self.pending_pan = self.pending_pan - pan;
+        if pan.len() > 0.0 {
+            camera.pan(pan.x, pan.y);
+        }
+
+        let zoom = self.pending_zoom * blend;
+        self.pending_zoom -= zoom;
+        if zoom != 0.0 {
+            let distance = (camera.distance() * zoom.exp())
+                .clamp(self.settings.min_distance, self.settings.max_distance);
+            camera.set_distance(distance);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CameraController, CameraInput, CameraSettings};
+    use crate::{
+        camera::{ActiveCamera, ArcballCamera, ThirdPersonCamera},
+        math::{Vec2, Vec3},
+    };
+
+    fn camera() -> ActiveCamera {
+        ActiveCamera::ThirdPerson(ThirdPersonCamera::new(
+            Vec3::new(0.0, 0.0, 0.0),
+            5.0,
+            0.0,
+            0.0,
+        ))
+    }
+
+    fn controller(settings: CameraSettings) -> CameraController {
+        CameraController::new(settings, 720.0, 45.0)
+    }
+
+    fn yaw_pitch(camera: &ActiveCamera) -> (f32, f32) {
+        match camera {
+            ActiveCamera::ThirdPerson(camera) => (camera.yaw(), camera.pitch()),
+            _ => unreachable!(),
+        }
+    }
+
+    // Feeds the same drag in at a given frame rate, for a second.
+    fn drag_at_frame_rate(frames_per_second: u32) -> ActiveCamera {
+        let mut camera = camera();
+        let mut controller = controller(CameraSettings::default());
+        let delta_time = 1.0 / frames_per_second as f32;
+
+        // 100 pixels to the right over a tenth of a second, however many
+        // frames that happens to be.
+        let drag_frames = frames_per_second / 10;
+        for _ in 0..drag_frames {
+            controller.handle_input(
+                &camera,
+                CameraInput::Rotate {
+                    dx: 100.0 / drag_frames as f32,
+                    dy: 0.0,
+                },
+            );
+            controller.update(&mut camera, delta_time);
+        }
+
+        controller.handle_input(&camera, CameraInput::Zoom(2.0));
+
+        for _ in 0..(frames_per_second * 2) {
+            controller.update(&mut camera, delta_time);
+        }
+
+        camera
+    }
+
+    #[test]
+    fn zero_scroll_does_nothing() {
+        let mut camera = camera();
+        let mut controller = controller(CameraSettings::default());
+
+        controller.handle_input(&camera, CameraInput::Zoom(0.0));
+        controller.update(&mut camera, 0.016);
+
+        assert_eq!(camera.distance(), 5.0);
+        assert!(!controller.is_moving());
+    }
+
+    #[test]
+    fn rotation_follows_sensitivity() {
+        let mut camera = camera();
+        let mut controller = controller(CameraSettings {
+            rotate_sensitivity: 0.5,
+            smoothing: 0.0,
+            inertia: 0.0,
+            ..CameraSettings::default()
+        });
+
+        controller.handle_input(
+            &camera,
+            CameraInput::Rotate {
+                dx: 20.0,
+                dy: -10.0,
+            },
+        );
+        controller.update(&mut camera, 0.016);
+
+        assert_eq!(yaw_pitch(&camera), (10.0, -5.0));
+    }
+
+    #[test]
+    fn smoothing_catches_up_gradually() {
+        let mut camera = camera();
+        let mut controller = controller(CameraSettings {
+            inertia: 0.0,
+            ..CameraSettings::default()
+        });
+
+        controller.handle_input(&camera, CameraInput::Rotate { dx: 100.0, dy: 0.0 });
+        controller.handle_input(&camera, CameraInput::Release);
+        controller.update(&mut camera, 0.016);
+
+        let (yaw, _) = yaw_pitch(&camera);
+        assert!(yaw > 0.0 && yaw < 30.0);
+
+        for _ in 0..100 {
+            controller.update(&mut camera, 0.016);
+        }
+
+        let (yaw, _) = yaw_pitch(&camera);
+        assert!((yaw - 30.0).abs() < 1e-3);
+        assert!(!controller.is_moving());
+    }
+
+    #[test]
+    fn movement_is_frame_rate_independent() {
+        let slow = drag_at_frame_rate(30);
+        let fast = drag_at_frame_rate(240);
+
+        let (slow_yaw, _) = yaw_pitch(&slow);
+        let (fast_yaw, _) = yaw_pitch(&fast);
+
+        assert!(
+            (slow_yaw - fast_yaw).abs() < 0.5,
+            "{} {}",
+            slow_yaw,
+            fast_yaw
+        );
+        assert!((slow.distance() - fast.distance()).abs() < 1e-3);
+        assert!((slow.distance() - 5.0 * 0.9 * 0.9).abs() < 1e-3);
+    }
+
+    #[test]
+    fn inertia_keeps_the_camera_going() {
+        let mut camera = camera();
+        let mut controller = controller(CameraSettings::default());
+
+        for _ in 0..10 {
+            controller.handle_input(&camera, CameraInput::Rotate { dx: 10.0, dy: 0.0 });
+            controller.update(&mut camera, 0.016);
+        }
+        controller.handle_input(&camera, CameraInput::Release);
+
+        // Long after the drag's own smoothing has run out, the camera keeps
+        // coasting...
+        for _ in 0..20 {
+            controller.update(&mut camera, 0.016);
+        }
+        let (coasting, _) = yaw_pitch(&camera);
+        controller.update(&mut camera, 0.016);
+        assert!(yaw_pitch(&camera).0 > coasting);
+
+        // ...but it does eventually come to a stop.
+        for _ in 0..1000 {
+            controller.update(&mut camera, 0.016);
+        }
+        let (stopped, _) = yaw_pitch(&camera);
+        controller.update(&mut camera, 0.016);
+        assert!((yaw_pitch(&camera).0 - stopped).abs() < 1e-3);
+        assert!(!controller.is_moving());
+    }
+
+    #[test]
+    fn zoom_is_clamped() {
+        let mut camera = camera();
+        let mut controller = controller(CameraSettings {
+            min_distance: 2.0,
+            max_distance: 8.0,
+            ..CameraSettings::default()
+        });
+
+        controller.handle_input(&camera, CameraInput::Zoom(50.0));
+        for _ in 0..100 {
+            controller.update(&mut camera, 0.016);
+        }
+        assert_eq!(camera.distance(), 2.0);
+
+        controller.handle_input(&camera, CameraInput::Zoom(-50.0));
+        for _ in 0..100 {
+            controller.update(&mut camera, 0.016);
+        }
+        assert_eq!(camera.distance(), 8.0);
+    }
+
+    #[test]
+    fn panning_keeps_the_target_under_the_mouse() {
+        let mut camera = camera();
+        let mut controller = controller(CameraSettings {
+            smoothing: 0.0,
+            ..CameraSettings::default()
+        });
+
+        // Half the viewport's height is exactly the distance times the
+        // tangent of half the field of view, at the target.
+        controller.handle_input(&camera, CameraInput::Pan { dx: 0.0, dy: 360.0 });
+        controller.update(&mut camera, 0.016);
+
+        let expected = 5.0 * 22.5f32.to_radians().tan();
+        let ActiveCamera::ThirdPerson(orbit) = &camera else {
+            unreachable!()
+        };
+        assert!((orbit.target().y - expected).abs() < 1e-3);
+    }
+
+    #[test]
+    fn trackball_drags_catch_up_with_the_arcball() {
+        let mut camera = ActiveCamera::Arcball(ArcballCamera::new(Vec3::new(0.0, 0.0, 0.0), 5.0));
+        let mut controller = controller(CameraSettings {
+            inertia: 0.0,
+            ..CameraSettings::default()
+        });
+
+        let mut expected = ArcballCamera::new(Vec3::new(0.0, 0.0, 0.0), 5.0);
+        let points = [
+            Vec2::new(0.0, 0.0),
+            Vec2::new(0.2, 0.1),
+            Vec2::new(0.4, 0.3),
+        ];
+        for pair in points.windows(2) {
+            let input = CameraInput::Drag {
+                from: pair[0],
+                to: pair[1],
+            };
+            controller.handle_input(&camera, input);
+            expected.drag(ArcballCamera::drag_rotation(pair[0], pair[1]));
+        }
+        controller.handle_input(&camera, CameraInput::Release);
+
+        // Smoothed, so only part of the way there after one frame...
+        controller.update(&mut camera, 0.016);
+        let partway = camera.position();
+        assert!((partway - expected.position()).len() > 0.1);
+        assert!((partway - Vec3::new(0.0, 0.0, 5.0)).len() > 0.1);
+
+        // ...but ending up exactly where dragging the arcball directly does.
+        for _ in 0..100 {
+            controller.update(&mut camera, 0.016);
+        }
+        assert!((camera.position() - expected.position()).len() < 1e-3);
+        assert!(!controller.is_moving());
+    }
+
+    #[test]
+    fn trackball_drags_coast_after_release() {
+        let mut camera = ActiveCamera::Arcball(ArcballCamera::new(Vec3::new(0.0, 0.0, 0.0), 5.0));
+        let mut controller = controller(CameraSettings::default());
+
+        for i in 0..10 {
+            let from = Vec2::new(i as f32 * 0.02, 0.0);
+            let to = Vec2::new((i + 1) as f32 * 0.02, 0.0);
+            controller.handle_input(&camera, CameraInput::Drag { from, to });
+            controller.update(&mut camera, 0.016);
+        }
+        controller.handle_input(&camera, CameraInput::Release);
+
+        for _ in 0..20 {
+            controller.update(&mut camera, 0.016);
+        }
+        let coasting = camera.position();
+        controller.update(&mut camera, 0.016);
+        assert!((camera.position() - coasting).len() > 1e-4);
+
+        for _ in 0..1000 {
+            controller.update(&mut camera, 0.016);
+        }
+        assert!(!controller.is_moving());
+    }
+}
@@ -0,0 +1,365 @@
+// A top-down grid of a single layer of the arena, drawn over everything else,
+// where cells can be switched on and off by clicking on them. Columns go
+// along x and rows along z. The cell under the mouse gets a label with its
+// coordinates and how many live neighbours it has.
+
+use crate::{
+    colors::Palette,
+    font::GLYPH_WIDTH,
+    game::GameOfLife,
+    math::{Mat4, Vec2, Vec3},
+    renderer::{Axis, CellInstance, Mesh, Renderer, TextMesh, TextRenderer},
+    shader_program_from_resources,
+    shader_reload::ShaderWatcher,
+    shaders::{self, ShaderError, ShaderProgram},
+};
+
+const EDITOR_MARGIN: f32 = 20.0;
+// How much of the shorter side of the window the grid takes up.
+const EDITOR_SIZE: f32 = 0.6;
+// How much of its square a live cell fills, leaving a gap to tell them apart.
+const LIVE_CELL_SCALE: f32 = 0.8;
+
+// The x and z coordinates of a square on the grid.
+pub type GridCell = (usize, usize);
+
+const BACKGROUND_COLOR: [f32; 4] = [0.08, 0.08, 0.1, 1.0];
+const HOVER_COLOR: [f32; 4] = [0.9, 0.9, 0.9, 1.0];
+
+// How many pixels on the screen each pixel of the font takes up in the
+// hover label, and how far the label is from the cell.
+const LABEL_PIXEL_SIZE: f32 = 2.0;
+const LABEL_GAP: f32 = 6.0;
+
+// Where the grid is on the screen, in the same pixels as `flat_projection`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EditorLayout {
+    // The top left corner of the grid.
+    pub origin: Vec2,
+    pub cell_pixels: f32,
+    // How many cells there are along each side.
+    pub cells: usize,
+}
+
+impl EditorLayout {
+    // Puts a grid `cells` cells across in the top right corner of a window
+    // of the given size.
+    pub fn fit(width: f32, height: f32, cells: usize) -> EditorLayout {
+        let size = width.min(height) * EDITOR_SIZE;
+
+        EditorLayout {
+            origin: Vec2::new(width - size - EDITOR_MARGIN, EDITOR_MARGIN),
+            cell_pixels: size / cells as f32,
+            cells,
+        }
+    }
+
+    // The x and z coordinates of the cell under a point on the screen.
+    pub fn cell_at(&self, x: f32, y: f32) -> Option<GridCell> {
+        let column = ((x - self.origin.x) / self.cell_pixels).floor();
+        let row = ((y - self.origin.y) / self.cell_pixels).floor();
+        let inside = |i: f32| i >= 0.0 && i < self.cells as f32;
+
+        (inside(column) && inside(row)).then_some((column as usize, row as usize))
+    }
+
+    // Where the top left corner of `text` goes to label a cell. The grid is
+    // against the right edge of the window, so the label goes to the left
+    // of the cell.
+    pub fn label_position(&self, (x, z): GridCell, text: &str) -> Vec2 {
+        let columns = text.lines().map(|line| line.chars().count()).max();
+        let width = columns.unwrap_or(0) as f32 * (GLYPH_WIDTH as f32 + 1.0) * LABEL_PIXEL_SIZE;
+
+        Vec2::new(
+            self.origin.x + x as f32 * self.cell_pixels - LABEL_GAP - width,
+            self.origin.y + z as f32 * self.cell_pixels,
+        )
+    }
+
+    // The nearest layer to `layer` that is inside the arena.
+    pub fn clamp_layer(&self, layer: usize) -> usize {
+        layer.min(self.cells - 1)
+    }
+
+    fn model_matrix(&self) -> Mat4 {
+        Mat4::translate(self.origin.x, self.origin.y, 0.0)
+            * Mat4::scale(self.cell_pixels, self.cell_pixels, 1.0)
+    }
+}
+
+pub struct LayerEditor {
+    open: bool,
+    layer: usize,
+    layout: EditorLayout,
+    hovered: Option<GridCell>,
+
+    renderer: Renderer,
+    shader_program: ShaderProgram,
+    label: TextMesh,
+    label_renderer: TextRenderer,
+    text_program: ShaderProgram,
+    // For `shader_program` and `text_program`, while the shaders are being
+    // reloaded.
+    shader_watchers: Option<[ShaderWatcher; 2]>,
+    // The game revision, layer and hovered cell the instances and the label
+    // were built from.
+    built: Option<(u64, usize, Option<GridCell>)>,
+}
+
+impl LayerEditor {
+    pub fn new(width: f32, height: f32, arena_size: usize) -> Result<LayerEditor, ShaderError> {
+        let mut square = Mesh::new();
+        square.append_face(
+            Axis::Z,
+            true,
+            0.0,
+            Vec3::new(0.0, 0.0, 0.0),
+            Vec2::new(1.0, 1.0),
+        );
+        let label = TextMesh::new();
+
+        Ok(LayerEditor {
+            open: false,
+            layer: arena_size / 2,
+            layout: EditorLayout::fit(width, height, arena_size),
+            hovered: None,
+            renderer: Renderer::new(&square),
+            shader_program: ShaderProgram::new(
+                &shaders::with_instance_data::<CellInstance>(shaders::EDITOR_VERT),
+                Some("shaders::EDITOR_VERT"),
+                shaders::EDITOR_FRAG,
+                Some("shaders::EDITOR_FRAG"),
+            )?,
+            label_renderer: TextRenderer::new(&label),
+            label,
+            text_program: shader_program_from_resources!(shaders::TEXT_VERT, shaders::TEXT_FRAG)?,
+            shader_watchers: None,
+            built: None,
+        })
+    }
+
+    // Reads the shaders from their files from now on. See `ShaderWatcher`.
+    pub fn watch_shaders(&mut self) {
+        self.shader_watchers = Some([
+            ShaderWatcher::new("editor.vert", "editor.frag")
+                .preparing_vertex(shaders::with_instance_data::<CellInstance>),
+            ShaderWatcher::new("text.vert", "text.frag"),
+        ]);
+    }
+
+    // Returns whether the shader files are broken.
+    pub fn reload_shaders(&mut self) -> bool {
+        let Some([grid_watcher, text_watcher]) = &mut self.shader_watchers else {
+            return false;
+        };
+
+        // Both are reloaded, so that a broken one doesn't hold up the other.
+        let grid_broken = grid_watcher.reload(&mut self.shader_program);
+        let text_broken = text_watcher.reload(&mut self.text_program);
+        grid_broken || text_broken
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.open
+    }
+
+    pub fn layer(&self) -> usize {
+        self.layer
+    }
+
+    pub fn hovered(&self) -> Option<GridCell> {
+        self.hovered
+    }
+
+    // Opens the editor on the given layer, or closes it. The cursor can be
+    // outside of the arena, so the layer is moved back inside it.
+    pub fn toggle(&mut self, layer: usize) {
+        self.open = !self.open;
+        self.layer = self.layout.clamp_layer(layer);
+        self.hovered = None;
+    }
+
+    pub fn step_layer(&mut self, delta: i32) {
+        self.layer = self
+            .layout
+            .clamp_layer(self.layer.saturating_add_signed(delta as isize));
+    }
+
+    // Rebuilds the grid on the next frame, for when the colours change.
+    pub fn invalidate(&mut self) {
+        self.built = None;
+    }
+
+    pub fn resize(&mut self, width: f32, height: f32) {
+        self.layout = EditorLayout::fit(width, height, self.layout.cells);
+    }
+
+    // Updates which cell the mouse is over.
+    pub fn hover(&mut self, x: f32, y: f32) {
+        self.hovered = if self.open {
+            self.layout.cell_at(x, y)
+        } else {
+            None
+        };
+    }
+
+    // The coordinates of the cell a click at the point should flip, if it
+    // is on the grid.
+    pub fn click(&self, x: f32, y: f32) -> Option<(usize, usize, usize)> {
+        if !self.open {
+            return None;
+        }
+
+        self.layout
+            .cell_at(x, y)
+            .map(|(cell_x, cell_z)| (cell_x, self.layer, cell_z))
+    }
+
+    fn build_instances(&mut self, game: &GameOfLife, palette: &Palette) {
+        let built = (game.revision(), self.layer, self.hovered);
+        if self.built == Some(built) {
+            return;
+        }
+
+        let square = |x: f32, z: f32, scale: f32, color: [f32; 4]| CellInstance {
+            scale,
+            color,
+            ..CellInstance::new(Vec3::new(x + 0.5, z + 0.5, 0.0))
+        };
+        let cells = self.layout.cells;
+        let half = cells as f32 / 2.0;
+
+        self.renderer.remove_all_instances();
+        self.renderer.add_instance(square(
+            half - 0.5,
+            half - 0.5,
+            cells as f32,
+            BACKGROUND_COLOR,
+        ));
+
+        // The hovered cell goes under the live ones, so that a live cell
+        // gets a frame around it.
+        if let Some((x, z)) = self.hovered {
+            self.renderer
+                .add_instance(square(x as f32, z as f32, 1.0, HOVER_COLOR));
+        }
+
+        for x in 0..cells {
+            for z in 0..cells {
+                if game.cell(x, self.layer, z).is_alive() {
+                    self.renderer.add_instance(square(
+                        x as f32,
+                        z as f32,
+                        LIVE_CELL_SCALE,
+                        palette.cell_color(game, x, self.layer, z),
+                    ));
+                }
+            }
+        }
+
+        self.label.clear();
+        if let Some((x, z)) = self.hovered {
+            let text = format!(
+                "{}, {}, {}\n{} neighbours",
+                x,
+                self.layer,
+                z,
+                game.living_neighbours(x, self.layer, z)
+            );
+            let position = self.layout.label_position((x, z), &text);
+            self.label
+                .append_text(&text, position, LABEL_PIXEL_SIZE, HOVER_COLOR);
+        }
+        self.label_renderer.upload(&self.label);
+
+        self.built = Some(built);
+    }
+
+    // Draws the grid with `projection`, which should be `flat_projection`.
+    pub fn render(&mut self, game: &GameOfLife, palette: &Palette, projection: Mat4) {
+        if !self.open {
+            return;
+        }
+
+        self.build_instances(game, palette);
+
+        let program = self.shader_program.use_program();
+        program.set_uniform("projection", projection);
+        program.set_uniform("model", self.layout.model_matrix());
+
+        unsafe { glad_gl::gl::Disable(glad_gl::gl::DEPTH_TEST) };
+        self.renderer.render_many();
+
+        let program = self.text_program.use_program();
+        program.set_uniform("projection", projection);
+        self.label_renderer.render_text();
+        unsafe { glad_gl::gl::Enable(glad_gl::gl::DEPTH_TEST) };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{EditorLayout, EDITOR_MARGIN, LABEL_GAP, LABEL_PIXEL_SIZE};
+    use crate::math::Vec2;
+
+    #[test]
+    fn fits_in_the_top_right_corner() {
+        let layout = EditorLayout::fit(1000.0, 500.0, 100);
+
+        assert_eq!(
+            layout.origin,
+            Vec2::new(1000.0 - 300.0 - EDITOR_MARGIN, EDITOR_MARGIN)
+        );
+        assert_eq!(layout.cell_pixels, 3.0);
+    }
+
+    #[test]
+    fn cells_under_the_mouse() {
+        let layout = EditorLayout {
+            origin: Vec2::new(100.0, 50.0),
+            cell_pixels: 4.0,
+            cells: 128,
+        };
+
+        assert_eq!(layout.cell_at(100.0, 50.0), Some((0, 0)));
+        assert_eq!(layout.cell_at(103.9, 57.0), Some((0, 1)));
+        assert_eq!(layout.cell_at(104.0, 50.0), Some((1, 0)));
+        let end = 4.0 * 128.0;
+        assert_eq!(
+            layout.cell_at(100.0 + end - 0.1, 50.0 + end - 0.1),
+            Some((127, 127))
+        );
+
+        assert_eq!(layout.cell_at(99.9, 60.0), None);
+        assert_eq!(layout.cell_at(120.0, 49.0), None);
+        assert_eq!(layout.cell_at(100.0 + end, 60.0), None);
+    }
+
+    #[test]
+    fn layers_outside_the_arena() {
+        let layout = EditorLayout::fit(1000.0, 500.0, 128);
+
+        assert_eq!(layout.clamp_layer(5), 5);
+        assert_eq!(layout.clamp_layer(127), 127);
+        assert_eq!(layout.clamp_layer(128), 127);
+        assert_eq!(layout.clamp_layer(u32::MAX as usize), 127);
+    }
+
+    #[test]
+    fn label_goes_left_of_the_cell() {
+        let layout = EditorLayout {
+            origin: Vec2::new(100.0, 50.0),
+            cell_pixels: 4.0,
+            cells: 128,
+        };
+
+        // The longest line is 4 characters, each 5 pixels of the font and a
+        // pixel of space.
+        let position = layout.label_position((10, 3), "1, 2\n7");
+        let width = 4.0 * 6.0 * LABEL_PIXEL_SIZE;
+        assert_eq!(
+            position,
+            Vec2::new(100.0 + 40.0 - LABEL_GAP - width, 50.0 + 12.0)
+        );
+    }
+}
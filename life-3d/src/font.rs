@@ -0,0 +1,157 @@
+// A small built-in bitmap font, so that text can be drawn without loading
+// anything. Only upper case letters are drawn, lower case ones use them too.
+
+use crate::math::Vec2;
+
+pub const GLYPH_WIDTH: usize = 5;
+pub const GLYPH_HEIGHT: usize = 7;
+
+// Each glyph sits in a cell one pixel wider and taller than itself, so that
+// sampling the edge of one doesn't pick up its neighbour.
+const CELL_WIDTH: usize = GLYPH_WIDTH + 1;
+const CELL_HEIGHT: usize = GLYPH_HEIGHT + 1;
+
+// A row per byte, top to bottom, with the leftmost pixel in bit 4.
+#[rustfmt::skip]
+const GLYPHS: &[(char, [u8; GLYPH_HEIGHT])] = &[
+    (' ', [0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b00000]),
+    ('!', [0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b00000, 0b00100]),
+    ('%', [0b11000, 0b11001, 0b00010, 0b00100, 0b01000, 0b10011, 0b00011]),
+    ('(', [0b00010, 0b00100, 0b01000, 0b01000, 0b01000, 0b00100, 0b00010]),
+    (')', [0b01000, 0b00100, 0b00010, 0b00010, 0b00010, 0b00100, 0b01000]),
+    ('+', [0b00000, 0b00100, 0b00100, 0b11111, 0b00100, 0b00100, 0b00000]),
+    (',', [0b00000, 0b00000, 0b00000, 0b00000, 0b01100, 0b00100, 0b01000]),
+    ('-', [0b00000, 0b00000, 0b00000, 0b11111, 0b00000, 0b00000, 0b00000]),
+    ('.', [0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b01100, 0b01100]),
+    ('/', [0b00000, 0b00001, 0b00010, 0b00100, 0b01000, 0b10000, 0b00000]),
+    ('0', [0b01110, 0b10001, 0b10011, 0b10101, 0b11001, 0b10001, 0b01110]),
+    ('1', [0b00100, 0b01100, 0b00100, 0b00100, 0b00100, 0b00100, 0b01110]),
+    ('2', [0b01110, 0b10001, 0b00001, 0b00010, 0b00100, 0b01000, 0b11111]),
+    ('3', [0b11111, 0b00010, 0b00100, 0b00010, 0b00001, 0b10001, 0b01110]),
+    ('4', [0b00010, 0b00110, 0b01010, 0b10010, 0b11111, 0b00010, 0b00010]),
+    ('5', [0b11111, 0b10000, 0b11110, 0b00001, 0b00001, 0b10001, 0b01110]),
+    ('6', [0b00110, 0b01000, 0b10000, 0b11110, 0b10001, 0b10001, 0b01110]),
+    ('7', [0b11111, 0b00001, 0b00010, 0b00100, 0b01000, 0b01000, 0b01000]),
+    ('8', [0b01110, 0b10001, 0b10001, 0b01110, 0b10001, 0b10001, 0b01110]),
+    ('9', [0b01110, 0b10001, 0b10001, 0b01111, 0b00001, 0b00010, 0b01100]),
+    (':', [0b00000, 0b01100, 0b01100, 0b00000, 0b01100, 0b01100, 0b00000]),
+    ('=', [0b00000, 0b00000, 0b11111, 0b00000, 0b11111, 0b00000, 0b00000]),
+    ('?', [0b01110, 0b10001, 0b00001, 0b00010, 0b00100, 0b00000, 0b00100]),
+    ('A', [0b01110, 0b10001, 0b10001, 0b11111, 0b10001, 0b10001, 0b10001]),
+    ('B', [0b11110, 0b10001, 0b10001, 0b11110, 0b10001, 0b10001, 0b11110]),
+    ('C', [0b01110, 0b10001, 0b10000, 0b10000, 0b10000, 0b10001, 0b01110]),
+    ('D', [0b11100, 0b10010, 0b10001, 0b10001, 0b10001, 0b10010, 0b11100]),
+    ('E', [0b11111, 0b10000, 0b10000, 0b11110, 0b10000, 0b10000, 0b11111]),
+    ('F', [0b11111, 0b10000, 0b10000, 0b11110, 0b10000, 0b10000, 0b10000]),
+    ('G', [0b01110, 0b10001, 0b10000, 0b10111, 0b10001, 0b10001, 0b01111]),
+    ('H', [0b10001, 0b10001, 0b10001, 0b11111, 0b10001, 0b10001, 0b10001]),
+    ('I', [0b01110, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b01110]),
+    ('J', [0b00111, 0b00010, 0b00010, 0b00010, 0b00010, 0b10010, 0b01100]),
+    ('K', [0b10001, 0b10010, 0b10100, 0b11000, 0b10100, 0b10010, 0b10001]),
+    ('L', [0b10000, 0b10000, 0b10000, 0b10000, 0b10000, 0b10000, 0b11111]),
+    ('M', [0b10001, 0b11011, 0b10101, 0b10101, 0b10001, 0b10001, 0b10001]),
+    ('N', [0b10001, 0b10001, 0b11001, 0b10101, 0b10011, 0b10001, 0b10001]),
+    ('O', [0b01110, 0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b01110]),
+    ('P', [0b11110, 0b10001, 0b10001, 0b11110, 0b10000, 0b10000, 0b10000]),
+    ('Q', [0b01110, 0b10001, 0b10001, 0b10001, 0b10101, 0b10010, 0b01101]),
+    ('R', [0b11110, 0b10001, 0b10001, 0b11110, 0b10100, 0b10010, 0b10001]),
+    ('S', [0b01111, 0b10000, 0b10000, 0b01110, 0b00001, 0b00001, 0b11110]),
+    ('T', [0b11111, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100]),
+    ('U', [0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b01110]),
+    ('V', [0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b01010, 0b00100]),
+    ('W', [0b10001, 0b10001, 0b10001, 0b10101, 0b10101, 0b10101, 0b01010]),
+    ('X', [0b10001, 0b10001, 0b01010, 0b00100, 0b01010, 0b10001, 0b10001]),
+    ('Y', [0b10001, 0b10001, 0b10001, 0b01010, 0b00100, 0b00100, 0b00100]),
+    ('Z', [0b11111, 0b00001, 0b00010, 0b00100, 0b01000, 0b10000, 0b11111]),
+    ('_', [0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b11111]),
+];
+
+// The glyph a character is drawn with, or '?' if the font doesn't have it.
+pub fn glyph_index(character: char) -> usize {
+    let character = character.to_ascii_uppercase();
+    let find = |character| GLYPHS.iter().position(|(c, _)| *c == character);
+
+    find(character).or_else(|| find('?')).unwrap()
+}
+
+// Every glyph side by side in a single row, one byte per pixel, 255 where
+// the glyph is drawn and 0 everywhere else.
+pub struct FontAtlas {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<u8>,
+}
+
+impl FontAtlas {
+    pub fn build() -> FontAtlas {
+        let width = GLYPHS.len() * CELL_WIDTH;
+        let height = CELL_HEIGHT;
+        let mut pixels = vec![0; width * height];
+
+        for (index, (_, rows)) in GLYPHS.iter().enumerate() {
+            for (y, row) in rows.iter().enumerate() {
+                for x in 0..GLYPH_WIDTH {
+                    if row & (1 << (GLYPH_WIDTH - 1 - x)) != 0 {
+                        pixels[y * width + index * CELL_WIDTH + x] = 255;
+                    }
+                }
+            }
+        }
+
+        FontAtlas {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    // The texture coordinates of the top left and bottom right corners of
+    // a glyph.
+    pub fn glyph_uv(&self, index: usize) -> (Vec2, Vec2) {
+        let left = (index * CELL_WIDTH) as f32 / self.width as f32;
+        let right = (index * CELL_WIDTH + GLYPH_WIDTH) as f32 / self.width as f32;
+        let bottom = GLYPH_HEIGHT as f32 / self.height as f32;
+
+        (Vec2::new(left, 0.0), Vec2::new(right, bottom))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{glyph_index, FontAtlas, GLYPHS, GLYPH_WIDTH};
+
+    #[test]
+    fn glyphs_fit_their_cells() {
+        for (character, rows) in GLYPHS {
+            assert!(
+                rows.iter().all(|row| *row < 1 << GLYPH_WIDTH),
+                "{:?} is too wide",
+                character
+            );
+        }
+    }
+
+    #[test]
+    fn lower_case_and_unknown_characters() {
+        assert_eq!(glyph_index('a'), glyph_index('A'));
+        assert_eq!(glyph_index('~'), glyph_index('?'));
+        assert_ne!(glyph_index('0'), glyph_index('O'));
+    }
+
+    #[test]
+    fn atlas_pixels() {
+        let atlas = FontAtlas::build();
+        let index = glyph_index('T');
+        let (top_left, bottom_right) = atlas.glyph_uv(index);
+
+        let x = (top_left.x * atlas.width as f32).round() as usize;
+        assert_eq!(&atlas.pixels[x..x + 6], &[255, 255, 255, 255, 255, 0]);
+        assert_eq!(atlas.pixels[atlas.width + x + 2], 255);
+        assert_eq!(atlas.pixels[atlas.width + x + 1], 0);
+
+        assert_eq!(
+            ((bottom_right.x - top_left.x) * atlas.width as f32).round(),
+            GLYPH_WIDTH as f32
+        );
+    }
+}
@@ -1,13 +1,20 @@
 // The file for the logic behind the game of life.
 
+use std::{fmt, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+
 use crate::{
+    clipping::{Clipping, GHOST_OPACITY},
+    colors::Palette,
     math::{Mat4, Vec3},
-    renderer::Renderer,
+    renderer::{CellInstance, Renderer},
     shader_program_from_resources,
-    shaders::{self, ShaderProgram},
+    shader_reload::ShaderWatcher,
+    shaders::{self, ShaderError, ShaderProgram},
 };
 
-#[derive(Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Cell {
     Alive,
     Dead,
@@ -19,19 +26,38 @@ pub struct Cursor {
     z: u32,
 
     shader_program: ShaderProgram,
+    shader_watcher: Option<ShaderWatcher>,
 }
 
 impl Cursor {
-    pub fn new() -> Cursor {
-        Cursor {
-            x: ARENA_SIZE as u32 / 2,
-            y: ARENA_SIZE as u32 / 2,
-            z: ARENA_SIZE as u32 / 2,
+    // Puts the cursor in the middle of an arena `arena_size` cells across.
+    pub fn new(arena_size: usize) -> Result<Cursor, ShaderError> {
+        Ok(Cursor {
+            x: arena_size as u32 / 2,
+            y: arena_size as u32 / 2,
+            z: arena_size as u32 / 2,
             shader_program: shader_program_from_resources!(
                 shaders::CURSOR_VERT,
                 shaders::CURSOR_FRAG
-            ),
-        }
+            )?,
+            shader_watcher: None,
+        })
+    }
+
+    // Reads the shader from its files from now on. See `ShaderWatcher`.
+    pub fn watch_shaders(&mut self) {
+        self.shader_watcher = Some(ShaderWatcher::new("cursor.vert", "cursor.frag"));
+    }
+
+    // Returns whether the shader files are broken.
+    pub fn reload_shaders(&mut self) -> bool {
+        self.shader_watcher
+            .as_mut()
+            .is_some_and(|watcher| watcher.reload(&mut self.shader_program))
+    }
+
+    pub fn position(&self) -> (u32, u32, u32) {
+        (self.x, self.y, self.z)
     }
 
     pub fn move_x(&mut self, dx: i32) {
@@ -46,6 +72,10 @@ impl Cursor {
         self.z = ((self.z as i32) + dz) as u32
     }
 
+    pub fn set_position(&mut self, (x, y, z): (u32, u32, u32)) {
+        (self.x, self.y, self.z) = (x, y, z);
+    }
+
     pub fn render(
         &self,
         game: &GameOfLife,
@@ -55,26 +85,17 @@ impl Cursor {
         view: &Mat4,
     ) {
         let program = self.shader_program.use_program();
+        let real = |i: u32| GameOfLife::to_real_coords(i as f32, game.size(), cell_size);
 
         program.set_uniform(
             "model",
-            Mat4::translate(
-                GameOfLife::to_real_coords(self.x as f32, cell_size),
-                GameOfLife::to_real_coords(self.y as f32, cell_size),
-                GameOfLife::to_real_coords(self.z as f32, cell_size),
-            ),
+            Mat4::translate(real(self.x), real(self.y), real(self.z)),
         );
         program.set_uniform("view", view);
         program.set_uniform("projection", projection);
 
-        if game
-            .cell(
-                self.x.try_into().unwrap(),
-                self.y.try_into().unwrap(),
-                self.z.try_into().unwrap(),
-            )
-            .is_alive()
-        {
+        let (x, y, z) = (self.x as usize, self.y as usize, self.z as usize);
+        if game.contains(x, y, z) && game.cell(x, y, z).is_alive() {
             program.set_uniform("in_color", Vec3::new(1.0, 1.0, 0.0));
         } else {
             program.set_uniform("in_color", Vec3::new(0.0, 1.0, 0.0));
@@ -97,29 +118,256 @@ impl Cell {
     }
 }
 
-pub const ARENA_SIZE: usize = 128;
-type CellsArray = Vec<[[Cell; ARENA_SIZE]; ARENA_SIZE]>;
+// How many cells the arena is across, unless the config says otherwise.
+pub const DEFAULT_ARENA_SIZE: usize = 128;
+
+// What the cells on the edges of the arena have as neighbours past the edge.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Boundary {
+    // The cells on the opposite edge, as if the arena was a torus.
+    Wrap,
+    // Nothing, as if everything outside the arena was dead.
+    Dead,
+}
+
+// Which numbers of live neighbours bring a dead cell to life, and which keep
+// a live one alive, a bit for each count. Files have them written out, like
+// "B5/S35".
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Rule {
+    birth: u32,
+    survival: u32,
+}
+
+impl Rule {
+    pub const fn new(birth: &[u32], survival: &[u32]) -> Rule {
+        const fn mask(counts: &[u32]) -> u32 {
+            let mut mask = 0;
+            let mut i = 0;
+            while i < counts.len() {
+                mask |= 1 << counts[i];
+                i += 1;
+            }
+            mask
+        }
+
+        Rule {
+            birth: mask(birth),
+            survival: mask(survival),
+        }
+    }
+
+    pub fn is_born(&self, live_neighbours: u32) -> bool {
+        self.birth & (1 << live_neighbours) != 0
+    }
+
+    pub fn survives(&self, live_neighbours: u32) -> bool {
+        self.survival & (1 << live_neighbours) != 0
+    }
+}
+
+// Written the usual way, like B5/S35. Counts past 9 would run into each
+// other, so if the rule has any, every count is separated by commas, like
+// B13,14/S26.
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let commas = (self.birth | self.survival) >> 10 != 0;
+        let counts = |mask: u32| {
+            let counts: Vec<String> = (0..=26)
+                .filter(|count| mask & (1 << count) != 0)
+                .map(|count| count.to_string())
+                .collect();
+
+            if commas {
+                counts.join(",")
+            } else {
+                counts.concat()
+            }
+        };
+
+        write!(f, "B{}/S{}", counts(self.birth), counts(self.survival))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseRuleError(String);
+
+impl fmt::Display for ParseRuleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?} is not a rule like B5/S35", self.0)
+    }
+}
+
+impl std::error::Error for ParseRuleError {}
+
+// Reads rules written the same way as `Display` writes them. Either letter
+// can be lower case. A comma anywhere in the rule means that every count in
+// it is separated by commas, so the S26 in B13,14/S26 is 26 rather than 2
+// and 6. Rules whose only counts past 9 are on their own, like B13/S26, have
+// no commas to tell them apart, and read as single digits.
+impl FromStr for Rule {
+    type Err = ParseRuleError;
+
+    fn from_str(text: &str) -> Result<Rule, ParseRuleError> {
+        let error = || ParseRuleError(text.to_string());
+        let commas = text.contains(',');
+
+        let mask = |counts: &str, letter: char| {
+            let counts = counts
+                .strip_prefix(letter)
+                .or_else(|| counts.strip_prefix(letter.to_ascii_lowercase()))?;
+
+            let counts: Vec<Option<u32>> = if commas {
+                counts
+                    .split(',')
+                    .filter(|count| !count.is_empty())
+                    .map(|count| count.trim().parse().ok())
+                    .collect()
+            } else {
+                // Without commas every digit is a count of its own.
+                counts.chars().map(|digit| digit.to_digit(10)).collect()
+            };
+
+            counts.into_iter().try_fold(0, |mask, count| {
+                let count = count?;
+                (count <= 26).then_some(mask | 1 << count)
+            })
+        };
+
+        let (birth, survival) = text.trim().split_once('/').ok_or_else(error)?;
+
+        Ok(Rule {
+            birth: mask(birth, 'B').ok_or_else(error)?,
+            survival: mask(survival, 'S').ok_or_else(error)?,
+        })
+    }
+}
+
+impl TryFrom<String> for Rule {
+    type Error = ParseRuleError;
+
+    fn try_from(text: String) -> Result<Rule, ParseRuleError> {
+        text.parse()
+    }
+}
+
+impl From<Rule> for String {
+    fn from(rule: Rule) -> String {
+        rule.to_string()
+    }
+}
+
+// The rules that can be picked from, the first one being the default.
+pub const RULES: &[Rule] = &[
+    Rule::new(&[5], &[3, 5]),
+    // Carter Bays' 4555 and 5766.
+    Rule::new(&[5], &[4, 5]),
+    Rule::new(&[6], &[5, 6, 7]),
+];
 
+#[derive(Clone)]
 pub struct GameOfLife {
-    cells: CellsArray,
+    // How many cells the arena is along each axis.
+    size: usize,
+    // Every cell, by y, then x, then z.
+    cells: Vec<Cell>,
+    // How many generations each live cell has survived for, so 0 for cells
+    // that were just born. Laid out the same way as `cells`.
+    ages: Vec<u16>,
+    // Goes up every time any cell might have changed, so that renderers know
+    // when to rebuild their instances.
+    revision: u64,
+    generation: u64,
+    rule: Rule,
+    boundary: Boundary,
 }
 
 impl GameOfLife {
     pub fn new() -> GameOfLife {
+        GameOfLife::with_size(DEFAULT_ARENA_SIZE)
+    }
+
+    // An empty arena `size` cells along each axis.
+    pub fn with_size(size: usize) -> GameOfLife {
         GameOfLife {
-            cells: vec![[[Cell::Dead; ARENA_SIZE]; ARENA_SIZE]; ARENA_SIZE],
+            size,
+            cells: vec![Cell::Dead; size.pow(3)],
+            ages: vec![0; size.pow(3)],
+            revision: 0,
+            generation: 0,
+            rule: RULES[0],
+            boundary: Boundary::Wrap,
         }
     }
 
-    fn clamp_coords(x: i32) -> usize {
-        let arena_max_index = ARENA_SIZE - 1;
+    pub fn size(&self) -> usize {
+        self.size
+    }
 
-        if x < 0 {
-            ((arena_max_index as i32) + x).try_into().unwrap()
-        } else if x > arena_max_index as i32 {
-            (x - (arena_max_index as i32)).try_into().unwrap()
-        } else {
-            x.try_into().unwrap()
+    pub fn revision(&self) -> u64 {
+        self.revision
+    }
+
+    // How many times `update_game` has been called.
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    // For carrying on from a saved game.
+    pub fn set_generation(&mut self, generation: u64) {
+        self.generation = generation;
+    }
+
+    pub fn rule(&self) -> Rule {
+        self.rule
+    }
+
+    pub fn set_rule(&mut self, rule: Rule) {
+        self.rule = rule;
+    }
+
+    pub fn boundary(&self) -> Boundary {
+        self.boundary
+    }
+
+    pub fn set_boundary(&mut self, boundary: Boundary) {
+        self.boundary = boundary;
+    }
+
+    // Kills every cell, and starts counting generations from 0 again.
+    pub fn clear(&mut self) {
+        self.cells.fill(Cell::Dead);
+        self.ages.fill(0);
+
+        self.generation = 0;
+        self.revision += 1;
+    }
+
+    pub fn population(&self) -> usize {
+        self.cells.iter().filter(|cell| cell.is_alive()).count()
+    }
+
+    fn index(&self, x: usize, y: usize, z: usize) -> usize {
+        (y * self.size + x) * self.size + z
+    }
+
+    // Whether the coordinates are inside the arena.
+    pub fn contains(&self, x: usize, y: usize, z: usize) -> bool {
+        x < self.size && y < self.size && z < self.size
+    }
+
+    // Where the neighbour `offset` cells away from `coordinate` is along an
+    // axis, or `None` if it is past the edge and the boundary is dead.
+    fn neighbour_coords(&self, coordinate: usize, offset: i32) -> Option<usize> {
+        let neighbour = coordinate as i32 + offset;
+
+        match self.boundary {
+            Boundary::Wrap => Some(neighbour.rem_euclid(self.size as i32) as usize),
+            Boundary::Dead => (0..self.size as i32)
+                .contains(&neighbour)
+                .then_some(neighbour as usize),
         }
     }
 
@@ -133,9 +381,13 @@ impl GameOfLife {
                         continue;
                     }
 
-                    let neighbour_x = Self::clamp_coords(cell_x as i32 + x_offset);
-                    let neighbour_y = Self::clamp_coords(cell_y as i32 + y_offset);
-                    let neighbour_z = Self::clamp_coords(cell_z as i32 + z_offset);
+                    let (Some(neighbour_x), Some(neighbour_y), Some(neighbour_z)) = (
+                        self.neighbour_coords(cell_x, x_offset),
+                        self.neighbour_coords(cell_y, y_offset),
+                        self.neighbour_coords(cell_z, z_offset),
+                    ) else {
+                        continue;
+                    };
 
                     if self.cell(neighbour_x, neighbour_y, neighbour_z).is_alive() {
                         neighbours_count += 1;
@@ -148,27 +400,27 @@ impl GameOfLife {
     }
 
     pub fn update_game(&mut self) {
-        let mut new_cells = vec![[[Cell::Dead; ARENA_SIZE]; ARENA_SIZE]; ARENA_SIZE];
-
-        for (y, layer) in self.cells().iter().enumerate() {
-            for (x, row) in layer.iter().enumerate() {
-                for (z, cell) in row.iter().enumerate() {
+        let mut new_cells = vec![Cell::Dead; self.cells.len()];
+        let mut new_ages = vec![0; self.ages.len()];
+
+        for y in 0..self.size {
+            for x in 0..self.size {
+                for z in 0..self.size {
+                    let index = self.index(x, y, z);
+                    let cell = self.cells[index];
                     let live_neighbours = self.living_neighbours(x, y, z);
-                    let new_cell = &mut new_cells[y][x][z];
-
-                    if cell.is_alive() {
-                        if live_neighbours < 3 {
-                            *new_cell = Cell::Dead;
-                        } else if live_neighbours == 3 || live_neighbours == 5 {
-                            *new_cell = Cell::Alive;
-                        } else if live_neighbours > 5 {
-                            *new_cell = Cell::Dead;
-                        }
+
+                    let alive = if cell.is_alive() {
+                        self.rule.survives(live_neighbours)
                     } else {
-                        if live_neighbours == 5 {
-                            *new_cell = Cell::Alive;
-                        } else {
-                            *new_cell = Cell::Dead;
+                        self.rule.is_born(live_neighbours)
+                    };
+
+                    if alive {
+                        new_cells[index] = Cell::Alive;
+
+                        if cell.is_alive() {
+                            new_ages[index] = self.ages[index].saturating_add(1);
                         }
                     }
                 }
@@ -176,73 +428,199 @@ impl GameOfLife {
         }
 
         self.cells = new_cells;
+        self.ages = new_ages;
+        self.revision += 1;
+        self.generation += 1;
     }
 
-    pub fn to_real_coords(x: f32, cell_size: f32) -> f32 {
-        (x as f32 * cell_size) - ((ARENA_SIZE / 2) as f32) * cell_size
+    // Which of the 26 cells around a cell are alive, a bit each. The bit for
+    // the cell offset by (dx, dy, dz) is `(dx + 1) * 9 + (dy + 1) * 3 + (dz + 1)`.
+    // This is for drawing, so unlike `living_neighbours` it doesn't wrap
+    // around the edges of the arena.
+    pub fn neighbour_mask(&self, x: usize, y: usize, z: usize) -> u32 {
+        let mut mask = 0;
+
+        for dx in -1..=1 {
+            for dy in -1..=1 {
+                for dz in -1..=1 {
+                    let (nx, ny, nz) = (x as i32 + dx, y as i32 + dy, z as i32 + dz);
+                    let inside = [nx, ny, nz].iter().all(|&n| n >= 0 && n < self.size as i32);
+
+                    if (dx, dy, dz) != (0, 0, 0)
+                        && inside
+                        && self.cell(nx as usize, ny as usize, nz as usize).is_alive()
+                    {
+                        mask |= 1 << ((dx + 1) * 9 + (dy + 1) * 3 + (dz + 1));
+                    }
+                }
+            }
+        }
+
+        mask
+    }
+
+    // Where the centre of the cell at coordinate `x` is along an axis, in an
+    // arena `arena_size` cells across that is centred on the origin.
+    pub fn to_real_coords(x: f32, arena_size: usize, cell_size: f32) -> f32 {
+        (x * cell_size) - ((arena_size / 2) as f32) * cell_size
+    }
+
+    // Calls `f` with the coordinates of every live cell.
+    fn for_each_alive(&self, mut f: impl FnMut(usize, usize, usize)) {
+        for y in 0..self.size {
+            for x in 0..self.size {
+                for z in 0..self.size {
+                    if self.cell(x, y, z).is_alive() {
+                        f(x, y, z);
+                    }
+                }
+            }
+        }
     }
 
-    pub fn render(&self, renderer: &mut Renderer, cell_size: f32, cursor: &Cursor) {
+    // The smallest and largest coordinates of the living cells along each
+    // axis, or `None` if everything is dead.
+    pub fn live_bounds(&self) -> Option<([usize; 3], [usize; 3])> {
+        let mut bounds: Option<([usize; 3], [usize; 3])> = None;
+
+        self.for_each_alive(|x, y, z| {
+            let position = [x, y, z];
+            let (min, max) = bounds.get_or_insert((position, position));
+            for axis in 0..3 {
+                min[axis] = min[axis].min(position[axis]);
+                max[axis] = max[axis].max(position[axis]);
+            }
+        });
+
+        bounds
+    }
+
+    // Rebuilds the instances from the live cells that `opacity` gives an
+    // opacity for, unless the game hasn't changed since they were last built.
+    fn build_instances(
+        &self,
+        renderer: &mut Renderer,
+        cell_size: f32,
+        palette: &Palette,
+        opacity: impl Fn(usize, usize, usize) -> Option<f32>,
+    ) {
+        if renderer.revision() == Some(self.revision) {
+            return;
+        }
+
         renderer.remove_all_instances();
 
-        self.cells().iter().enumerate().for_each(|(y, layer)| {
-            layer.iter().enumerate().for_each(|(x, row)| {
-                row.iter()
-                    .enumerate()
-                    .filter(|(_, cell)| cell.is_alive())
-                    .for_each(|(z, _)| {
-                        if x != cursor.x as usize
-                            || y != cursor.y as usize
-                            || z != cursor.z as usize
-                        {
-                            let (x, y, z) = (
-                                Self::to_real_coords(x as f32, cell_size),
-                                Self::to_real_coords(y as f32, cell_size),
-                                Self::to_real_coords(z as f32, cell_size),
-                            );
-
-                            renderer.add_instance(Vec3::new(x, y, z));
-                        }
-                    })
-            })
+        self.for_each_alive(|x, y, z| {
+            let Some(opacity) = opacity(x, y, z) else {
+                return;
+            };
+
+            let real = |i: usize| Self::to_real_coords(i as f32, self.size, cell_size);
+            let offset = Vec3::new(real(x), real(y), real(z));
+            let [r, g, b, _] = palette.cell_color(self, x, y, z);
+
+            renderer.add_instance(CellInstance {
+                color: [r, g, b, opacity],
+                age: self.age(x, y, z) as u32,
+                neighbours: self.neighbour_mask(x, y, z),
+                ..CellInstance::new(offset)
+            });
+        });
+
+        renderer.set_revision(self.revision);
+    }
+
+    pub fn render(
+        &self,
+        renderer: &mut Renderer,
+        cell_size: f32,
+        palette: &Palette,
+        clipping: &Clipping,
+    ) {
+        self.build_instances(renderer, cell_size, palette, |x, y, z| {
+            clipping.is_visible(x, y, z).then_some(1.0)
         });
 
         renderer.render_many();
     }
-    
+
+    // Draws the layers around the slice see-through. This has to come after
+    // everything else is drawn.
+    pub fn render_ghosts(
+        &self,
+        renderer: &mut Renderer,
+        cell_size: f32,
+        palette: &Palette,
+        clipping: &Clipping,
+    ) {
+        self.build_instances(renderer, cell_size, palette, |x, y, z| {
+            clipping.is_ghost(x, y, z).then_some(GHOST_OPACITY)
+        });
+
+        renderer.render_many_blended();
+    }
+
     pub fn flip_at_cursor(&mut self, cursor: &Cursor) {
-        self.set_cell(
-            cursor.x as usize,
-            cursor.y as usize,
-            cursor.z as usize,
-            if self.cell(cursor.x as usize, cursor.y as usize, cursor.z as usize).is_alive() {
-                Cell::Dead
-            } else {
-                Cell::Alive
-            },
-        );
+        self.flip_cell(cursor.x as usize, cursor.y as usize, cursor.z as usize);
     }
 
-    pub fn cells(&self) -> &CellsArray {
-        &self.cells
+    // Brings a dead cell to life, or kills a live one.
+    pub fn flip_cell(&mut self, x: usize, y: usize, z: usize) {
+        let cell = if self.cell(x, y, z).is_alive() {
+            Cell::Dead
+        } else {
+            Cell::Alive
+        };
+
+        self.set_cell(x, y, z, cell);
     }
 
-    pub fn cells_mut(&mut self) -> &mut CellsArray {
-        &mut self.cells
+    // Makes this game the same as `other`, without allocating like `clone`
+    // would, as long as the two are the same size.
+    pub fn copy_from(&mut self, other: &GameOfLife) {
+        self.size = other.size;
+        self.cells.clone_from(&other.cells);
+        self.ages.clone_from(&other.ages);
+        self.revision = other.revision;
+        self.generation = other.generation;
+        self.rule = other.rule;
+        self.boundary = other.boundary;
     }
 
     pub fn cell(&self, x: usize, y: usize, z: usize) -> Cell {
-        self.cells()[y][x][z]
+        self.cells[self.index(x, y, z)]
     }
 
     pub fn set_cell(&mut self, x: usize, y: usize, z: usize, cell: Cell) {
-        self.cells_mut()[y][x][z] = cell;
+        let index = self.index(x, y, z);
+        self.cells[index] = cell;
+        self.ages[index] = 0;
+        self.revision += 1;
+    }
+
+    // How many generations the cell at the coordinates has been alive for.
+    pub fn age(&self, x: usize, y: usize, z: usize) -> u16 {
+        self.ages[self.index(x, y, z)]
+    }
+
+    // For restoring the ages of the cells in a saved game.
+    pub fn set_age(&mut self, x: usize, y: usize, z: usize, age: u16) {
+        let index = self.index(x, y, z);
+        self.ages[index] = age;
+        self.revision += 1;
+    }
+
+    // The coordinates of every live cell, by y, then x, then z.
+    pub fn live_cells(&self) -> Vec<[usize; 3]> {
+        let mut cells = Vec::new();
+        self.for_each_alive(|x, y, z| cells.push([x, y, z]));
+        cells
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{Cell, GameOfLife};
+    use super::{Boundary, Cell, GameOfLife, Rule, DEFAULT_ARENA_SIZE, RULES};
 
     #[test]
     fn neighbour_count_test() {
@@ -254,4 +632,127 @@ mod tests {
 
         assert_eq!(game.living_neighbours(3, 3, 3), 3);
     }
+
+    #[test]
+    fn live_bounds_test() {
+        let mut game = Box::new(GameOfLife::new());
+        assert_eq!(game.live_bounds(), None);
+
+        game.set_cell(3, 40, 7, Cell::Alive);
+        assert_eq!(game.live_bounds(), Some(([3, 40, 7], [3, 40, 7])));
+
+        game.set_cell(10, 2, 5, Cell::Alive);
+        assert_eq!(game.revision(), 2);
+        game.set_cell(5, 20, 90, Cell::Alive);
+        assert_eq!(game.live_bounds(), Some(([3, 2, 5], [10, 40, 90])));
+    }
+
+    #[test]
+    fn neighbour_mask_test() {
+        let mut game = Box::new(GameOfLife::new());
+        game.set_cell(5, 5, 5, Cell::Alive);
+        game.set_cell(6, 5, 5, Cell::Alive);
+        game.set_cell(4, 4, 6, Cell::Alive);
+        game.set_cell(0, 0, 0, Cell::Alive);
+
+        assert_eq!(game.neighbour_mask(5, 5, 5), 1 << 22 | 1 << 2);
+        // Nothing wraps around to the far side.
+        assert_eq!(game.neighbour_mask(DEFAULT_ARENA_SIZE - 1, 0, 0), 0);
+        assert_eq!(game.neighbour_mask(1, 1, 1), 1);
+    }
+
+    #[test]
+    fn age_test() {
+        let mut game = Box::new(GameOfLife::new());
+
+        // A flat 2x2 square is stable, as every cell of it has 3 neighbours.
+        for (x, z) in [(4, 4), (4, 5), (5, 4), (5, 5)] {
+            game.set_cell(x, 10, z, Cell::Alive);
+        }
+        assert_eq!(game.population(), 4);
+        assert_eq!(game.generation(), 0);
+
+        game.update_game();
+        assert!(game.cell(4, 10, 4).is_alive());
+        assert_eq!(game.age(4, 10, 4), 1);
+
+        game.update_game();
+        assert_eq!(game.age(5, 10, 5), 2);
+        assert_eq!(game.population(), 4);
+        assert_eq!(game.generation(), 2);
+
+        game.set_cell(5, 10, 5, Cell::Alive);
+        assert_eq!(game.age(5, 10, 5), 0);
+    }
+
+    #[test]
+    fn rules() {
+        let rule = RULES[0];
+        assert_eq!(rule.to_string(), "B5/S35");
+        assert!(rule.is_born(5));
+        assert!(!rule.is_born(3));
+        assert!(rule.survives(3) && rule.survives(5));
+        assert!(!rule.survives(4));
+
+        assert_eq!(Rule::new(&[13, 14], &[26]).to_string(), "B13,14/S26");
+    }
+
+    #[test]
+    fn parse_rules() {
+        for rule in RULES {
+            assert_eq!(rule.to_string().parse::<Rule>(), Ok(*rule));
+        }
+
+        assert_eq!("b6/s567".parse::<Rule>(), Ok(RULES[2]));
+        let big = Rule::new(&[13, 14], &[26]);
+        assert_eq!(big.to_string().parse(), Ok(big));
+        assert_eq!("B13,14/S26".parse(), Ok(big));
+        let mixed = Rule::new(&[5], &[2, 16]);
+        assert_eq!(mixed.to_string(), "B5/S2,16");
+        assert_eq!(mixed.to_string().parse(), Ok(mixed));
+        assert_eq!("B/S".parse(), Ok(Rule::new(&[], &[])));
+        // Without commas, every digit is a count.
+        assert_eq!("B5/S26".parse(), Ok(Rule::new(&[5], &[2, 6])));
+
+        for invalid in ["", "B5", "S35/B5", "B5/S3x", "B27,/S3", "B1,x/S3", "Bé/S3"] {
+            assert!(invalid.parse::<Rule>().is_err(), "{:?}", invalid);
+        }
+    }
+
+    #[test]
+    fn boundaries() {
+        let mut game = GameOfLife::with_size(16);
+        game.set_cell(15, 0, 0, Cell::Alive);
+        game.set_cell(0, 15, 15, Cell::Alive);
+
+        assert_eq!(game.living_neighbours(0, 0, 0), 2);
+
+        game.set_boundary(Boundary::Dead);
+        assert_eq!(game.living_neighbours(0, 0, 0), 0);
+        assert_eq!(game.living_neighbours(14, 0, 0), 1);
+    }
+
+    #[test]
+    fn corners_wrap_to_the_opposite_corner() {
+        let mut game = GameOfLife::with_size(16);
+        game.set_cell(15, 15, 15, Cell::Alive);
+        // One in from the far corner, which is where wrapping used to end
+        // up, and isn't a neighbour.
+        game.set_cell(14, 14, 14, Cell::Alive);
+
+        assert_eq!(game.living_neighbours(0, 0, 0), 1);
+        assert_eq!(game.living_neighbours(15, 15, 15), 1);
+        assert_eq!(game.living_neighbours(0, 15, 0), 1);
+    }
+
+    #[test]
+    fn clear_test() {
+        let mut game = Box::new(GameOfLife::new());
+        game.set_cell(1, 2, 3, Cell::Alive);
+        let revision = game.revision();
+
+        game.clear();
+        assert_eq!(game.population(), 0);
+        assert!(game.revision() > revision);
+    }
 }
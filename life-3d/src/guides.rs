@@ -0,0 +1,201 @@
+// Lines that help to find your way around the arena: a box around its edges,
+// a grid on the floor of the cursor's layer, and a gizmo in the corner of the
+// screen showing which way the axes point.
+
+use glad_gl::gl;
+
+use crate::{
+    game::GameOfLife,
+    math::{Mat4, Vec3},
+    renderer::{LineMesh, LineRenderer},
+    shader_program_from_resources,
+    shader_reload::ShaderWatcher,
+    shaders::{self, ShaderError, ShaderProgram},
+};
+
+const BOUNDS_COLOR: [f32; 4] = [0.6, 0.6, 0.6, 1.0];
+const GRID_COLOR: [f32; 4] = [0.2, 0.2, 0.25, 1.0];
+const AXIS_COLORS: [[f32; 4]; 3] = [
+    [1.0, 0.25, 0.25, 1.0],
+    [0.25, 1.0, 0.25, 1.0],
+    [0.3, 0.5, 1.0, 1.0],
+];
+
+// The gizmo's square in the bottom left corner, in framebuffer pixels.
+const GIZMO_PIXELS: i32 = 100;
+const GIZMO_MARGIN: i32 = 20;
+
+// Where a cell's faces are along an axis, given its coordinate.
+fn cell_edge(coordinate: usize, side: f32, arena_size: usize, cell_size: f32) -> f32 {
+    GameOfLife::to_real_coords(coordinate as f32, arena_size, cell_size) + side * cell_size / 2.0
+}
+
+// The box just around every cell in the arena.
+pub fn arena_bounds(arena_size: usize, cell_size: f32) -> LineMesh {
+    let min = cell_edge(0, -1.0, arena_size, cell_size);
+    let max = cell_edge(arena_size - 1, 1.0, arena_size, cell_size);
+
+    let mut mesh = LineMesh::new();
+    mesh.append_box(
+        Vec3::new(min, min, min),
+        Vec3::new(max, max, max),
+        BOUNDS_COLOR,
+    );
+    mesh
+}
+
+// A line between every row and column of cells, underneath layer 0. It is
+// moved up to the cursor's layer when it is drawn.
+pub fn floor_grid(arena_size: usize, cell_size: f32) -> LineMesh {
+    let min = cell_edge(0, -1.0, arena_size, cell_size);
+    let max = cell_edge(arena_size - 1, 1.0, arena_size, cell_size);
+
+    let mut mesh = LineMesh::new();
+    for i in 0..=arena_size {
+        let position = min + i as f32 * cell_size;
+
+        mesh.append_line(
+            Vec3::new(position, min, min),
+            Vec3::new(position, min, max),
+            GRID_COLOR,
+        );
+        mesh.append_line(
+            Vec3::new(min, min, position),
+            Vec3::new(max, min, position),
+            GRID_COLOR,
+        );
+    }
+
+    mesh
+}
+
+// A line from the origin along each of x, y and z, coloured red, green and
+// blue.
+pub fn axis_gizmo() -> LineMesh {
+    let mut mesh = LineMesh::new();
+    let axes = [
+        Vec3::new(1.0, 0.0, 0.0),
+        Vec3::new(0.0, 1.0, 0.0),
+        Vec3::new(0.0, 0.0, 1.0),
+    ];
+
+    for (axis, color) in axes.into_iter().zip(AXIS_COLORS) {
+        mesh.append_line(Vec3::new(0.0, 0.0, 0.0), axis, color);
+    }
+
+    mesh
+}
+
+pub struct Guides {
+    bounds: LineRenderer,
+    grid: LineRenderer,
+    gizmo: LineRenderer,
+    shader_program: ShaderProgram,
+    shader_watcher: Option<ShaderWatcher>,
+    show_grid: bool,
+}
+
+impl Guides {
+    pub fn new(arena_size: usize, cell_size: f32) -> Result<Guides, ShaderError> {
+        Ok(Guides {
+            bounds: LineRenderer::new(&arena_bounds(arena_size, cell_size)),
+            grid: LineRenderer::new(&floor_grid(arena_size, cell_size)),
+            gizmo: LineRenderer::new(&axis_gizmo()),
+            shader_program: shader_program_from_resources!(shaders::LINE_VERT, shaders::LINE_FRAG)?,
+            shader_watcher: None,
+            show_grid: false,
+        })
+    }
+
+    // Reads the shaders from their files from now on. See `ShaderWatcher`.
+    pub fn watch_shaders(&mut self) {
+        self.shader_watcher = Some(ShaderWatcher::new("line.vert", "line.frag"));
+    }
+
+    // Returns whether the shader files are broken.
+    pub fn reload_shaders(&mut self) -> bool {
+        self.shader_watcher
+            .as_mut()
+            .is_some_and(|watcher| watcher.reload(&mut self.shader_program))
+    }
+
+    pub fn toggle_grid(&mut self) {
+        self.show_grid = !self.show_grid;
+    }
+
+    // Draws the bounds, and the grid under `layer` if it is shown.
+    pub fn render(&self, layer: usize, cell_size: f32, projection: Mat4, view: Mat4) {
+        let program = self.shader_program.use_program();
+        program.set_uniform("model", Mat4::new(1.0));
+        program.set_uniform("view", view);
+        program.set_uniform("projection", projection);
+
+        self.bounds.render_lines();
+
+        if self.show_grid {
+            program.set_uniform("model", Mat4::translate(0.0, layer as f32 * cell_size, 0.0));
+            self.grid.render_lines();
+        }
+    }
+
+    // Draws the gizmo turned the same way as the arena is through `view`.
+    pub fn render_gizmo(&self, view: Mat4) {
+        let program = self.shader_program.use_program();
+        program.set_uniform("model", Mat4::new(1.0));
+        program.set_uniform("view", view.without_translation());
+        program.set_uniform(
+            "projection",
+            Mat4::orthographic(-1.2, 1.2, 1.2, -1.2, 2.0, -2.0),
+        );
+
+        unsafe {
+            let mut viewport = [0; 4];
+            gl::GetIntegerv(gl::VIEWPORT, viewport.as_mut_ptr());
+
+            gl::Viewport(GIZMO_MARGIN, GIZMO_MARGIN, GIZMO_PIXELS, GIZMO_PIXELS);
+            gl::Disable(gl::DEPTH_TEST);
+            self.gizmo.render_lines();
+            gl::Enable(gl::DEPTH_TEST);
+
+            gl::Viewport(viewport[0], viewport[1], viewport[2], viewport[3]);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{arena_bounds, floor_grid};
+    use crate::game::{GameOfLife, DEFAULT_ARENA_SIZE};
+
+    #[test]
+    fn bounds_surround_every_cell() {
+        let cell_size = 0.5;
+        let bounds = arena_bounds(DEFAULT_ARENA_SIZE, cell_size);
+        let first = GameOfLife::to_real_coords(0.0, DEFAULT_ARENA_SIZE, cell_size);
+        let last = GameOfLife::to_real_coords(
+            (DEFAULT_ARENA_SIZE - 1) as f32,
+            DEFAULT_ARENA_SIZE,
+            cell_size,
+        );
+
+        for position in bounds.positions() {
+            for coordinate in [position.x, position.y, position.z] {
+                assert!(
+                    coordinate == first - cell_size / 2.0 || coordinate == last + cell_size / 2.0
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn grid_lines_between_cells() {
+        let grid = floor_grid(32, 1.0);
+        assert_eq!(grid.line_count(), 2 * (32 + 1));
+
+        let first = GameOfLife::to_real_coords(0.0, 32, 1.0);
+        for position in grid.positions() {
+            assert_eq!(position.y, first - 0.5);
+            assert_eq!((position.x - first).fract().abs(), 0.5);
+        }
+    }
+}
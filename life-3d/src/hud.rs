@@ -0,0 +1,218 @@
+// The text under the speed bars, saying what the game is doing.
+
+use crate::{
+    game::{GameOfLife, Rule},
+    math::{Mat4, Vec2},
+    renderer::{TextMesh, TextRenderer},
+    scheduler::Rate,
+    shader_program_from_resources,
+    shader_reload::ShaderWatcher,
+    shaders::{self, ShaderError, ShaderProgram},
+};
+
+const HUD_POSITION: Vec2 = Vec2 { x: 40.0, y: 170.0 };
+// How many pixels on the screen each pixel of the font takes up.
+const HUD_PIXEL_SIZE: f32 = 2.0;
+const HUD_COLOR: [f32; 4] = [0.9, 0.9, 0.9, 1.0];
+
+// How often the frame rate shown is worked out again, in seconds.
+const FPS_INTERVAL: f32 = 0.5;
+
+// Everything the HUD shows.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HudStatus {
+    pub generation: u64,
+    pub population: usize,
+    pub rule: Rule,
+    pub paused: bool,
+    pub rate: Rate,
+    pub cursor: (u32, u32, u32),
+    pub fps: f32,
+    // Something that has gone wrong, shown under everything else.
+    pub message: Option<String>,
+}
+
+impl HudStatus {
+    pub fn text(&self) -> String {
+        let state = if self.paused {
+            "Paused".to_string()
+        } else {
+            format!("Running at {}", self.rate)
+        };
+        let (x, y, z) = self.cursor;
+
+        let mut text = format!(
+            "Generation {}\nPopulation {}\nRule {}\n{}\nCursor {}, {}, {}\n{:.0} FPS",
+            self.generation, self.population, self.rule, state, x, y, z, self.fps
+        );
+
+        if let Some(message) = &self.message {
+            text += &format!("\n\n{}", message);
+        }
+
+        text
+    }
+}
+
+// Averages the frame rate over a short while, so the number doesn't flicker.
+#[derive(Debug, Default)]
+pub struct FpsCounter {
+    frames: u32,
+    elapsed: f32,
+    fps: f32,
+}
+
+impl FpsCounter {
+    pub fn frame(&mut self, delta_time: f32) -> f32 {
+        self.frames += 1;
+        self.elapsed += delta_time;
+
+        if self.elapsed >= FPS_INTERVAL {
+            self.fps = self.frames as f32 / self.elapsed;
+            self.frames = 0;
+            self.elapsed = 0.0;
+        }
+
+        self.fps
+    }
+}
+
+pub struct Hud {
+    text: TextMesh,
+    renderer: TextRenderer,
+    shader_program: ShaderProgram,
+    shader_watcher: Option<ShaderWatcher>,
+    fps: FpsCounter,
+    // Counting the cells is slow, so only do it when they change. This is
+    // the game revision it was counted at, and the count.
+    population: Option<(u64, usize)>,
+    // What was last uploaded to `renderer`.
+    drawn: Option<HudStatus>,
+    message: Option<String>,
+}
+
+impl Hud {
+    pub fn new() -> Result<Hud, ShaderError> {
+        let text = TextMesh::new();
+
+        Ok(Hud {
+            renderer: TextRenderer::new(&text),
+            text,
+            shader_program: shader_program_from_resources!(shaders::TEXT_VERT, shaders::TEXT_FRAG)?,
+            shader_watcher: None,
+            fps: FpsCounter::default(),
+            population: None,
+            drawn: None,
+            message: None,
+        })
+    }
+
+    // Reads the shaders from their files from now on. See `ShaderWatcher`.
+    pub fn watch_shaders(&mut self) {
+        self.shader_watcher = Some(ShaderWatcher::new("text.vert", "text.frag"));
+    }
+
+    // Returns whether the shader files are broken.
+    pub fn reload_shaders(&mut self) -> bool {
+        self.shader_watcher
+            .as_mut()
+            .is_some_and(|watcher| watcher.reload(&mut self.shader_program))
+    }
+
+    pub fn set_message(&mut self, message: Option<&str>) {
+        self.message = message.map(str::to_string);
+    }
+
+    fn population(&mut self, game: &GameOfLife) -> usize {
+        match self.population {
+            Some((revision, population)) if revision == game.revision() => population,
+            _ => {
+                let population = game.population();
+                self.population = Some((game.revision(), population));
+                population
+            }
+        }
+    }
+
+    pub fn render(
+        &mut self,
+        game: &GameOfLife,
+        paused: bool,
+        rate: Rate,
+        cursor: (u32, u32, u32),
+        delta_time: f32,
+        projection: Mat4,
+    ) {
+        let status = HudStatus {
+            generation: game.generation(),
+            population: self.population(game),
+            rule: game.rule(),
+            paused,
+            rate,
+            cursor,
+            fps: self.fps.frame(delta_time),
+            message: self.message.clone(),
+        };
+
+        if self.drawn.as_ref() != Some(&status) {
+            self.text.clear();
+            self.text
+                .append_text(&status.text(), HUD_POSITION, HUD_PIXEL_SIZE, HUD_COLOR);
+            self.renderer.upload(&self.text);
+            self.drawn = Some(status);
+        }
+
+        let program = self.shader_program.use_program();
+        program.set_uniform("projection", projection);
+
+        unsafe { glad_gl::gl::Disable(glad_gl::gl::DEPTH_TEST) };
+        self.renderer.render_text();
+        unsafe { glad_gl::gl::Enable(glad_gl::gl::DEPTH_TEST) };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{FpsCounter, HudStatus};
+    use crate::{game::RULES, scheduler::Rate};
+
+    #[test]
+    fn status_text() {
+        let mut status = HudStatus {
+            generation: 12,
+            population: 345,
+            rule: RULES[0],
+            paused: true,
+            rate: Rate::PerSecond(0.5),
+            cursor: (1, 2, 3),
+            fps: 59.7,
+            message: None,
+        };
+
+        assert_eq!(
+            status.text(),
+            "Generation 12\nPopulation 345\nRule B5/S35\nPaused\nCursor 1, 2, 3\n60 FPS"
+        );
+
+        status.paused = false;
+        assert!(status.text().contains("\nRunning at 0.5 gen/s\n"));
+
+        status.rate = Rate::Unlimited;
+        assert!(status.text().contains("\nRunning at full speed\n"));
+
+        status.message = Some("Shader error".to_string());
+        assert!(status.text().ends_with("60 FPS\n\nShader error"));
+    }
+
+    #[test]
+    fn fps_is_averaged() {
+        let mut counter = FpsCounter::default();
+        for _ in 0..3 {
+            assert_eq!(counter.frame(0.125), 0.0);
+        }
+
+        // Half a second over four frames.
+        assert_eq!(counter.frame(0.125), 8.0);
+        assert_eq!(counter.frame(0.25), 8.0);
+    }
+}
@@ -0,0 +1,636 @@
+// What the keys and mouse buttons do. Everything is bound to an `Action`,
+// and the bindings can be changed in a file next to the palette. Keys are
+// named here rather than with GLFW's types, so that none of this needs a
+// window.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt, fs, io,
+    path::Path,
+    str::FromStr,
+};
+
+use serde::Deserialize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Action {
+    TogglePause,
+    Step,
+    StepBatch,
+    Faster,
+    Slower,
+    FlipCell,
+    // Move the cursor, or the camera while flying.
+    Forward,
+    Back,
+    Left,
+    Right,
+    Up,
+    Down,
+    RandomCursor,
+    ToggleRenderMode,
+    NextColorMode,
+    ToggleGrid,
+    ToggleEditor,
+    EditorLayerUp,
+    EditorLayerDown,
+    // Cycle the clipping plane along an axis.
+    ClipX,
+    ClipY,
+    ClipZ,
+    ToggleSlice,
+    SliceDown,
+    SliceUp,
+    ToggleHeadlight,
+    ToggleArcball,
+    ToggleFly,
+    // Fly faster or slower while these are held.
+    FlyFast,
+    FlySlow,
+    PlayFlythrough,
+    FocusCursor,
+    FrameCells,
+    TopView,
+    // Numbered from 1 to 9.
+    Bookmark(u8),
+    SaveBookmark(u8),
+    // Press the controls, or flip a cell in the editor.
+    Click,
+    // Move the camera while these are held.
+    RotateCamera,
+    PanCamera,
+}
+
+const BOOKMARK_SLOTS: u8 = 9;
+
+impl Action {
+    // Every action there is.
+    pub fn all() -> Vec<Action> {
+        use Action::*;
+
+        let mut actions = vec![
+            TogglePause,
+            Step,
+            StepBatch,
+            Faster,
+            Slower,
+            FlipCell,
+            Forward,
+            Back,
+            Left,
+            Right,
+            Up,
+            Down,
+            RandomCursor,
+            ToggleRenderMode,
+            NextColorMode,
+            ToggleGrid,
+            ToggleEditor,
+            EditorLayerUp,
+            EditorLayerDown,
+            ClipX,
+            ClipY,
+            ClipZ,
+            ToggleSlice,
+            SliceDown,
+            SliceUp,
+            ToggleHeadlight,
+            ToggleArcball,
+            ToggleFly,
+            FlyFast,
+            FlySlow,
+            PlayFlythrough,
+            FocusCursor,
+            FrameCells,
+            TopView,
+            Click,
+            RotateCamera,
+            PanCamera,
+        ];
+        actions.extend((1..=BOOKMARK_SLOTS).map(Bookmark));
+        actions.extend((1..=BOOKMARK_SLOTS).map(SaveBookmark));
+        actions
+    }
+}
+
+// The names used in the bindings file.
+impl fmt::Display for Action {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Action::TogglePause => "toggle_pause",
+            Action::Step => "step",
+            Action::StepBatch => "step_batch",
+            Action::Faster => "faster",
+            Action::Slower => "slower",
+            Action::FlipCell => "flip_cell",
+            Action::Forward => "forward",
+            Action::Back => "back",
+            Action::Left => "left",
+            Action::Right => "right",
+            Action::Up => "up",
+            Action::Down => "down",
+            Action::RandomCursor => "random_cursor",
+            Action::ToggleRenderMode => "toggle_render_mode",
+            Action::NextColorMode => "next_color_mode",
+            Action::ToggleGrid => "toggle_grid",
+            Action::ToggleEditor => "toggle_editor",
+            Action::EditorLayerUp => "editor_layer_up",
+            Action::EditorLayerDown => "editor_layer_down",
+            Action::ClipX => "clip_x",
+            Action::ClipY => "clip_y",
+            Action::ClipZ => "clip_z",
+            Action::ToggleSlice => "toggle_slice",
+            Action::SliceDown => "slice_down",
+            Action::SliceUp => "slice_up",
+            Action::ToggleHeadlight => "toggle_headlight",
+            Action::ToggleArcball => "toggle_arcball",
+            Action::ToggleFly => "toggle_fly",
+            Action::FlyFast => "fly_fast",
+            Action::FlySlow => "fly_slow",
+            Action::PlayFlythrough => "play_flythrough",
+            Action::FocusCursor => "focus_cursor",
+            Action::FrameCells => "frame_cells",
+            Action::TopView => "top_view",
+            Action::Bookmark(slot) => return write!(f, "bookmark_{}", slot),
+            Action::SaveBookmark(slot) => return write!(f, "save_bookmark_{}", slot),
+            Action::Click => "click",
+            Action::RotateCamera => "rotate_camera",
+            Action::PanCamera => "pan_camera",
+        };
+
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for Action {
+    type Err = BindingError;
+
+    fn from_str(name: &str) -> Result<Action, BindingError> {
+        Action::all()
+            .into_iter()
+            .find(|action| action.to_string() == name)
+            .ok_or_else(|| BindingError::UnknownAction(name.to_string()))
+    }
+}
+
+// The keys that can be bound, by the names used in the bindings file.
+#[rustfmt::skip]
+pub const KEYS: &[&str] = &[
+    "a", "b", "c", "d", "e", "f", "g", "h", "i", "j", "k", "l", "m",
+    "n", "o", "p", "q", "r", "s", "t", "u", "v", "w", "x", "y", "z",
+    "0", "1", "2", "3", "4", "5", "6", "7", "8", "9",
+    "space", "enter", "tab", "escape", "backspace", "insert", "delete",
+    "home", "end", "page_up", "page_down",
+    "arrow_up", "arrow_down", "arrow_left", "arrow_right",
+    "period", "comma", "minus", "equal", "slash", "backslash", "semicolon",
+    "apostrophe", "grave_accent", "left_bracket", "right_bracket",
+    "kp_0", "kp_1", "kp_2", "kp_3", "kp_4", "kp_5", "kp_6", "kp_7", "kp_8", "kp_9",
+    "kp_add", "kp_subtract", "kp_multiply", "kp_divide", "kp_decimal", "kp_enter",
+    "f1", "f2", "f3", "f4", "f5", "f6", "f7", "f8", "f9", "f10", "f11", "f12",
+    "left_shift", "right_shift", "left_control", "right_control", "left_alt", "right_alt",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MouseButton {
+    Left,
+    Right,
+    Middle,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Input {
+    // One of `KEYS`.
+    Key(&'static str),
+    Mouse(MouseButton),
+}
+
+impl fmt::Display for Input {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Input::Key(name) => write!(f, "{}", name),
+            Input::Mouse(MouseButton::Left) => write!(f, "mouse_left"),
+            Input::Mouse(MouseButton::Right) => write!(f, "mouse_right"),
+            Input::Mouse(MouseButton::Middle) => write!(f, "mouse_middle"),
+        }
+    }
+}
+
+impl FromStr for Input {
+    type Err = BindingError;
+
+    fn from_str(name: &str) -> Result<Input, BindingError> {
+        match name {
+            "mouse_left" => Ok(Input::Mouse(MouseButton::Left)),
+            "mouse_right" => Ok(Input::Mouse(MouseButton::Right)),
+            "mouse_middle" => Ok(Input::Mouse(MouseButton::Middle)),
+            _ => KEYS
+                .iter()
+                .find(|key| **key == name)
+                .map(|key| Input::Key(key))
+                .ok_or_else(|| BindingError::UnknownInput(name.to_string())),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Modifiers {
+    pub shift: bool,
+    pub control: bool,
+    pub alt: bool,
+}
+
+impl Modifiers {
+    pub const NONE: Modifiers = Modifiers {
+        shift: false,
+        control: false,
+        alt: false,
+    };
+
+    // Whether every modifier in `self` is also in `other`.
+    pub fn within(self, other: Modifiers) -> bool {
+        (!self.shift || other.shift) && (!self.control || other.control) && (!self.alt || other.alt)
+    }
+}
+
+// An input, along with the modifiers that have to be held for it, written
+// like "ctrl+shift+n" in the bindings file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Trigger {
+    pub input: Input,
+    pub modifiers: Modifiers,
+}
+
+impl Trigger {
+    pub fn new(input: Input, modifiers: Modifiers) -> Trigger {
+        Trigger { input, modifiers }
+    }
+}
+
+impl fmt::Display for Trigger {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.modifiers.control {
+            write!(f, "ctrl+")?;
+        }
+        if self.modifiers.alt {
+            write!(f, "alt+")?;
+        }
+        if self.modifiers.shift {
+            write!(f, "shift+")?;
+        }
+
+        write!(f, "{}", self.input)
+    }
+}
+
+impl FromStr for Trigger {
+    type Err = BindingError;
+
+    fn from_str(text: &str) -> Result<Trigger, BindingError> {
+        let lowercase = text.trim().to_ascii_lowercase();
+        let mut parts: Vec<&str> = lowercase.split('+').map(str::trim).collect();
+        let input = parts.pop().unwrap().parse()?;
+        let mut modifiers = Modifiers::NONE;
+
+        for part in parts {
+            match part {
+                "shift" => modifiers.shift = true,
+                "ctrl" | "control" => modifiers.control = true,
+                "alt" => modifiers.alt = true,
+                _ => return Err(BindingError::UnknownInput(part.to_string())),
+            }
+        }
+
+        Ok(Trigger { input, modifiers })
+    }
+}
+
+#[derive(Debug)]
+pub enum BindingError {
+    Io(io::Error),
+    Parse(toml::de::Error),
+    UnknownAction(String),
+    UnknownInput(String),
+    // Two actions bound to the same trigger.
+    Conflict {
+        trigger: Trigger,
+        first: Action,
+        second: Action,
+    },
+}
+
+impl fmt::Display for BindingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BindingError::Io(error) => write!(f, "{}", error),
+            BindingError::Parse(error) => write!(f, "invalid bindings file: {}", error),
+            BindingError::UnknownAction(name) => write!(f, "there is no action called {:?}", name),
+            BindingError::UnknownInput(name) => {
+                write!(f, "there is no key or mouse button called {:?}", name)
+            }
+            BindingError::Conflict {
+                trigger,
+                first,
+                second,
+            } => write!(f, "{} is bound to both {} and {}", trigger, first, second),
+        }
+    }
+}
+
+impl std::error::Error for BindingError {}
+
+impl From<io::Error> for BindingError {
+    fn from(error: io::Error) -> Self {
+        BindingError::Io(error)
+    }
+}
+
+// The keys everything is bound to when the bindings file doesn't say
+// otherwise.
+const DEFAULT_BINDINGS: &[(Action, &[&str])] = &[
+    (Action::TogglePause, &["space"]),
+    (Action::Step, &["n"]),
+    (Action::StepBatch, &["shift+n"]),
+    (Action::Faster, &["kp_add", "equal"]),
+    (Action::Slower, &["kp_subtract", "minus"]),
+    (Action::FlipCell, &["enter"]),
+    (Action::Forward, &["w"]),
+    (Action::Back, &["s"]),
+    (Action::Left, &["a"]),
+    (Action::Right, &["d"]),
+    (Action::Up, &["q"]),
+    (Action::Down, &["e"]),
+    (Action::RandomCursor, &["r"]),
+    (Action::ToggleRenderMode, &["m"]),
+    (Action::NextColorMode, &["v"]),
+    (Action::ToggleGrid, &["b"]),
+    (Action::ToggleEditor, &["tab"]),
+    (Action::EditorLayerUp, &["page_up"]),
+    (Action::EditorLayerDown, &["page_down"]),
+    (Action::ClipX, &["x"]),
+    (Action::ClipY, &["y"]),
+    (Action::ClipZ, &["z"]),
+    (Action::ToggleSlice, &["g"]),
+    (Action::SliceDown, &["left_bracket"]),
+    (Action::SliceUp, &["right_bracket"]),
+    (Action::ToggleHeadlight, &["l"]),
+    (Action::ToggleArcball, &["c"]),
+    (Action::ToggleFly, &["f"]),
+    (Action::FlyFast, &["left_shift"]),
+    (Action::FlySlow, &["left_control"]),
+    (Action::PlayFlythrough, &["p"]),
+    (Action::FocusCursor, &["period"]),
+    (Action::FrameCells, &["home"]),
+    (Action::TopView, &["t"]),
+    (Action::Click, &["mouse_left"]),
+    (Action::RotateCamera, &["mouse_middle"]),
+    (Action::PanCamera, &["shift+mouse_middle"]),
+];
+
+// Either a single trigger or a list of them.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum TriggerList {
+    One(String),
+    Many(Vec<String>),
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct BindingsFile {
+    #[serde(default)]
+    bindings: BTreeMap<String, TriggerList>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Bindings {
+    actions: HashMap<Trigger, Action>,
+}
+
+impl Bindings {
+    // Fails if two actions share a trigger. The same action can be given
+    // the same trigger twice.
+    pub fn new(
+        bindings: impl IntoIterator<Item = (Action, Trigger)>,
+    ) -> Result<Bindings, BindingError> {
+        let mut actions = HashMap::new();
+
+        for (action, trigger) in bindings {
+            match actions.insert(trigger, action) {
+                Some(first) if first != action => {
+                    return Err(BindingError::Conflict {
+                        trigger,
+                        first,
+                        second: action,
+                    })
+                }
+                _ => {}
+            }
+        }
+
+        Ok(Bindings { actions })
+    }
+
+    fn defaults() -> Vec<(Action, Trigger)> {
+        let mut bindings: Vec<(Action, Trigger)> = DEFAULT_BINDINGS
+            .iter()
+            .flat_map(|(action, triggers)| {
+                triggers
+                    .iter()
+                    .map(|trigger| (*action, trigger.parse().unwrap()))
+            })
+            .collect();
+
+        // The number keys go to bookmarks, and save them with Ctrl.
+        for slot in 1..=BOOKMARK_SLOTS {
+            let key: Input = slot.to_string().parse().unwrap();
+            let control = Modifiers {
+                control: true,
+                ..Modifiers::NONE
+            };
+
+            bindings.push((Action::Bookmark(slot), Trigger::new(key, Modifiers::NONE)));
+            bindings.push((Action::SaveBookmark(slot), Trigger::new(key, control)));
+        }
+
+        bindings
+    }
+
+    // Loads the bindings from `path`, or the defaults if there is no such
+    // file.
+    pub fn load(path: &Path) -> Result<Bindings, BindingError> {
+        match fs::read_to_string(path) {
+            Ok(contents) => Bindings::from_toml(&contents),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(Bindings::default()),
+            Err(error) => Err(error.into()),
+        }
+    }
+
+    // Any action the file lists loses its default triggers, the rest keep
+    // theirs. An empty list unbinds an action.
+    pub fn from_toml(contents: &str) -> Result<Bindings, BindingError> {
+        let file: BindingsFile = toml::from_str(contents).map_err(BindingError::Parse)?;
+        let mut overridden = Vec::new();
+        let mut overrides = Vec::new();
+
+        for (name, triggers) in file.bindings {
+            let action: Action = name.parse()?;
+            overridden.push(action);
+            let triggers = match triggers {
+                TriggerList::One(trigger) => vec![trigger],
+                TriggerList::Many(triggers) => triggers,
+            };
+
+            for trigger in triggers {
+                overrides.push((action, trigger.parse()?));
+            }
+        }
+
+        let defaults = Bindings::defaults()
+            .into_iter()
+            .filter(|(action, _)| !overridden.contains(action));
+
+        Bindings::new(defaults.chain(overrides))
+    }
+
+    // What pressing the input with these modifiers does. When nothing is
+    // bound to that exact combination, the modifiers are ignored, so that
+    // holding Shift doesn't stop the other keys from working.
+    pub fn action(&self, trigger: Trigger) -> Option<Action> {
+        self.actions
+            .get(&trigger)
+            .or_else(|| {
+                self.actions
+                    .get(&Trigger::new(trigger.input, Modifiers::NONE))
+            })
+            .copied()
+    }
+
+    pub fn triggers(&self, action: Action) -> impl Iterator<Item = Trigger> + '_ {
+        self.actions
+            .iter()
+            .filter(move |(_, bound)| **bound == action)
+            .map(|(trigger, _)| *trigger)
+    }
+
+    // Whether any of the action's inputs is held down, along with at least
+    // the modifiers it's bound with.
+    pub fn is_held(
+        &self,
+        action: Action,
+        modifiers: Modifiers,
+        is_down: impl Fn(Input) -> bool,
+    ) -> bool {
+        self.triggers(action)
+            .any(|trigger| trigger.modifiers.within(modifiers) && is_down(trigger.input))
+    }
+}
+
+impl Default for Bindings {
+    fn default() -> Bindings {
+        Bindings::new(Bindings::defaults()).unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Action, BindingError, Bindings, Input, Modifiers, MouseButton, Trigger};
+
+    fn trigger(text: &str) -> Trigger {
+        text.parse().unwrap()
+    }
+
+    #[test]
+    fn names_round_trip() {
+        for action in Action::all() {
+            assert_eq!(action.to_string().parse::<Action>().unwrap(), action);
+        }
+
+        for text in ["ctrl+shift+n", "alt+mouse_middle", "kp_add", "page_up"] {
+            assert_eq!(trigger(text).to_string(), text);
+        }
+        assert_eq!(trigger("Shift + N"), trigger("shift+n"));
+        assert_eq!(trigger("control+1"), trigger("ctrl+1"));
+    }
+
+    #[test]
+    fn default_dispatch() {
+        let bindings = Bindings::default();
+        let shift = Modifiers {
+            shift: true,
+            ..Modifiers::NONE
+        };
+
+        assert_eq!(bindings.action(trigger("n")), Some(Action::Step));
+        assert_eq!(bindings.action(trigger("shift+n")), Some(Action::StepBatch));
+        assert_eq!(
+            bindings.action(trigger("ctrl+3")),
+            Some(Action::SaveBookmark(3))
+        );
+        assert_eq!(bindings.action(trigger("3")), Some(Action::Bookmark(3)));
+        // Nothing is bound to Shift+Space, so it does what Space does.
+        assert_eq!(
+            bindings.action(trigger("shift+space")),
+            Some(Action::TogglePause)
+        );
+        assert_eq!(bindings.action(trigger("equal")), Some(Action::Faster));
+        assert_eq!(bindings.action(trigger("f12")), None);
+
+        let middle = |input| input == Input::Mouse(MouseButton::Middle);
+        assert!(bindings.is_held(Action::RotateCamera, Modifiers::NONE, middle));
+        assert!(!bindings.is_held(Action::PanCamera, Modifiers::NONE, middle));
+        assert!(bindings.is_held(Action::PanCamera, shift, middle));
+        assert!(!bindings.is_held(Action::Click, shift, middle));
+    }
+
+    #[test]
+    fn file_overrides_defaults() {
+        let bindings = Bindings::from_toml(
+            r#"
+            [bindings]
+            toggle_pause = "p"
+            play_flythrough = ["shift+p", "f5"]
+            random_cursor = []
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(bindings.action(trigger("p")), Some(Action::TogglePause));
+        assert_eq!(bindings.action(trigger("space")), None);
+        assert_eq!(bindings.action(trigger("f5")), Some(Action::PlayFlythrough));
+        assert_eq!(bindings.action(trigger("r")), None);
+        assert_eq!(bindings.action(trigger("n")), Some(Action::Step));
+    }
+
+    #[test]
+    fn bad_bindings() {
+        assert!(matches!(
+            Bindings::from_toml("[bindings]\nwarp_speed = \"w\""),
+            Err(BindingError::UnknownAction(name)) if name == "warp_speed"
+        ));
+        assert!(matches!(
+            Bindings::from_toml("[bindings]\nstep = \"hyper+n\""),
+            Err(BindingError::UnknownInput(name)) if name == "hyper"
+        ));
+        assert!(matches!(
+            Bindings::from_toml("[bindings]\nstep = \"numpad_plus\""),
+            Err(BindingError::UnknownInput(_))
+        ));
+        assert!(matches!(
+            Bindings::from_toml("[bindings]\nstep = 5"),
+            Err(BindingError::Parse(_))
+        ));
+
+        // W already moves the cursor forward.
+        match Bindings::from_toml("[bindings]\nstep = \"w\"") {
+            Err(BindingError::Conflict {
+                trigger: bound,
+                first,
+                second,
+            }) => {
+                assert_eq!(bound, trigger("w"));
+                assert_eq!([first, second], [Action::Forward, Action::Step]);
+            }
+            other => panic!("expected a conflict, got {:?}", other),
+        }
+    }
+}
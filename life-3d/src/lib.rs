@@ -1,6 +1,24 @@
+pub mod bookmarks;
 pub mod buffers;
 pub mod camera;
+pub mod clipping;
+pub mod colors;
+pub mod config;
+pub mod controller;
+pub mod editor;
+pub mod font;
 pub mod game;
+pub mod guides;
+pub mod hud;
+pub mod input;
+pub mod lighting;
 pub mod math;
+pub mod mesher;
+pub mod pattern;
 pub mod renderer;
+pub mod scheduler;
+pub mod session;
+pub mod shader_reload;
 pub mod shaders;
+pub mod simulation;
+pub mod ui;
@@ -0,0 +1,74 @@
+// The light the cells are shaded with: a single directional light, plus some
+// ambient light so that the faces turned away from it aren't pitch black.
+
+use crate::{
+    math::{Mat4, Vec3, Vec4},
+    shaders::UsedShaderProgram,
+};
+
+pub struct Lighting {
+    // The way the light travels, in world space.
+    pub direction: Vec3,
+    pub ambient: f32,
+    pub diffuse: f32,
+    // Whether the light shines from the camera instead, so that whatever is
+    // being looked at is always lit.
+    pub headlight: bool,
+}
+
+impl Default for Lighting {
+    fn default() -> Lighting {
+        Lighting {
+            direction: Vec3::new(-0.4, -1.0, -0.6).normalize(),
+            ambient: 0.3,
+            diffuse: 0.7,
+            headlight: false,
+        }
+    }
+}
+
+impl Lighting {
+    pub fn toggle_headlight(&mut self) {
+        self.headlight = !self.headlight;
+    }
+
+    // The way the light travels for a camera with the given view matrix.
+    pub fn light_direction(&self, view: &Mat4) -> Vec3 {
+        if !self.headlight {
+            return self.direction;
+        }
+
+        // The camera looks down -z in view space.
+        match view.inverse() {
+            Some(camera) => (camera * Vec4::new(0.0, 0.0, -1.0, 0.0)).xyz().normalize(),
+            None => self.direction,
+        }
+    }
+
+    pub fn set_uniforms(&self, program: &UsedShaderProgram<'_>, view: &Mat4) {
+        program.set_uniform("light_direction", self.light_direction(view));
+        program.set_uniform("ambient", self.ambient);
+        program.set_uniform("diffuse", self.diffuse);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Lighting;
+    use crate::math::{Mat4, Vec3};
+
+    #[test]
+    fn headlight_follows_the_camera() {
+        let eye = Vec3::new(3.0, 4.0, -2.0);
+        let target = Vec3::new(0.5, -1.0, 1.0);
+        let view = Mat4::look_at(&eye, &target, &Vec3::new(0.0, 1.0, 0.0));
+
+        let mut lighting = Lighting::default();
+        assert_eq!(lighting.light_direction(&view), lighting.direction);
+
+        lighting.toggle_headlight();
+        let expected = (target - eye).normalize();
+        let direction = lighting.light_direction(&view);
+        assert!((direction - expected).len() < 1e-5);
+    }
+}
@@ -1,17 +1,38 @@
 use std::{
     ffi::{c_char, c_void, CStr},
+    fmt, fs, io,
+    path::Path,
     ptr::null,
+    thread::{self, JoinHandle},
 };
 
 use glad_gl::gl;
 use glfw::Context;
 
 use life_3d::{
-    camera::ThirdPersonCamera,
-    game::{Cursor, GameOfLife},
-    math::{Mat4, Vec3},
-    renderer::{BarRenderer, BarsMesh, Mesh, Renderer},
-    shader_program_from_resources, shaders,
+    bookmarks::{Bookmark, Bookmarks, Flythrough},
+    camera::{ActiveCamera, ThirdPersonCamera, FLY_FAST_MULTIPLIER, FLY_SLOW_MULTIPLIER},
+    clipping::Clipping,
+    colors::Palette,
+    config::{self, Config},
+    controller::{CameraController, CameraInput, CameraSettings},
+    editor::LayerEditor,
+    game::{Cursor, GameOfLife, RULES},
+    guides::Guides,
+    hud::Hud,
+    input::{Action, Bindings, Input, Modifiers, MouseButton, Trigger},
+    lighting::Lighting,
+    math::{Mat4, Vec2, Vec3},
+    mesher::{ChunkMesher, RenderMode},
+    pattern::Pattern,
+    renderer::{screen_projection, BarRenderer, BarsMesh, CellInstance, Mesh, Renderer},
+    scheduler::{Scheduler, SPEEDS, STEP_BATCH},
+    session::Session,
+    shader_program_from_resources,
+    shader_reload::ShaderWatcher,
+    shaders::{self, ShaderProgram},
+    simulation::{Command, Simulation},
+    ui::{Panel, UiEvent, UiRenderer, Widget},
 };
 use rand::Rng;
 
@@ -31,15 +52,297 @@ extern "system" fn opengl_debug_callback(
     );
 }
 
-fn main() {
-    let mut debug_opengl = false;
+// Where the camera bookmarks and paths are kept, relative to the working
+// directory.
+const BOOKMARKS_FILE: &str = "bookmarks.toml";
+
+// What the keys and mouse buttons do, also relative to the working
+// directory.
+const BINDINGS_FILE: &str = "bindings.toml";
+
+// Where the game is saved when it's closed, to carry on from the next time,
+// and where it's saved every so often in case it never gets closed properly.
+// The recovery file is removed again once the session file is saved.
+const SESSION_FILE: &str = "session.toml";
+const RECOVERY_FILE: &str = "session.recovery.toml";
+
+// How long the camera takes to get from one bookmark to the next, when
+// flying through all of them.
+const SECONDS_PER_BOOKMARK: f32 = 3.0;
+
+// The names the bindings file uses for GLFW's keys.
+const KEY_NAMES: &[(glfw::Key, &str)] = &[
+    (glfw::Key::A, "a"),
+    (glfw::Key::B, "b"),
+    (glfw::Key::C, "c"),
+    (glfw::Key::D, "d"),
+    (glfw::Key::E, "e"),
+    (glfw::Key::F, "f"),
+    (glfw::Key::G, "g"),
+    (glfw::Key::H, "h"),
+    (glfw::Key::I, "i"),
+    (glfw::Key::J, "j"),
+    (glfw::Key::K, "k"),
+    (glfw::Key::L, "l"),
+    (glfw::Key::M, "m"),
+    (glfw::Key::N, "n"),
+    (glfw::Key::O, "o"),
+    (glfw::Key::P, "p"),
+    (glfw::Key::Q, "q"),
+    (glfw::Key::R, "r"),
+    (glfw::Key::S, "s"),
+    (glfw::Key::T, "t"),
+    (glfw::Key::U, "u"),
+    (glfw::Key::V, "v"),
+    (glfw::Key::W, "w"),
+    (glfw::Key::X, "x"),
+    (glfw::Key::Y, "y"),
+    (glfw::Key::Z, "z"),
+    (glfw::Key::Num0, "0"),
+    (glfw::Key::Num1, "1"),
+    (glfw::Key::Num2, "2"),
+    (glfw::Key::Num3, "3"),
+    (glfw::Key::Num4, "4"),
+    (glfw::Key::Num5, "5"),
+    (glfw::Key::Num6, "6"),
+    (glfw::Key::Num7, "7"),
+    (glfw::Key::Num8, "8"),
+    (glfw::Key::Num9, "9"),
+    (glfw::Key::Space, "space"),
+    (glfw::Key::Enter, "enter"),
+    (glfw::Key::Tab, "tab"),
+    (glfw::Key::Escape, "escape"),
+    (glfw::Key::Backspace, "backspace"),
+    (glfw::Key::Insert, "insert"),
+    (glfw::Key::Delete, "delete"),
+    (glfw::Key::Home, "home"),
+    (glfw::Key::End, "end"),
+    (glfw::Key::PageUp, "page_up"),
+    (glfw::Key::PageDown, "page_down"),
+    (glfw::Key::Up, "arrow_up"),
+    (glfw::Key::Down, "arrow_down"),
+    (glfw::Key::Left, "arrow_left"),
+    (glfw::Key::Right, "arrow_right"),
+    (glfw::Key::Period, "period"),
+    (glfw::Key::Comma, "comma"),
+    (glfw::Key::Minus, "minus"),
+    (glfw::Key::Equal, "equal"),
+    (glfw::Key::Slash, "slash"),
+    (glfw::Key::Backslash, "backslash"),
+    (glfw::Key::Semicolon, "semicolon"),
+    (glfw::Key::Apostrophe, "apostrophe"),
+    (glfw::Key::GraveAccent, "grave_accent"),
+    (glfw::Key::LeftBracket, "left_bracket"),
+    (glfw::Key::RightBracket, "right_bracket"),
+    (glfw::Key::Kp0, "kp_0"),
+    (glfw::Key::Kp1, "kp_1"),
+    (glfw::Key::Kp2, "kp_2"),
+    (glfw::Key::Kp3, "kp_3"),
+    (glfw::Key::Kp4, "kp_4"),
+    (glfw::Key::Kp5, "kp_5"),
+    (glfw::Key::Kp6, "kp_6"),
+    (glfw::Key::Kp7, "kp_7"),
+    (glfw::Key::Kp8, "kp_8"),
+    (glfw::Key::Kp9, "kp_9"),
+    (glfw::Key::KpAdd, "kp_add"),
+    (glfw::Key::KpSubtract, "kp_subtract"),
+    (glfw::Key::KpMultiply, "kp_multiply"),
+    (glfw::Key::KpDivide, "kp_divide"),
+    (glfw::Key::KpDecimal, "kp_decimal"),
+    (glfw::Key::KpEnter, "kp_enter"),
+    (glfw::Key::F1, "f1"),
+    (glfw::Key::F2, "f2"),
+    (glfw::Key::F3, "f3"),
+    (glfw::Key::F4, "f4"),
+    (glfw::Key::F5, "f5"),
+    (glfw::Key::F6, "f6"),
+    (glfw::Key::F7, "f7"),
+    (glfw::Key::F8, "f8"),
+    (glfw::Key::F9, "f9"),
+    (glfw::Key::F10, "f10"),
+    (glfw::Key::F11, "f11"),
+    (glfw::Key::F12, "f12"),
+    (glfw::Key::LeftShift, "left_shift"),
+    (glfw::Key::RightShift, "right_shift"),
+    (glfw::Key::LeftControl, "left_control"),
+    (glfw::Key::RightControl, "right_control"),
+    (glfw::Key::LeftAlt, "left_alt"),
+    (glfw::Key::RightAlt, "right_alt"),
+];
+
+fn key_input(key: glfw::Key) -> Option<Input> {
+    let (_, name) = KEY_NAMES.iter().find(|(named, _)| *named == key)?;
+    name.parse().ok()
+}
+
+fn mouse_input(button: glfw::MouseButton) -> Option<Input> {
+    match button {
+        glfw::MouseButtonLeft => Some(Input::Mouse(MouseButton::Left)),
+        glfw::MouseButtonRight => Some(Input::Mouse(MouseButton::Right)),
+        glfw::MouseButtonMiddle => Some(Input::Mouse(MouseButton::Middle)),
+        _ => None,
+    }
+}
+
+fn modifiers(modifiers: glfw::Modifiers) -> Modifiers {
+    Modifiers {
+        shift: modifiers.contains(glfw::Modifiers::Shift),
+        control: modifiers.contains(glfw::Modifiers::Control),
+        alt: modifiers.contains(glfw::Modifiers::Alt),
+    }
+}
+
+fn is_down(window: &glfw::Window, input: Input) -> bool {
+    match input {
+        Input::Key(name) => KEY_NAMES
+            .iter()
+            .any(|(key, named)| *named == name && window.get_key(*key) == glfw::Action::Press),
+        Input::Mouse(button) => {
+            let button = match button {
+                MouseButton::Left => glfw::MouseButtonLeft,
+                MouseButton::Right => glfw::MouseButtonRight,
+                MouseButton::Middle => glfw::MouseButtonMiddle,
+            };
+            window.get_mouse_button(button) == glfw::Action::Press
+        }
+    }
+}
+
+// The modifiers being held right now, for actions that last as long as
+// their input is held.
+fn held_modifiers(window: &glfw::Window) -> Modifiers {
+    let held = |left, right| {
+        window.get_key(left) == glfw::Action::Press || window.get_key(right) == glfw::Action::Press
+    };
+
+    Modifiers {
+        shift: held(glfw::Key::LeftShift, glfw::Key::RightShift),
+        control: held(glfw::Key::LeftControl, glfw::Key::RightControl),
+        alt: held(glfw::Key::LeftAlt, glfw::Key::RightAlt),
+    }
+}
+
+// The widgets on the control panel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Control {
+    Play,
+    Step,
+    Speed,
+    Rule,
+    Clear,
+}
+
+// What to tell the simulation when a control is used.
+fn control_command(control: Control, event: UiEvent) -> Option<Command> {
+    match (control, event) {
+        (Control::Play, UiEvent::Toggled(on)) => Some(Command::SetPaused(!on)),
+        (Control::Step, UiEvent::Pressed) => Some(Command::Step(1)),
+        // Speeds are numbered from 1 on the panel too.
+        (Control::Speed, UiEvent::Changed(speed)) => Some(Command::SetSpeed(speed as usize - 1)),
+        // The rules are numbered from 1 on the panel.
+        (Control::Rule, UiEvent::Changed(rule)) => Some(Command::SetRule(RULES[rule as usize - 1])),
+        (Control::Clear, UiEvent::Pressed) => Some(Command::Clear),
+        _ => None,
+    }
+}
+
+fn flip_at_cursor(cursor: &Cursor) -> Command {
+    let (x, y, z) = cursor.position();
+
+    Command::FlipCell {
+        x: x as usize,
+        y: y as usize,
+        z: z as usize,
+    }
+}
+
+// Reads the config file, and then the options on the command line over it.
+fn load_config(args: &[String]) -> Result<Config, String> {
+    let path = config::config_path(args).map_err(|error| error.to_string())?;
+    let mut config = Config::load(&path)
+        .map_err(|error| format!("Failed to load {}: {}", path.display(), error))?;
+
+    config.apply_args(args).map_err(|error| error.to_string())?;
+    config.validate().map_err(|error| error.to_string())?;
+    Ok(config)
+}
+
+// The arena as the config sets it up, with the pattern in it if there is one.
+fn initial_game(config: &Config) -> Result<GameOfLife, String> {
+    let mut game = GameOfLife::with_size(config.arena.size);
+    game.set_rule(config.arena.rule);
+    game.set_boundary(config.arena.boundary);
+
+    if let Some(path) = &config.arena.pattern {
+        Pattern::load(path)
+            .and_then(|pattern| pattern.place(&mut game))
+            .map_err(|error| format!("Failed to load {}: {}", path.display(), error))?;
+    }
+
+    Ok(game)
+}
 
-    for args in std::env::args() {
-        if args == "--debug-opengl" {
-            debug_opengl = true;
+// The last game, and its arena. A recovery file is only left behind when the
+// game wasn't closed properly, so it's newer than the session file if it's
+// there.
+fn restore_session() -> Option<(Session, GameOfLife)> {
+    for file in [RECOVERY_FILE, SESSION_FILE] {
+        let restored = Session::load(Path::new(file)).and_then(|session| match session {
+            Some(session) => session.game().map(|game| Some((session, game))),
+            None => Ok(None),
+        });
+
+        match restored {
+            Ok(Some(restored)) => {
+                if file == RECOVERY_FILE {
+                    eprintln!(
+                        "The last game wasn't closed properly, carrying on from {}",
+                        RECOVERY_FILE
+                    );
+                }
+                return Some(restored);
+            }
+            Ok(None) => {}
+            Err(error) => eprintln!("Failed to restore {}: {}", file, error),
         }
     }
 
+    None
+}
+
+// For anything that stops the game from starting, like a broken config or
+// a shader that doesn't compile.
+fn exit_with(error: impl fmt::Display) -> ! {
+    eprintln!("{}", error);
+    std::process::exit(1);
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+
+    if config::wants_help(&args) {
+        print!("{}", config::usage());
+        return;
+    }
+
+    let config = load_config(&args).unwrap_or_else(|error| exit_with(error));
+
+    // A restored game takes the place of the arena settings in the config file.
+    // The arena options on the command line turn restoring off instead.
+    let restored = if config.session.restore {
+        restore_session()
+    } else {
+        None
+    };
+    let (game, session) = match restored {
+        Some((session, game)) => (game, Some(session)),
+        None => (
+            initial_game(&config).unwrap_or_else(|error| exit_with(error)),
+            None,
+        ),
+    };
+    let arena_size = game.size();
+
     let mut glfw = glfw::init(|error, message| eprintln!("[GLFW ERROR {:?}]: {}", error, message))
         .expect("Failed to initialize GLFW");
 
@@ -48,29 +351,53 @@ fn main() {
     ));
     glfw.window_hint(glfw::WindowHint::ContextVersion(4, 6));
 
-    if debug_opengl {
+    if config.window.debug_opengl {
         glfw.window_hint(glfw::WindowHint::OpenGlDebugContext(true));
     }
 
     let (mut window, events) = glfw.with_primary_monitor(|glfw, monitor| {
         let monitor = monitor.unwrap();
         let video_mode = monitor.get_video_mode().unwrap();
-        let width = (0.75 * video_mode.width as f32) as u32;
-        let height = ((9.0 / 16.0) * width as f32) as u32;
 
-        glfw.create_window(width, height, "Life 3D", glfw::WindowMode::Windowed)
+        // Fullscreen windows take the screen's size, whatever the config
+        // says.
+        let (width, height, mode) = if config.window.fullscreen {
+            (
+                video_mode.width,
+                video_mode.height,
+                glfw::WindowMode::FullScreen(monitor),
+            )
+        } else {
+            let width = config
+                .window
+                .width
+                .unwrap_or((0.75 * video_mode.width as f32) as u32);
+            let height = config
+                .window
+                .height
+                .unwrap_or(((9.0 / 16.0) * width as f32) as u32);
+
+            (width, height, glfw::WindowMode::Windowed)
+        };
+
+        glfw.create_window(width, height, "Life 3D", mode)
             .expect("Failed to create the GLFW window.")
     });
 
     window.set_framebuffer_size_polling(true);
     window.set_scroll_polling(true);
     window.set_key_polling(true);
+    window.set_mouse_button_polling(true);
 
     window.make_current();
-    glfw.set_swap_interval(glfw::SwapInterval::Sync(1));
+    glfw.set_swap_interval(if config.window.vsync {
+        glfw::SwapInterval::Sync(1)
+    } else {
+        glfw::SwapInterval::None
+    });
     gl::load(|sym| glfw.get_proc_address_raw(sym));
 
-    if debug_opengl {
+    if config.window.debug_opengl {
         unsafe {
             gl::Enable(gl::DEBUG_OUTPUT);
             gl::Enable(gl::DEBUG_OUTPUT_SYNCHRONOUS);
@@ -86,61 +413,214 @@ fn main() {
         }
     }
 
-    let shader_program = shader_program_from_resources!(shaders::MAIN_VERT, shaders::MAIN_FRAG);
-    const CELL_SIZE: f32 = 0.1;
-    let cell = Mesh::cube(CELL_SIZE);
+    let mut shader_program = ShaderProgram::new(
+        &shaders::with_instance_data::<CellInstance>(shaders::MAIN_VERT),
+        Some("shaders::MAIN_VERT"),
+        shaders::MAIN_FRAG,
+        Some("shaders::MAIN_FRAG"),
+    )
+    .unwrap_or_else(|error| exit_with(error));
+    let cell_size = config.view.cell_size;
+    let cell = Mesh::cube(cell_size);
     let mut renderer = Renderer::new(&cell);
+    let mut chunk_mesher = ChunkMesher::new(cell_size, arena_size);
+    let mut render_mode = RenderMode::Meshed;
+    let mut lighting = Lighting::default();
+    // The layers around the slice are drawn separately, after everything
+    // else, as they are see-through.
+    let mut ghost_renderer = Renderer::new(&cell);
+    let mut clipping = Clipping::new(arena_size);
+    let mut drawn_clipping = clipping.revision();
 
     let window_size = window.get_size();
     let (window_width, window_height) = window_size;
     let (window_width, window_height): (f32, f32) = (window_width as f32, window_height as f32);
 
-    let mut projection =
-        life_3d::math::Mat4::perspective(window_width / window_height, 0.1, 100.0, 45.0);
+    let mut aspect_ratio = window_width / window_height;
+    let mut field_of_view = config.view.fov;
 
-    let mut flat_projection = Mat4::orthographic(
-        0.0,
-        window_width.into(),
-        0.0,
-        window_width.into(),
-        1.0,
-        -1.0,
-    );
+    let mut flat_projection = screen_projection(window_width, window_height);
 
     let (mut previous_mouse_x, mut previous_mouse_y) = (0.0, 0.0);
     let mut has_set_mouse_x = false;
 
-    let mut game = GameOfLife::new();
+    let scheduler = session
+        .as_ref()
+        .map_or_else(Scheduler::default, Session::scheduler);
+    let simulation = Simulation::spawn(game, scheduler);
+    let mut snapshot = simulation.latest();
 
     let mut bar_mesh = BarsMesh::new();
-    (0..5).for_each(|_| {
+    SPEEDS.iter().for_each(|_| {
         bar_mesh.append_bar(100.0, 20.0);
     });
     let bar_renderer = BarRenderer::new(&bar_mesh);
-    let bar_shader = shader_program_from_resources!(shaders::CURSOR_VERT, shaders::FLAT_FRAG);
+    let mut bar_shader = shader_program_from_resources!(shaders::CURSOR_VERT, shaders::FLAT_FRAG)
+        .unwrap_or_else(|error| exit_with(error));
 
     // let mut camera = Camera::new(&Vec3::new(0.0, 0.0, 3.0), &Vec3::new(0.0, 0.0, -1.0));
 
     let mut delta_time;
     let mut previous_time = 0.0;
 
-    let max_tick_progress = 0.25;
-    let mut tick_progress = 0.25;
-    let mut tick_speed = 1;
-    let mut paused = true;
     let mut random_cursor = false;
 
     let mut rng = rand::thread_rng();
 
-    let mut cursor = Cursor::new();
+    let mut cursor = Cursor::new(arena_size).unwrap_or_else(|error| exit_with(error));
+
+    let mut camera = ActiveCamera::ThirdPerson(ThirdPersonCamera::new(
+        Vec3::new(0.0, 0.0, 0.0),
+        5.0,
+        0.0,
+        0.0,
+    ));
+
+    if let Some(session) = &session {
+        camera = session.camera.camera();
+        field_of_view = session.camera.fov;
+        cursor.set_position(session.cursor());
+    }
+
+    let mut camera_controller =
+        CameraController::new(CameraSettings::default(), window_height, field_of_view);
+    let mut dragging_camera = false;
+
+    let bookmarks_path = Path::new(BOOKMARKS_FILE);
+    let mut bookmarks = Bookmarks::load(bookmarks_path).unwrap_or_else(|error| {
+        eprintln!("Failed to load {}: {}", BOOKMARKS_FILE, error);
+        Bookmarks::default()
+    });
+    let mut flythrough: Option<Flythrough> = None;
+
+    let palette_path = &config.colors.palette;
+    let mut palette = Palette::load(palette_path).unwrap_or_else(|error| {
+        eprintln!("Failed to load {}: {}", palette_path.display(), error);
+        Palette::default()
+    });
+    if let Some(mode) = config.colors.mode {
+        palette.mode = mode;
+    }
+
+    let bindings = Bindings::load(Path::new(BINDINGS_FILE)).unwrap_or_else(|error| {
+        eprintln!("Failed to load {}: {}", BINDINGS_FILE, error);
+        Bindings::default()
+    });
+
+    let (framebuffer_width, framebuffer_height) = window.get_framebuffer_size();
+    let mut guides = Guides::new(arena_size, cell_size).unwrap_or_else(|error| exit_with(error));
+    let mut hud = Hud::new().unwrap_or_else(|error| exit_with(error));
+
+    let mut controls = Panel::new(Vec2::new(40.0, 300.0));
+    controls.add(
+        Control::Play,
+        Widget::toggle("Play", !snapshot.scheduler.is_paused()),
+    );
+    controls.add(Control::Step, Widget::button("Step"));
+    controls.add(
+        Control::Speed,
+        Widget::slider(
+            "Speed",
+            snapshot.scheduler.speed() as u32 + 1,
+            1,
+            SPEEDS.len() as u32,
+        ),
+    );
+    controls.add(
+        Control::Rule,
+        Widget::number_field(
+            "Rule",
+            // A rule from the config that isn't one of `RULES` shows as the
+            // first, until another is picked.
+            RULES
+                .iter()
+                .position(|rule| *rule == snapshot.game.rule())
+                .map_or(1, |i| i as u32 + 1),
+            1,
+            RULES.len() as u32,
+        ),
+    );
+    controls.add(Control::Clear, Widget::button("Clear"));
+    let mut controls_renderer = UiRenderer::new().unwrap_or_else(|error| exit_with(error));
+    let mut editor = LayerEditor::new(
+        framebuffer_width as f32,
+        framebuffer_height as f32,
+        arena_size,
+    )
+    .unwrap_or_else(|error| exit_with(error));
+
+    // While working on the shaders, they are read from their files instead,
+    // and every program is swapped for a new one whenever its files change
+    // and it still builds.
+    let mut shader_watchers = config.window.reload_shaders.then(|| {
+        cursor.watch_shaders();
+        guides.watch_shaders();
+        hud.watch_shaders();
+        controls_renderer.watch_shaders();
+        editor.watch_shaders();
 
-    let mut camera = ThirdPersonCamera::new(Vec3::new(0.0, 0.0, 0.0), 5.0, 0.0, 0.0);
+        (
+            ShaderWatcher::new("main.vert", "main.frag")
+                .preparing_vertex(shaders::with_instance_data::<CellInstance>),
+            ShaderWatcher::new("cursor.vert", "flat.frag"),
+        )
+    });
+
+    let autosave_interval = config.session.autosave as f64;
+    let mut previous_autosave = 0.0;
+    let mut autosave: Option<JoinHandle<()>> = None;
 
     while !window.should_close() {
         let current_time = glfw.get_time();
         delta_time = current_time - previous_time;
         previous_time = current_time;
 
+        // Whatever generation the simulation thread has got to.
+        snapshot = simulation.latest();
+        let game = &snapshot.game;
+
+        if let Some((cell_watcher, bar_watcher)) = &mut shader_watchers {
+            // Every one is reloaded, so that a broken one doesn't hold up
+            // the rest.
+            let broken = [
+                cell_watcher.reload(&mut shader_program),
+                bar_watcher.reload(&mut bar_shader),
+                cursor.reload_shaders(),
+                guides.reload_shaders(),
+                hud.reload_shaders(),
+                controls_renderer.reload_shaders(),
+                editor.reload_shaders(),
+            ];
+
+            hud.set_message(
+                broken
+                    .contains(&true)
+                    .then_some("Shader error, see the terminal"),
+            );
+        }
+
+        // Writing a big arena out takes a while, so it's done on another
+        // thread. If the last autosave still hasn't finished, this one waits.
+        if autosave_interval > 0.0
+            && current_time - previous_autosave >= autosave_interval
+            && autosave.as_ref().is_none_or(JoinHandle::is_finished)
+        {
+            previous_autosave = current_time;
+            let session = Session::capture(
+                game,
+                &snapshot.scheduler,
+                cursor.position(),
+                &camera,
+                field_of_view,
+            );
+
+            autosave = Some(thread::spawn(move || {
+                if let Err(error) = session.save(Path::new(RECOVERY_FILE)) {
+                    eprintln!("Failed to save {}: {}", RECOVERY_FILE, error);
+                }
+            }));
+        }
+
         unsafe {
             gl::ClearColor(0.0, 0.0, 0.0, 1.0);
             gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
@@ -153,65 +633,170 @@ fn main() {
             has_set_mouse_x = true;
         }
 
-        if let glfw::Action::Press = window.get_mouse_button(glfw::MouseButtonMiddle) {
-            let (delta_mouse_x, delta_mouse_y) =
-                (mouse_x - previous_mouse_x, mouse_y - previous_mouse_y);
+        // The editor and the controls are laid out in framebuffer pixels,
+        // and the mouse is in screen coordinates.
+        let mouse_scale = window.get_framebuffer_size().0 as f64 / window.get_size().0 as f64;
+        let mouse_pixels = Vec2::new(
+            (mouse_x * mouse_scale) as f32,
+            (mouse_y * mouse_scale) as f32,
+        );
+
+        editor.hover(mouse_pixels.x, mouse_pixels.y);
+
+        let held_modifiers = held_modifiers(&window);
+        let held =
+            |action| bindings.is_held(action, held_modifiers, |input| is_down(&window, input));
+
+        if held(Action::Click) {
+            if let Some(command) = controls
+                .drag(mouse_pixels)
+                .and_then(|(control, event)| control_command(control, event))
+            {
+                simulation.send(command);
+            }
+        } else {
+            controls.release();
+        }
+
+        let panning = held(Action::PanCamera);
 
-            let sensitivity = 10.0;
-            camera.rotate_camera(
-                sensitivity * (delta_time * delta_mouse_x) as f32,
-                sensitivity * (delta_time * delta_mouse_y) as f32,
+        if panning || held(Action::RotateCamera) {
+            let (dx, dy) = (
+                (mouse_x - previous_mouse_x) as f32,
+                (mouse_y - previous_mouse_y) as f32,
             );
+
+            let input = if panning && !camera.is_flying() {
+                CameraInput::Pan { dx, dy }
+            } else if matches!(camera, ActiveCamera::Arcball(_)) {
+                let (width, height) = window.get_size();
+                let ndc = |x: f64, y: f64| {
+                    Vec2::new(
+                        (2.0 * x / width.max(1) as f64 - 1.0) as f32,
+                        (1.0 - 2.0 * y / height.max(1) as f64) as f32,
+                    )
+                };
+
+                CameraInput::Drag {
+                    from: ndc(previous_mouse_x, previous_mouse_y),
+                    to: ndc(mouse_x, mouse_y),
+                }
+            } else {
+                CameraInput::Rotate { dx, dy }
+            };
+
+            camera_controller.handle_input(&camera, input);
+            dragging_camera = true;
+            // Grabbing the camera takes it away from the flythrough.
+            flythrough = None;
+        } else if dragging_camera {
+            camera_controller.handle_input(&camera, CameraInput::Release);
+            dragging_camera = false;
         }
 
-        if tick_progress >= max_tick_progress {
-            tick_progress = 0.0;
-            game.update_game();
-        } else {
-            if !paused {
-                tick_progress += tick_speed as f64 * delta_time;
+        camera_controller.update(&mut camera, delta_time as f32);
+
+        if let Some(path) = &mut flythrough {
+            path.advance(delta_time as f32);
+            (camera, field_of_view) = path.sample();
+            camera_controller.set_fov(field_of_view);
+
+            if path.is_finished() {
+                flythrough = None;
             }
+        }
+        camera.update(delta_time as f32);
 
-            if random_cursor {
-                let stuff = rng.gen_range(1..=6);
-                match stuff {
-                    1 => {
-                        cursor.move_x(-1);
-                    }
-                    2 => {
-                        cursor.move_x(1);
-                    }
-                    3 => {
-                        cursor.move_z(1);
-                    }
-                    4 => {
-                        cursor.move_z(-1);
-                    }
-                    5 => {
-                        cursor.move_y(1);
-                    }
-                    6 => {
-                        cursor.move_y(-1);
-                    }
-                    _ => {}
-                }
+        if let ActiveCamera::Fly(camera, _) = &mut camera {
+            let axis = |positive, negative| match (held(positive), held(negative)) {
+                (true, false) => 1.0,
+                (false, true) => -1.0,
+                _ => 0.0,
+            };
 
-                game.flip_at_cursor(&cursor);
+            let direction = Vec3::new(
+                axis(Action::Right, Action::Left),
+                axis(Action::Up, Action::Down),
+                axis(Action::Forward, Action::Back),
+            );
+
+            let multiplier = if held(Action::FlyFast) {
+                FLY_FAST_MULTIPLIER
+            } else if held(Action::FlySlow) {
+                FLY_SLOW_MULTIPLIER
+            } else {
+                1.0
+            };
+
+            camera.fly(direction, multiplier, delta_time as f32);
+        }
+
+        if random_cursor {
+            let stuff = rng.gen_range(1..=6);
+            match stuff {
+                1 => {
+                    cursor.move_x(-1);
+                }
+                2 => {
+                    cursor.move_x(1);
+                }
+                3 => {
+                    cursor.move_z(1);
+                }
+                4 => {
+                    cursor.move_z(-1);
+                }
+                5 => {
+                    cursor.move_y(1);
+                }
+                6 => {
+                    cursor.move_y(-1);
+                }
+                _ => {}
             }
+
+            simulation.send(flip_at_cursor(&cursor));
         }
 
         let view = camera.view_matrix();
+        let projection = Mat4::perspective(
+            aspect_ratio,
+            config.view.near,
+            config.view.far,
+            field_of_view,
+        );
+
+        let (x, y, z) = cursor.position();
+        clipping.follow_cursor(y as usize);
+        // The cursor gets drawn instead of the cell it is on.
+        clipping.hide_cell(Some([x as usize, y as usize, z as usize]));
+        if clipping.revision() != drawn_clipping {
+            drawn_clipping = clipping.revision();
+            renderer.invalidate_instances();
+            ghost_renderer.invalidate_instances();
+            chunk_mesher.invalidate();
+        }
 
         {
             let shader_program = shader_program.use_program();
-            shader_program.set_uniform("cell_size", CELL_SIZE);
-            shader_program.set_uniform("view", &view);
+            shader_program.set_uniform("view", view);
             shader_program.set_uniform("model", Mat4::new(1.0));
-            shader_program.set_uniform("projection", &projection);
-            game.render(&mut renderer, CELL_SIZE, &cursor);
+            shader_program.set_uniform("projection", projection);
+            lighting.set_uniforms(&shader_program, &view);
+            match render_mode {
+                RenderMode::Instanced => game.render(&mut renderer, cell_size, &palette, &clipping),
+                RenderMode::Meshed => chunk_mesher.render(game, &palette, &clipping),
+            }
         }
 
-        cursor.render(&game, &renderer, CELL_SIZE, &projection, &view);
+        cursor.render(game, &renderer, cell_size, &projection, &view);
+        guides.render(cursor.position().1 as usize, cell_size, projection, view);
+
+        if clipping.slice().is_some() {
+            // The uniforms are still set from before.
+            let _shader_program = shader_program.use_program();
+            game.render_ghosts(&mut ghost_renderer, cell_size, &palette, &clipping);
+        }
 
         {
             let transform = Mat4::translate(50.0, 100.0, 0.0);
@@ -219,75 +804,280 @@ fn main() {
             let shader_program = bar_shader.use_program();
             shader_program.set_uniform("model", transform);
             shader_program.set_uniform("view", Mat4::new(1.0));
-            shader_program.set_uniform("projection", &flat_projection);
+            shader_program.set_uniform("projection", flat_projection);
 
-            bar_renderer.render_bars(tick_speed);
+            bar_renderer.render_bars(snapshot.scheduler.speed() as u32 + 1);
         }
 
+        // The keyboard can change these too.
+        controls.set_on(Control::Play, !snapshot.scheduler.is_paused());
+        controls.set_value(Control::Speed, snapshot.scheduler.speed() as u32 + 1);
+        controls_renderer.render(&controls, flat_projection);
+
+        hud.render(
+            game,
+            snapshot.scheduler.is_paused(),
+            snapshot.scheduler.rate(),
+            cursor.position(),
+            delta_time as f32,
+            flat_projection,
+        );
+
+        guides.render_gizmo(view);
+        editor.render(game, &palette, flat_projection);
+
         window.swap_buffers();
         glfw.poll_events();
 
         for (_, event) in glfw::flush_messages(&events) {
-            match event {
-                glfw::WindowEvent::FramebufferSize(width, height) => unsafe {
-                    gl::Viewport(0, 0, width, height);
+            let trigger = match event {
+                glfw::WindowEvent::FramebufferSize(width, height) => {
+                    unsafe { gl::Viewport(0, 0, width, height) };
                     let (width, height) = (width as f32, height as f32);
 
-                    projection = life_3d::math::Mat4::perspective(width / height, 0.1, 100.0, 45.0);
-                    flat_projection =
-                        Mat4::orthographic(0.0, width as f32, 0.0, height as f32, 1.0, -1.0);
-                },
+                    aspect_ratio = width / height;
+                    // Mouse positions are in screen coordinates, which are
+                    // not always the same as the framebuffer's.
+                    camera_controller.set_viewport_height(window.get_size().1 as f32);
+                    flat_projection = screen_projection(width, height);
+                    editor.resize(width, height);
+                    None
+                }
                 glfw::WindowEvent::Scroll(_xoffset, yoffset) => {
-                    let factor = (yoffset / yoffset.abs()) as f32;
+                    camera_controller.handle_input(&camera, CameraInput::Zoom(yoffset as f32));
+                    None
+                }
+                glfw::WindowEvent::MouseButton(button, glfw::Action::Press, mods) => {
+                    mouse_input(button).map(|input| Trigger::new(input, modifiers(mods)))
+                }
+                glfw::WindowEvent::Key(key, _, glfw::Action::Press, mods) => {
+                    key_input(key).map(|input| Trigger::new(input, modifiers(mods)))
+                }
+                _ => None,
+            };
 
-                    camera.move_camera(-factor * 10.0 * delta_time as f32);
+            let Some(action) = trigger.and_then(|trigger| bindings.action(trigger)) else {
+                continue;
+            };
+
+            match action {
+                // While flying, these move the camera instead of the cursor.
+                Action::Forward
+                | Action::Back
+                | Action::Left
+                | Action::Right
+                | Action::Up
+                | Action::Down
+                    if camera.is_flying() => {}
+                Action::Forward => {
+                    cursor.move_x(-1);
                 }
-                glfw::WindowEvent::Key(key, _, action, _modifiers) => match action {
-                    glfw::Action::Press => match key {
-                        glfw::Key::Space => {
-                            paused = !paused;
-                        }
-                        glfw::Key::Enter => {
-                            game.flip_at_cursor(&cursor);
-                        }
-                        glfw::Key::KpAdd => {
-                            tick_speed += 1;
-                            tick_speed = tick_speed.clamp(1, 5);
-                        }
-                        glfw::Key::KpSubtract => {
-                            tick_speed -= 1;
-                            tick_speed = tick_speed.clamp(1, 5);
-                        }
-                        glfw::Key::W => {
-                            cursor.move_x(-1);
-                        }
-                        glfw::Key::S => {
-                            cursor.move_x(1);
-                        }
-                        glfw::Key::A => {
-                            cursor.move_z(1);
-                        }
-                        glfw::Key::D => {
-                            cursor.move_z(-1);
-                        }
-                        glfw::Key::Q => {
-                            cursor.move_y(1);
+                Action::Back => {
+                    cursor.move_x(1);
+                }
+                Action::Left => {
+                    cursor.move_z(1);
+                }
+                Action::Right => {
+                    cursor.move_z(-1);
+                }
+                Action::Up => {
+                    cursor.move_y(1);
+                }
+                Action::Down => {
+                    cursor.move_y(-1);
+                }
+                Action::Click => {
+                    if let Some((control, event)) = controls.press(mouse_pixels) {
+                        if let Some(command) = control_command(control, event) {
+                            simulation.send(command);
                         }
-                        glfw::Key::E => {
-                            cursor.move_y(-1);
+                    } else if !controls.contains(mouse_pixels) {
+                        if let Some((x, y, z)) = editor.click(mouse_pixels.x, mouse_pixels.y) {
+                            simulation.send(Command::FlipCell { x, y, z });
                         }
-                        glfw::Key::R => {
-                            random_cursor = !random_cursor;
+                    }
+                }
+                Action::SaveBookmark(slot) => {
+                    bookmarks.insert(Bookmark::capture(&slot.to_string(), &camera, field_of_view));
+
+                    if let Err(error) = bookmarks.save(bookmarks_path) {
+                        eprintln!("Failed to save {}: {}", BOOKMARKS_FILE, error);
+                    }
+                }
+                Action::Bookmark(slot) => {
+                    if let Some(bookmark) = bookmarks.get(&slot.to_string()) {
+                        camera_controller.stop();
+                        flythrough = None;
+                        camera = bookmark.camera();
+                        field_of_view = bookmark.fov;
+                        camera_controller.set_fov(field_of_view);
+                    }
+                }
+                Action::TogglePause => {
+                    simulation.send(Command::TogglePause);
+                }
+                Action::FlipCell => {
+                    simulation.send(flip_at_cursor(&cursor));
+                }
+                Action::Faster => {
+                    simulation.send(Command::Faster);
+                }
+                Action::Slower => {
+                    simulation.send(Command::Slower);
+                }
+                Action::RandomCursor => {
+                    random_cursor = !random_cursor;
+                }
+                Action::ToggleRenderMode => {
+                    render_mode = render_mode.toggle();
+                }
+                Action::NextColorMode => {
+                    palette.mode = palette.mode.next();
+                    renderer.invalidate_instances();
+                    ghost_renderer.invalidate_instances();
+                    chunk_mesher.invalidate();
+                    editor.invalidate();
+                }
+                Action::Step => {
+                    simulation.send(Command::Step(1));
+                }
+                Action::StepBatch => {
+                    simulation.send(Command::Step(STEP_BATCH));
+                }
+                Action::ToggleGrid => {
+                    guides.toggle_grid();
+                }
+                Action::ToggleEditor => {
+                    editor.toggle(cursor.position().1 as usize);
+                }
+                Action::EditorLayerUp if editor.is_open() => {
+                    editor.step_layer(1);
+                }
+                Action::EditorLayerDown if editor.is_open() => {
+                    editor.step_layer(-1);
+                }
+                Action::ClipX | Action::ClipY | Action::ClipZ => {
+                    let axis = match action {
+                        Action::ClipX => 0,
+                        Action::ClipY => 1,
+                        _ => 2,
+                    };
+                    let (x, y, z) = cursor.position();
+                    let position = [x, y, z][axis] as usize;
+
+                    clipping.cycle_plane(axis, position);
+                }
+                Action::ToggleSlice => {
+                    clipping.toggle_slice(cursor.position().1 as usize);
+                }
+                Action::SliceDown => {
+                    clipping.step(-1);
+                }
+                Action::SliceUp => {
+                    clipping.step(1);
+                }
+                Action::ToggleHeadlight => {
+                    lighting.toggle_headlight();
+                }
+                Action::ToggleArcball => {
+                    camera_controller.stop();
+                    camera.toggle_arcball();
+                }
+                Action::PlayFlythrough => {
+                    camera_controller.stop();
+
+                    if flythrough.is_some() {
+                        flythrough = None;
+                    } else {
+                        match bookmarks.default_flythrough(SECONDS_PER_BOOKMARK) {
+                            Some(Ok(path)) => flythrough = Some(path),
+                            Some(Err(error)) => eprintln!("Can't play the path: {}", error),
+                            None => eprintln!("There are no bookmarks to fly through."),
                         }
-                        _ => {}
-                    },
-                    _ => {}
-                },
-                _ => {}
+                    }
+                }
+                Action::ToggleFly => {
+                    camera_controller.stop();
+                    camera.toggle_fly();
+                }
+                Action::FocusCursor => {
+                    camera_controller.stop();
+                    let (x, y, z) = cursor.position();
+                    let real = |i: u32| GameOfLife::to_real_coords(i as f32, arena_size, cell_size);
+                    let target = Vec3::new(real(x), real(y), real(z));
+
+                    let distance = camera.distance();
+                    camera.focus_on(target, distance);
+                }
+                Action::FrameCells => {
+                    camera_controller.stop();
+                    // With nothing alive, frame the entire arena.
+                    let (min, max) = game.live_bounds().unwrap_or(([0; 3], [arena_size - 1; 3]));
+
+                    // Cells are centered on their coordinates, so the box
+                    // reaches half a cell further out.
+                    let corner = |cell: [usize; 3], offset: f32| {
+                        let real = |i: usize| {
+                            GameOfLife::to_real_coords(i as f32, arena_size, cell_size)
+                                + offset * cell_size
+                        };
+                        Vec3::new(real(cell[0]), real(cell[1]), real(cell[2]))
+                    };
+
+                    camera.zoom_to_fit(
+                        corner(min, -0.5),
+                        corner(max, 0.5),
+                        field_of_view,
+                        aspect_ratio,
+                    );
+                }
+                Action::TopView => {
+                    camera_controller.stop();
+                    if !matches!(camera, ActiveCamera::Arcball(_)) {
+                        camera.toggle_arcball();
+                    }
+
+                    if let ActiveCamera::Arcball(camera) = &mut camera {
+                        camera.look_from_above();
+                    }
+                }
+                // These last as long as they're held, see above.
+                Action::FlyFast
+                | Action::FlySlow
+                | Action::RotateCamera
+                | Action::PanCamera
+                | Action::EditorLayerUp
+                | Action::EditorLayerDown => {}
             }
         }
 
         previous_mouse_x = mouse_x;
         previous_mouse_y = mouse_y;
     }
+
+    // So that the autosave can't finish after the recovery file is removed.
+    if let Some(saving) = autosave {
+        let _ = saving.join();
+    }
+
+    // Not `latest`, which may not have the last frame's edits in it yet.
+    let snapshot = simulation.finish();
+    let session = Session::capture(
+        &snapshot.game,
+        &snapshot.scheduler,
+        cursor.position(),
+        &camera,
+        field_of_view,
+    );
+
+    match session.save(Path::new(SESSION_FILE)) {
+        Ok(()) => match fs::remove_file(RECOVERY_FILE) {
+            Err(error) if error.kind() != io::ErrorKind::NotFound => {
+                eprintln!("Failed to remove {}: {}", RECOVERY_FILE, error);
+            }
+            _ => {}
+        },
+        Err(error) => eprintln!("Failed to save {}: {}", SESSION_FILE, error),
+    }
 }
@@ -1,11 +1,12 @@
-use std::ops::{Add, Div, Mul, Sub};
+use std::ops::{Add, Div, Mul, Neg, Sub};
 
 use glad_gl::gl;
+use serde::{Deserialize, Serialize};
 
 use crate::shaders::ShaderUniform;
 
 #[repr(C, align(16))]
-#[derive(Debug, Default, Clone, PartialEq, Copy)]
+#[derive(Debug, Default, Clone, PartialEq, Copy, Serialize, Deserialize)]
 pub struct Vec3 {
     pub x: f32,
     pub y: f32,
@@ -88,13 +89,15 @@ impl Mul<Vec3> for Vec3 {
 }
 
 unsafe impl ShaderUniform for Vec3 {
+    const GLSL_TYPE: gl::GLenum = gl::FLOAT_VEC3;
+
     unsafe fn set_uniform(&self, location: gl::GLint) {
         gl::Uniform3f(location, self.x, self.y, self.z);
     }
 }
 
 #[repr(C, align(8))]
-#[derive(Debug, Default, Clone, PartialEq, Copy)]
+#[derive(Debug, Default, Clone, PartialEq, Copy, Serialize, Deserialize)]
 pub struct Vec2 {
     pub x: f32,
     pub y: f32,
@@ -104,6 +107,29 @@ impl Vec2 {
     pub fn new(x: f32, y: f32) -> Vec2 {
         Vec2 { x, y }
     }
+
+    pub fn len(&self) -> f32 {
+        (self.x * self.x + self.y * self.y).sqrt()
+    }
+}
+
+impl Sub for Vec2 {
+    type Output = Vec2;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        Vec2 {
+            x: self.x - rhs.x,
+            y: self.y - rhs.y,
+        }
+    }
+}
+
+impl Mul<f32> for Vec2 {
+    type Output = Vec2;
+
+    fn mul(self, rhs: f32) -> Self::Output {
+        Vec2::new(self.x * rhs, self.y * rhs)
+    }
 }
 
 impl Add for Vec2 {
@@ -118,7 +144,7 @@ impl Add for Vec2 {
 }
 
 #[repr(C, align(16))]
-#[derive(Debug, Default, Clone, PartialEq)]
+#[derive(Debug, Default, Clone, PartialEq, Copy, Serialize, Deserialize)]
 pub struct Vec4 {
     pub x: f32,
     pub y: f32,
@@ -130,10 +156,99 @@ impl Vec4 {
     pub fn new(x: f32, y: f32, z: f32, w: f32) -> Vec4 {
         Vec4 { x, y, z, w }
     }
+
+    pub fn from_vec3(v: Vec3, w: f32) -> Vec4 {
+        Vec4::new(v.x, v.y, v.z, w)
+    }
+
+    pub fn xyz(&self) -> Vec3 {
+        Vec3::new(self.x, self.y, self.z)
+    }
+
+    pub fn len(&self) -> f32 {
+        self.dot(self).sqrt()
+    }
+
+    pub fn normalize(&self) -> Vec4 {
+        *self / self.len()
+    }
+
+    pub fn dot(&self, other: &Vec4) -> f32 {
+        self.x * other.x + self.y * other.y + self.z * other.z + self.w * other.w
+    }
+
+    // Divides by `w`, turning a clip-space position into normalized device
+    // coordinates.
+    pub fn perspective_divide(&self) -> Vec3 {
+        self.xyz() / self.w
+    }
+}
+
+impl Add for Vec4 {
+    type Output = Vec4;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        Vec4::new(
+            self.x + rhs.x,
+            self.y + rhs.y,
+            self.z + rhs.z,
+            self.w + rhs.w,
+        )
+    }
+}
+
+impl Sub for Vec4 {
+    type Output = Vec4;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        Vec4::new(
+            self.x - rhs.x,
+            self.y - rhs.y,
+            self.z - rhs.z,
+            self.w - rhs.w,
+        )
+    }
+}
+
+impl Neg for Vec4 {
+    type Output = Vec4;
+
+    fn neg(self) -> Self::Output {
+        Vec4::new(-self.x, -self.y, -self.z, -self.w)
+    }
+}
+
+impl Div<f32> for Vec4 {
+    type Output = Vec4;
+
+    fn div(self, rhs: f32) -> Self::Output {
+        Vec4::new(self.x / rhs, self.y / rhs, self.z / rhs, self.w / rhs)
+    }
+}
+
+impl Mul<f32> for Vec4 {
+    type Output = Vec4;
+
+    fn mul(self, rhs: f32) -> Self::Output {
+        Vec4::new(self.x * rhs, self.y * rhs, self.z * rhs, self.w * rhs)
+    }
+}
+
+impl Mul<Vec4> for Vec4 {
+    type Output = Vec4;
+
+    fn mul(self, rhs: Vec4) -> Self::Output {
+        Vec4::new(
+            self.x * rhs.x,
+            self.y * rhs.y,
+            self.z * rhs.z,
+            self.w * rhs.w,
+        )
+    }
 }
 
 #[repr(C)]
-#[derive(Debug, Default, Clone, PartialEq)]
+#[derive(Debug, Default, Clone, PartialEq, Copy, Serialize, Deserialize)]
 pub struct Quaternion(Vec4);
 
 impl Quaternion {
@@ -185,6 +300,71 @@ impl Quaternion {
     pub fn conjugate(&self) -> Quaternion {
         Quaternion(Vec4::new(-self.0.x, -self.0.y, -self.0.z, self.0.w))
     }
+
+    pub fn identity() -> Quaternion {
+        Quaternion(Vec4::new(0.0, 0.0, 0.0, 1.0))
+    }
+
+    pub fn dot(&self, other: &Quaternion) -> f32 {
+        self.0.dot(&other.0)
+    }
+
+    pub fn normalize(&self) -> Quaternion {
+        Quaternion(self.0.normalize())
+    }
+
+    // Rotates `v` by this quaternion, which must be a unit quaternion.
+    pub fn rotate_vector(&self, v: &Vec3) -> Vec3 {
+        let v = Quaternion(Vec4::from_vec3(*v, 0.0));
+        (*self * v * self.conjugate()).0.xyz()
+    }
+
+    // The unit axis and the angle (in radians, at most pi) of the rotation
+    // this unit quaternion makes. The identity gives an angle of zero about
+    // an arbitrary axis.
+    pub fn to_axis_angle(&self) -> (Vec3, f32) {
+        // `q` and `-q` are the same rotation, the one with a positive `w`
+        // is the short way around.
+        let q = if self.0.w < 0.0 { -self.0 } else { self.0 };
+        let sin_half_angle = q.xyz().len();
+
+        if sin_half_angle < 1e-6 {
+            return (Vec3::new(1.0, 0.0, 0.0), 0.0);
+        }
+
+        (
+            q.xyz() * (1.0 / sin_half_angle),
+            2.0 * sin_half_angle.atan2(q.w),
+        )
+    }
+
+    // Spherical linear interpolation, always along the shortest arc. Both
+    // quaternions must be unit quaternions.
+    // Source: https://en.wikipedia.org/wiki/Slerp#Source_code
+    pub fn slerp(&self, other: &Quaternion, t: f32) -> Quaternion {
+        let mut other = other.0;
+        let mut cos_theta = self.dot(&Quaternion(other));
+
+        // `q` and `-q` represent the same rotation, but only one of them is
+        // the short way around.
+        if cos_theta < 0.0 {
+            other = -other;
+            cos_theta = -cos_theta;
+        }
+
+        // Too close together for `sin(theta)` to be safe to divide by, a
+        // plain lerp is indistinguishable at this point.
+        if cos_theta > 0.9995 {
+            return Quaternion(self.0 + (other - self.0) * t).normalize();
+        }
+
+        let theta = cos_theta.acos();
+        let sin_theta = theta.sin();
+        let from_weight = ((1.0 - t) * theta).sin() / sin_theta;
+        let to_weight = (t * theta).sin() / sin_theta;
+
+        Quaternion(self.0 * from_weight + other * to_weight)
+    }
 }
 
 impl Mul for Quaternion {
@@ -204,8 +384,10 @@ impl Mul for Quaternion {
     }
 }
 
+// Matrices are stored column-major, the same way OpenGL expects them, so
+// `data[column][row]`.
 #[repr(C, align(16))]
-#[derive(Debug, Default, Clone, PartialEq)]
+#[derive(Debug, Default, Clone, PartialEq, Copy)]
 pub struct Mat4 {
     pub(crate) data: [[f32; 4]; 4],
 }
@@ -264,41 +446,208 @@ impl Mat4 {
             ],
         }
     }
-}
-
-#[cfg(test)]
-mod tests {
-    use super::Mat4;
 
-    #[test]
-    fn matrix_multiplication() {
-        let first = Mat4 {
+    pub fn scale(x: f32, y: f32, z: f32) -> Mat4 {
+        Mat4 {
             data: [
-                [1.0, 0.0, 0.0, 0.0],
-                [0.0, 1.0, 0.0, 0.0],
-                [0.0, 0.0, 1.0, 0.0],
-                [0.0, 0.0, -5.0, 1.0],
+                [x, 0.0, 0.0, 0.0],
+                [0.0, y, 0.0, 0.0],
+                [0.0, 0.0, z, 0.0],
+                [0.0, 0.0, 0.0, 1.0],
             ],
-        };
-        let second = Mat4 {
+        }
+    }
+
+    // Note: `angle` must be in radians and `axis` must be a unit vector.
+    // Source: https://en.wikipedia.org/wiki/Rotation_matrix#Rotation_matrix_from_axis_and_angle
+    pub fn rotate(axis: &Vec3, angle: f32) -> Mat4 {
+        let (sin, cos) = angle.sin_cos();
+        let t = 1.0 - cos;
+        let Vec3 { x, y, z } = *axis;
+
+        Mat4 {
             data: [
-                [1.0, 0.0, 0.0, 0.0],
-                [0.0, 1.0, 0.0, 0.0],
-                [0.0, 0.0, 1.0, 0.0],
+                [
+                    t * x * x + cos,
+                    t * x * y + sin * z,
+                    t * x * z - sin * y,
+                    0.0,
+                ],
+                [
+                    t * x * y - sin * z,
+                    t * y * y + cos,
+                    t * y * z + sin * x,
+                    0.0,
+                ],
+                [
+                    t * x * z + sin * y,
+                    t * y * z - sin * x,
+                    t * z * z + cos,
+                    0.0,
+                ],
                 [0.0, 0.0, 0.0, 1.0],
             ],
-        };
-        let expected = Mat4 {
+        }
+    }
+
+    // Rotates around Z (roll), then X (pitch), then Y (yaw). All of the
+    // angles are in radians.
+    pub fn rotate_euler(pitch: f32, yaw: f32, roll: f32) -> Mat4 {
+        Mat4::rotate(&Vec3::new(0.0, 1.0, 0.0), yaw)
+            * Mat4::rotate(&Vec3::new(1.0, 0.0, 0.0), pitch)
+            * Mat4::rotate(&Vec3::new(0.0, 0.0, 1.0), roll)
+    }
+
+    // Builds the same view matrix as `Camera::view_matrix` for a camera at
+    // `eye` looking at `target`.
+    pub fn look_at(eye: &Vec3, target: &Vec3, up: &Vec3) -> Mat4 {
+        let front = (*target - *eye).normalize();
+        let right = front.cross(up).normalize();
+        let up = right.cross(&front).normalize();
+
+        Mat4 {
             data: [
-                [1.0, 0.0, 0.0, 0.0],
-                [0.0, 1.0, 0.0, 0.0],
-                [0.0, 0.0, 1.0, 0.0],
-                [0.0, 0.0, -5.0, 1.0],
+                [right.x, up.x, -front.x, 0.0],
+                [right.y, up.y, -front.y, 0.0],
+                [right.z, up.z, -front.z, 0.0],
+                [-right.dot(eye), -up.dot(eye), front.dot(eye), 1.0],
             ],
-        };
+        }
+    }
 
-        let obtained = first * second;
-        assert_eq!(expected, obtained);
+    pub fn transpose(&self) -> Mat4 {
+        let mut result = Mat4::new(0.0);
+
+        for column in 0..4 {
+            for row in 0..4 {
+                result.data[column][row] = self.data[row][column];
+            }
+        }
+
+        result
+    }
+
+    // The same matrix without the translation, so that for a view matrix
+    // only the camera's rotation is left.
+    pub fn without_translation(&self) -> Mat4 {
+        let mut result = *self;
+        result.data[3] = [0.0, 0.0, 0.0, 1.0];
+        result
+    }
+
+    pub fn determinant(&self) -> f32 {
+        Self::determinant_from_cofactors(&self.data, &self.cofactors())
+    }
+
+    // Returns `None` if the matrix is singular (or close enough to it that
+    // the result would be meaningless).
+    pub fn inverse(&self) -> Option<Mat4> {
+        let cofactors = self.cofactors();
+        let determinant = Self::determinant_from_cofactors(&self.data, &cofactors);
+
+        // The determinant is at most the product of the column lengths
+        // (Hadamard's inequality), so comparing against that keeps the check
+        // independent of how large or small the matrix is.
+        let column_lengths: f32 = self
+            .data
+            .iter()
+            .map(|column| column.iter().map(|value| value * value).sum::<f32>().sqrt())
+            .product();
+        let tolerance = 1e-5 * column_lengths;
+
+        if !determinant.is_finite() || determinant.abs() <= tolerance {
+            return None;
+        }
+
+        let mut result = Mat4::new(0.0);
+        for (i, cofactor) in cofactors.iter().enumerate() {
+            result.data[i / 4][i % 4] = cofactor / determinant;
+        }
+
+        Some(result)
+    }
+
+    fn determinant_from_cofactors(data: &[[f32; 4]; 4], cofactors: &[f32; 16]) -> f32 {
+        data[0][0] * cofactors[0]
+            + data[0][1] * cofactors[4]
+            + data[0][2] * cofactors[8]
+            + data[0][3] * cofactors[12]
+    }
+
+    // The transposed cofactor matrix (the adjugate), flattened in the same
+    // order as `data`. Works the same regardless of the storage order, as the
+    // inverse of the transpose is the transpose of the inverse.
+    // Source: https://www.mesa3d.org/ (gluInvertMatrix)
+    fn cofactors(&self) -> [f32; 16] {
+        let m: [f32; 16] = std::array::from_fn(|i| self.data[i / 4][i % 4]);
+        let mut inv = [0.0; 16];
+
+        inv[0] = m[5] * m[10] * m[15] - m[5] * m[11] * m[14] - m[9] * m[6] * m[15]
+            + m[9] * m[7] * m[14]
+            + m[13] * m[6] * m[11]
+            - m[13] * m[7] * m[10];
+        inv[4] = -m[4] * m[10] * m[15] + m[4] * m[11] * m[14] + m[8] * m[6] * m[15]
+            - m[8] * m[7] * m[14]
+            - m[12] * m[6] * m[11]
+            + m[12] * m[7] * m[10];
+        inv[8] = m[4] * m[9] * m[15] - m[4] * m[11] * m[13] - m[8] * m[5] * m[15]
+            + m[8] * m[7] * m[13]
+            + m[12] * m[5] * m[11]
+            - m[12] * m[7] * m[9];
+        inv[12] = -m[4] * m[9] * m[14] + m[4] * m[10] * m[13] + m[8] * m[5] * m[14]
+            - m[8] * m[6] * m[13]
+            - m[12] * m[5] * m[10]
+            + m[12] * m[6] * m[9];
+        inv[1] = -m[1] * m[10] * m[15] + m[1] * m[11] * m[14] + m[9] * m[2] * m[15]
+            - m[9] * m[3] * m[14]
+            - m[13] * m[2] * m[11]
+            + m[13] * m[3] * m[10];
+        inv[5] = m[0] * m[10] * m[15] - m[0] * m[11] * m[14] - m[8] * m[2] * m[15]
+            + m[8] * m[3] * m[14]
+            + m[12] * m[2] * m[11]
+            - m[12] * m[3] * m[10];
+        inv[9] = -m[0] * m[9] * m[15] + m[0] * m[11] * m[13] + m[8] * m[1] * m[15]
+            - m[8] * m[3] * m[13]
+            - m[12] * m[1] * m[11]
+            + m[12] * m[3] * m[9];
+        inv[13] = m[0] * m[9] * m[14] - m[0] * m[10] * m[13] - m[8] * m[1] * m[14]
+            + m[8] * m[2] * m[13]
+            + m[12] * m[1] * m[10]
+            - m[12] * m[2] * m[9];
+        inv[2] = m[1] * m[6] * m[15] - m[1] * m[7] * m[14] - m[5] * m[2] * m[15]
+            + m[5] * m[3] * m[14]
+            + m[13] * m[2] * m[7]
+            - m[13] * m[3] * m[6];
+        inv[6] = -m[0] * m[6] * m[15] + m[0] * m[7] * m[14] + m[4] * m[2] * m[15]
+            - m[4] * m[3] * m[14]
+            - m[12] * m[2] * m[7]
+            + m[12] * m[3] * m[6];
+        inv[10] = m[0] * m[5] * m[15] - m[0] * m[7] * m[13] - m[4] * m[1] * m[15]
+            + m[4] * m[3] * m[13]
+            + m[12] * m[1] * m[7]
+            - m[12] * m[3] * m[5];
+        inv[14] = -m[0] * m[5] * m[14] + m[0] * m[6] * m[13] + m[4] * m[1] * m[14]
+            - m[4] * m[2] * m[13]
+            - m[12] * m[1] * m[6]
+            + m[12] * m[2] * m[5];
+        inv[3] = -m[1] * m[6] * m[11] + m[1] * m[7] * m[10] + m[5] * m[2] * m[11]
+            - m[5] * m[3] * m[10]
+            - m[9] * m[2] * m[7]
+            + m[9] * m[3] * m[6];
+        inv[7] = m[0] * m[6] * m[11] - m[0] * m[7] * m[10] - m[4] * m[2] * m[11]
+            + m[4] * m[3] * m[10]
+            + m[8] * m[2] * m[7]
+            - m[8] * m[3] * m[6];
+        inv[11] = -m[0] * m[5] * m[11] + m[0] * m[7] * m[9] + m[4] * m[1] * m[11]
+            - m[4] * m[3] * m[9]
+            - m[8] * m[1] * m[7]
+            + m[8] * m[3] * m[5];
+        inv[15] = m[0] * m[5] * m[10] - m[0] * m[6] * m[9] - m[4] * m[1] * m[10]
+            + m[4] * m[2] * m[9]
+            + m[8] * m[1] * m[6]
+            - m[8] * m[2] * m[5];
+
+        inv
     }
 }
 
@@ -321,14 +670,277 @@ impl Mul for Mat4 {
     }
 }
 
+impl Mul<Vec4> for Mat4 {
+    type Output = Vec4;
+
+    fn mul(self, rhs: Vec4) -> Self::Output {
+        let column = |i: usize| {
+            Vec4::new(
+                self.data[i][0],
+                self.data[i][1],
+                self.data[i][2],
+                self.data[i][3],
+            )
+        };
+
+        column(0) * rhs.x + column(1) * rhs.y + column(2) * rhs.z + column(3) * rhs.w
+    }
+}
+
 unsafe impl ShaderUniform for Mat4 {
+    const GLSL_TYPE: gl::GLenum = gl::FLOAT_MAT4;
+
     unsafe fn set_uniform(&self, location: glad_gl::gl::GLint) {
         gl::UniformMatrix4fv(location, 1, gl::FALSE, self.data.as_ptr() as *const f32);
     }
 }
 
 unsafe impl ShaderUniform for &Mat4 {
+    const GLSL_TYPE: gl::GLenum = gl::FLOAT_MAT4;
+
     unsafe fn set_uniform(&self, location: glad_gl::gl::GLint) {
         (*self).set_uniform(location);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+
+    use super::{Mat4, Quaternion, Vec3, Vec4};
+    use crate::camera::Camera;
+
+    const EPSILON: f32 = 1e-3;
+
+    fn approx_eq(a: &Mat4, b: &Mat4) -> bool {
+        a.data
+            .iter()
+            .flatten()
+            .zip(b.data.iter().flatten())
+            .all(|(a, b)| (a - b).abs() < EPSILON)
+    }
+
+    fn approx_eq_vec4(a: Vec4, b: Vec4) -> bool {
+        (a - b).len() < EPSILON
+    }
+
+    fn unit_vector() -> impl Strategy<Value = Vec3> {
+        (-1.0f32..1.0, -1.0f32..1.0, -1.0f32..1.0).prop_filter_map(
+            "too short to normalize",
+            |(x, y, z)| {
+                let v = Vec3::new(x, y, z);
+                (v.len() > 0.1).then(|| v.normalize())
+            },
+        )
+    }
+
+    fn matrix() -> impl Strategy<Value = Mat4> {
+        prop::array::uniform4(prop::array::uniform4(-10.0f32..10.0)).prop_map(|data| Mat4 { data })
+    }
+
+    // A random rigid transform with a non-uniform scale, the kind of matrix
+    // the renderer actually deals with.
+    fn transform() -> impl Strategy<Value = Mat4> {
+        (
+            prop::array::uniform3(-50.0f32..50.0),
+            unit_vector(),
+            -6.0f32..6.0,
+            prop::array::uniform3(0.1f32..5.0),
+        )
+            .prop_map(|([tx, ty, tz], axis, angle, [sx, sy, sz])| {
+                Mat4::translate(tx, ty, tz) * Mat4::rotate(&axis, angle) * Mat4::scale(sx, sy, sz)
+            })
+    }
+
+    fn vec4() -> impl Strategy<Value = Vec4> {
+        prop::array::uniform4(-10.0f32..10.0).prop_map(|[x, y, z, w]| Vec4::new(x, y, z, w))
+    }
+
+    proptest! {
+        #[test]
+        fn inverse_round_trip(matrix in transform()) {
+            let inverse = matrix.inverse().expect("transforms are invertible");
+
+            prop_assert!(approx_eq(&(matrix * inverse), &Mat4::new(1.0)));
+            prop_assert!(approx_eq(&(inverse * matrix), &Mat4::new(1.0)));
+        }
+
+        #[test]
+        fn inverse_round_trip_general(matrix in matrix(), vector in vec4()) {
+            // Skip badly conditioned matrices, their inverse is legitimately
+            // imprecise in single precision.
+            let column_lengths: f32 = matrix
+                .data
+                .iter()
+                .map(|column| Vec4::new(column[0], column[1], column[2], column[3]).len())
+                .product();
+            prop_assume!(matrix.determinant().abs() >= 0.05 * column_lengths);
+
+            let inverse = matrix.inverse().expect("well conditioned");
+            let round_trip = inverse * (matrix * vector);
+
+            prop_assert!((round_trip - vector).len() < EPSILON * vector.len().max(1.0));
+        }
+
+        #[test]
+        fn inverse_of_matrix_with_equal_columns(mut matrix in matrix()) {
+            // Two equal columns always make a singular matrix.
+            matrix.data[2] = matrix.data[0];
+            prop_assert_eq!(matrix.inverse(), None);
+        }
+
+        #[test]
+        fn transpose_properties(a in matrix(), b in matrix()) {
+            prop_assert_eq!(a.transpose().transpose(), a);
+            prop_assert!(approx_eq(
+                &(a * b).transpose(),
+                &(b.transpose() * a.transpose())
+            ));
+        }
+
+        #[test]
+        fn matrix_vector_multiplication_is_associative(a in matrix(), b in matrix(), v in vec4()) {
+            let expected = a * (b * v);
+            let obtained = (a * b) * v;
+            prop_assert!((expected - obtained).len() < EPSILON * expected.len().max(1.0));
+        }
+
+        #[test]
+        fn rotation_matches_quaternion(axis in unit_vector(), angle in -6.0f32..6.0) {
+            prop_assert!(approx_eq(
+                &Mat4::rotate(&axis, angle),
+                &Quaternion::new(&axis, angle).to_rotation_matrix()
+            ));
+        }
+
+        #[test]
+        fn axis_angle_round_trip(axis in unit_vector(), angle in 0.01f32..3.1) {
+            let (obtained_axis, obtained_angle) = Quaternion::new(&axis, angle).to_axis_angle();
+
+            prop_assert!((obtained_axis - axis).len() < EPSILON);
+            prop_assert!((obtained_angle - angle).abs() < EPSILON);
+        }
+
+        #[test]
+        fn look_at_matches_camera(eye in prop::array::uniform3(-10.0f32..10.0), front in unit_vector()) {
+            let eye = Vec3::new(eye[0], eye[1], eye[2]);
+            let mut front = front;
+            front.y = front.y.clamp(-0.9, 0.9);
+            let front = front.normalize();
+
+            let camera = Camera::new(&eye, &front);
+            let look_at = Mat4::look_at(&eye, &(eye + front), &Vec3::new(0.0, 1.0, 0.0));
+
+            prop_assert!(approx_eq(&camera.view_matrix(), &look_at));
+
+            // The eye ends up at the origin of view space.
+            prop_assert!(approx_eq_vec4(
+                look_at * Vec4::from_vec3(eye, 1.0),
+                Vec4::new(0.0, 0.0, 0.0, 1.0)
+            ));
+        }
+    }
+
+    #[test]
+    fn inverse_of_singular_matrix() {
+        assert_eq!(Mat4::new(0.0).inverse(), None);
+        assert_eq!(Mat4::scale(1.0, 0.0, 1.0).inverse(), None);
+    }
+
+    #[test]
+    fn inverse_of_tiny_matrix() {
+        let matrix = Mat4::scale(0.01, 0.01, 0.01);
+        let inverse = matrix.inverse().expect("scaling is invertible");
+
+        assert!(approx_eq(&inverse, &Mat4::scale(100.0, 100.0, 100.0)));
+    }
+
+    #[test]
+    fn matrix_vector_multiplication_is_column_major() {
+        let translated = Mat4::translate(1.0, 2.0, 3.0) * Vec4::new(1.0, 1.0, 1.0, 1.0);
+        assert_eq!(translated, Vec4::new(2.0, 3.0, 4.0, 1.0));
+
+        // Directions (w = 0) are not affected by translation.
+        let direction = Mat4::translate(1.0, 2.0, 3.0) * Vec4::new(1.0, 0.0, 0.0, 0.0);
+        assert_eq!(direction, Vec4::new(1.0, 0.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn quarter_turn_rotation() {
+        let quarter_turn = Mat4::rotate(&Vec3::new(0.0, 0.0, 1.0), std::f32::consts::FRAC_PI_2);
+        assert!(approx_eq_vec4(
+            quarter_turn * Vec4::new(1.0, 0.0, 0.0, 0.0),
+            Vec4::new(0.0, 1.0, 0.0, 0.0)
+        ));
+    }
+
+    #[test]
+    fn euler_rotation() {
+        let yaw_only = Mat4::rotate_euler(0.0, 0.5, 0.0);
+        assert!(approx_eq(
+            &yaw_only,
+            &Mat4::rotate(&Vec3::new(0.0, 1.0, 0.0), 0.5)
+        ));
+
+        let rotation = Mat4::rotate_euler(0.3, -1.2, 0.7);
+        assert!(approx_eq(
+            &(rotation * rotation.transpose()),
+            &Mat4::new(1.0)
+        ));
+    }
+
+    #[test]
+    fn scale_is_non_uniform() {
+        let scaled = Mat4::scale(2.0, 3.0, 4.0) * Vec4::new(1.0, 1.0, 1.0, 1.0);
+        assert_eq!(scaled, Vec4::new(2.0, 3.0, 4.0, 1.0));
+    }
+
+    #[test]
+    fn vec4_arithmetic() {
+        let a = Vec4::new(1.0, 2.0, 3.0, 4.0);
+        let b = Vec4::new(4.0, 3.0, 2.0, 1.0);
+
+        assert_eq!(a + b, Vec4::new(5.0, 5.0, 5.0, 5.0));
+        assert_eq!(a - b, Vec4::new(-3.0, -1.0, 1.0, 3.0));
+        assert_eq!(-a, Vec4::new(-1.0, -2.0, -3.0, -4.0));
+        assert_eq!(a * 2.0, Vec4::new(2.0, 4.0, 6.0, 8.0));
+        assert_eq!(a / 2.0, Vec4::new(0.5, 1.0, 1.5, 2.0));
+        assert_eq!(a.dot(&b), 20.0);
+        assert!((Vec4::new(3.0, 0.0, 4.0, 0.0).normalize().len() - 1.0).abs() < EPSILON);
+        assert_eq!(
+            Vec4::new(2.0, 4.0, 6.0, 2.0).perspective_divide(),
+            Vec3::new(1.0, 2.0, 3.0)
+        );
+    }
+
+    #[test]
+    fn matrix_multiplication() {
+        let first = Mat4 {
+            data: [
+                [1.0, 0.0, 0.0, 0.0],
+                [0.0, 1.0, 0.0, 0.0],
+                [0.0, 0.0, 1.0, 0.0],
+                [0.0, 0.0, -5.0, 1.0],
+            ],
+        };
+        let second = Mat4 {
+            data: [
+                [1.0, 0.0, 0.0, 0.0],
+                [0.0, 1.0, 0.0, 0.0],
+                [0.0, 0.0, 1.0, 0.0],
+                [0.0, 0.0, 0.0, 1.0],
+            ],
+        };
+        let expected = Mat4 {
+            data: [
+                [1.0, 0.0, 0.0, 0.0],
+                [0.0, 1.0, 0.0, 0.0],
+                [0.0, 0.0, 1.0, 0.0],
+                [0.0, 0.0, -5.0, 1.0],
+            ],
+        };
+
+        let obtained = first * second;
+        assert_eq!(expected, obtained);
+    }
+}
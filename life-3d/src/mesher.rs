@@ -0,0 +1,709 @@
+// Builds meshes out of the live cells, as an alternative to drawing a whole
+// cube for every one of them. Only the faces that aren't buried against
+// another live cell are kept, and faces that line up get merged into larger
+// rectangles. The arena is split into chunks that are meshed separately, so
+// that a change only rebuilds the chunks around it.
+
+use std::{
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
+};
+
+use crate::{
+    clipping::Clipping,
+    colors::Palette,
+    game::GameOfLife,
+    math::{Vec2, Vec3},
+    renderer::{
+        occlusion_brightness, occlusion_level, Axis, CellInstance, FaceShading, Mesh, Renderer,
+    },
+};
+
+// The arena has to be a whole number of chunks across.
+pub const CHUNK_SIZE: usize = 16;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RenderMode {
+    // A cube per live cell.
+    Instanced,
+    // A mesh per chunk, from `ChunkMesher`.
+    Meshed,
+}
+
+impl RenderMode {
+    pub fn toggle(self) -> RenderMode {
+        match self {
+            RenderMode::Instanced => RenderMode::Meshed,
+            RenderMode::Meshed => RenderMode::Instanced,
+        }
+    }
+}
+
+fn chunk_index(chunk: [usize; 3], chunks_per_axis: usize) -> usize {
+    (chunk[1] * chunks_per_axis + chunk[0]) * chunks_per_axis + chunk[2]
+}
+
+// The two axes a face looking along `axis` spans, in the order
+// `Mesh::append_face` takes its size in.
+fn face_axes(axis: usize) -> (usize, usize) {
+    match axis {
+        0 => (2, 1),
+        1 => (0, 2),
+        _ => (0, 1),
+    }
+}
+
+// The corners of a face along u and v, in the order `FaceShading` has them in.
+const CORNERS: [(i32, i32); 4] = [(1, 1), (1, -1), (-1, -1), (-1, 1)];
+
+fn to_axis(axis: usize) -> Axis {
+    match axis {
+        0 => Axis::X,
+        1 => Axis::Y,
+        _ => Axis::Z,
+    }
+}
+
+// Which cells of a chunk are alive, one bit each, and a hash of their
+// colours.
+#[derive(Clone, PartialEq, Eq)]
+struct ChunkCells {
+    alive: [u64; CHUNK_SIZE * CHUNK_SIZE * CHUNK_SIZE / 64],
+    colors: u64,
+}
+
+impl ChunkCells {
+    fn empty() -> ChunkCells {
+        ChunkCells {
+            alive: [0; CHUNK_SIZE * CHUNK_SIZE * CHUNK_SIZE / 64],
+            colors: 0,
+        }
+    }
+
+    fn from_game(
+        game: &GameOfLife,
+        chunk: [usize; 3],
+        palette: &Palette,
+        clipping: &Clipping,
+    ) -> ChunkCells {
+        let mut cells = ChunkCells::empty();
+        let mut colors = DefaultHasher::new();
+
+        for y in 0..CHUNK_SIZE {
+            for x in 0..CHUNK_SIZE {
+                for z in 0..CHUNK_SIZE {
+                    let index = Self::index([x, y, z]);
+                    let [x, y, z] = [
+                        chunk[0] * CHUNK_SIZE + x,
+                        chunk[1] * CHUNK_SIZE + y,
+                        chunk[2] * CHUNK_SIZE + z,
+                    ];
+
+                    if game.cell(x, y, z).is_alive() && clipping.is_visible(x, y, z) {
+                        cells.alive[index / 64] |= 1 << (index % 64);
+
+                        palette
+                            .cell_color(game, x, y, z)
+                            .map(f32::to_bits)
+                            .hash(&mut colors);
+                    }
+                }
+            }
+        }
+
+        // Leave empty chunks equal to `empty`.
+        if cells.alive.iter().any(|&bits| bits != 0) {
+            cells.colors = colors.finish();
+        }
+
+        cells
+    }
+
+    fn index(position: [usize; 3]) -> usize {
+        (position[1] * CHUNK_SIZE + position[0]) * CHUNK_SIZE + position[2]
+    }
+
+    fn get(&self, position: [usize; 3]) -> bool {
+        let index = Self::index(position);
+        self.alive[index / 64] & (1 << (index % 64)) != 0
+    }
+
+    // Whether the outermost cells of the chunk towards `direction` differ
+    // between the two. `direction` is -1, 0 or 1 along each axis, so it is
+    // a side, an edge or a corner of the chunk. The chunk that way has to be
+    // meshed again if they do, as its faces against this chunk, or the
+    // occlusion baked into them from diagonally across, may have changed.
+    fn boundary_differs(&self, other: &ChunkCells, direction: [i32; 3]) -> bool {
+        let cells = |axis: usize| match direction[axis] {
+            1 => CHUNK_SIZE - 1..CHUNK_SIZE,
+            -1 => 0..1,
+            _ => 0..CHUNK_SIZE,
+        };
+
+        cells(0).any(|x| {
+            cells(1).any(|y| cells(2).any(|z| self.get([x, y, z]) != other.get([x, y, z])))
+        })
+    }
+}
+
+// The 26 chunks around a chunk, through its sides, edges and corners.
+const NEIGHBOUR_DIRECTIONS: [[i32; 3]; 26] = {
+    let mut directions = [[0; 3]; 26];
+    let mut i = 0;
+    let mut n = 0;
+
+    while n < 27 {
+        let direction = [n / 9 - 1, n / 3 % 3 - 1, n % 3 - 1];
+        if n != 13 {
+            directions[i] = direction;
+            i += 1;
+        }
+        n += 1;
+    }
+
+    directions
+};
+
+// Remembers which cells were alive in every chunk when the chunks were last
+// meshed, to work out which of them need meshing again.
+pub struct ChunkTracker {
+    chunks_per_axis: usize,
+    chunks: Vec<ChunkCells>,
+}
+
+impl ChunkTracker {
+    fn new(arena_size: usize) -> ChunkTracker {
+        let chunks_per_axis = arena_size / CHUNK_SIZE;
+
+        ChunkTracker {
+            chunks_per_axis,
+            chunks: vec![ChunkCells::empty(); chunks_per_axis.pow(3)],
+        }
+    }
+
+    fn index(&self, chunk: [usize; 3]) -> usize {
+        chunk_index(chunk, self.chunks_per_axis)
+    }
+
+    // The chunks whose meshes may have changed since the last call.
+    pub fn dirty_chunks(
+        &mut self,
+        game: &GameOfLife,
+        palette: &Palette,
+        clipping: &Clipping,
+    ) -> Vec<[usize; 3]> {
+        let mut dirty = vec![false; self.chunks.len()];
+        let chunks_per_axis = self.chunks_per_axis;
+
+        for y in 0..chunks_per_axis {
+            for x in 0..chunks_per_axis {
+                for z in 0..chunks_per_axis {
+                    let chunk = [x, y, z];
+                    let index = self.index(chunk);
+                    let new_cells = ChunkCells::from_game(game, chunk, palette, clipping);
+                    let old_cells = &self.chunks[index];
+
+                    if *old_cells == new_cells {
+                        continue;
+                    }
+
+                    dirty[index] = true;
+
+                    for direction in NEIGHBOUR_DIRECTIONS {
+                        let neighbour = [0, 1, 2].map(|axis| chunk[axis] as i32 + direction[axis]);
+                        let inside = neighbour
+                            .iter()
+                            .all(|&c| c >= 0 && c < chunks_per_axis as i32);
+
+                        if inside && old_cells.boundary_differs(&new_cells, direction) {
+                            dirty[self.index(neighbour.map(|c| c as usize))] = true;
+                        }
+                    }
+
+                    self.chunks[index] = new_cells;
+                }
+            }
+        }
+
+        let mut chunks = Vec::new();
+        for y in 0..chunks_per_axis {
+            for x in 0..chunks_per_axis {
+                for z in 0..chunks_per_axis {
+                    if dirty[self.index([x, y, z])] {
+                        chunks.push([x, y, z]);
+                    }
+                }
+            }
+        }
+
+        chunks
+    }
+}
+
+// Meshes the live cells of a chunk, with the positions relative to the centre
+// of the chunk's first cell.
+pub fn mesh_chunk(
+    game: &GameOfLife,
+    chunk: [usize; 3],
+    cell_size: f32,
+    palette: &Palette,
+    clipping: &Clipping,
+) -> Mesh {
+    let mut mesh = Mesh::new();
+    let base = chunk.map(|c| (c * CHUNK_SIZE) as i32);
+
+    // Unlike the simulation, the mesh doesn't wrap around the arena, so that
+    // the cells on its edges are closed off. Clipped cells count as dead, so
+    // that the cells along the cut are closed off too.
+    let is_alive = |position: [i32; 3]| {
+        if !position.iter().all(|&p| p >= 0 && p < game.size() as i32) {
+            return false;
+        }
+
+        let [x, y, z] = position.map(|p| p as usize);
+        game.cell(x, y, z).is_alive() && clipping.is_visible(x, y, z)
+    };
+
+    for axis in 0..3 {
+        let (u, v) = face_axes(axis);
+
+        for positive in [true, false] {
+            for layer in 0..CHUNK_SIZE {
+                // The occlusion levels and colours of the exposed faces in
+                // this layer, indexed by v and then u.
+                let mut exposed = [[None; CHUNK_SIZE]; CHUNK_SIZE];
+
+                for (b, row) in exposed.iter_mut().enumerate() {
+                    for (a, face) in row.iter_mut().enumerate() {
+                        let mut position = base;
+                        position[axis] += layer as i32;
+                        position[u] += a as i32;
+                        position[v] += b as i32;
+
+                        let mut in_front = position;
+                        in_front[axis] += if positive { 1 } else { -1 };
+
+                        if is_alive(position) && !is_alive(in_front) {
+                            let levels = CORNERS.map(|(du, dv)| {
+                                let offset = |du: i32, dv: i32| {
+                                    let mut cell = in_front;
+                                    cell[u] += du;
+                                    cell[v] += dv;
+                                    cell
+                                };
+
+                                occlusion_level(
+                                    is_alive(offset(du, 0)),
+                                    is_alive(offset(0, dv)),
+                                    is_alive(offset(du, dv)),
+                                )
+                            });
+                            let color = palette.cell_color(
+                                game,
+                                position[0] as usize,
+                                position[1] as usize,
+                                position[2] as usize,
+                            );
+
+                            *face = Some((levels, color));
+                        }
+                    }
+                }
+
+                // Grow each face as far as it goes along u, then take as
+                // many of the following rows as match it all the way along.
+                // Only faces with the same colour and occlusion get merged,
+                // or the shading would stretch across the merged face.
+                for b in 0..CHUNK_SIZE {
+                    let mut a = 0;
+
+                    while a < CHUNK_SIZE {
+                        let Some((levels, color)) = exposed[b][a] else {
+                            a += 1;
+                            continue;
+                        };
+
+                        let width = exposed[b][a..]
+                            .iter()
+                            .take_while(|&&face| face == Some((levels, color)))
+                            .count();
+                        let height = exposed[b..]
+                            .iter()
+                            .take_while(|row| {
+                                row[a..a + width]
+                                    .iter()
+                                    .all(|&face| face == Some((levels, color)))
+                            })
+                            .count();
+
+                        for row in &mut exposed[b..b + height] {
+                            row[a..a + width].fill(None);
+                        }
+
+                        let mut centre = [0.0; 3];
+                        centre[axis] = layer as f32 * cell_size;
+                        centre[u] = (a as f32 + (width as f32 - 1.0) / 2.0) * cell_size;
+                        centre[v] = (b as f32 + (height as f32 - 1.0) / 2.0) * cell_size;
+
+                        mesh.append_shaded_face(
+                            to_axis(axis),
+                            positive,
+                            cell_size / 2.0,
+                            Vec3::new(centre[0], centre[1], centre[2]),
+                            Vec2::new(width as f32 * cell_size, height as f32 * cell_size),
+                            FaceShading {
+                                occlusion: levels.map(occlusion_brightness),
+                                color,
+                            },
+                        );
+
+                        a += width;
+                    }
+                }
+            }
+        }
+    }
+
+    mesh
+}
+
+// Keeps a mesh for every chunk with live cells in it, and rebuilds the ones
+// that changed whenever the game does.
+pub struct ChunkMesher {
+    cell_size: f32,
+    tracker: ChunkTracker,
+    renderers: Vec<Option<Renderer>>,
+    revision: Option<u64>,
+}
+
+impl ChunkMesher {
+    // `arena_size` has to be a multiple of `CHUNK_SIZE`.
+    pub fn new(cell_size: f32, arena_size: usize) -> ChunkMesher {
+        ChunkMesher {
+            cell_size,
+            tracker: ChunkTracker::new(arena_size),
+            renderers: (0..(arena_size / CHUNK_SIZE).pow(3))
+                .map(|_| None)
+                .collect(),
+            revision: None,
+        }
+    }
+
+    fn rebuild_chunk(
+        &mut self,
+        game: &GameOfLife,
+        chunk: [usize; 3],
+        palette: &Palette,
+        clipping: &Clipping,
+    ) {
+        let mesh = mesh_chunk(game, chunk, self.cell_size, palette, clipping);
+        let index = self.tracker.index(chunk);
+
+        // A chunk keeps its buffers once it has had cells in it, even if it
+        // empties, as they tend to come back.
+        if let Some(renderer) = &mut self.renderers[index] {
+            renderer.replace_mesh(&mesh);
+            return;
+        }
+
+        if mesh.is_empty() {
+            return;
+        }
+
+        // The chunk gets drawn as a single instance, placed where its first
+        // cell is.
+        let mut renderer = Renderer::new(&mesh);
+        let origin = chunk.map(|c| {
+            GameOfLife::to_real_coords((c * CHUNK_SIZE) as f32, game.size(), self.cell_size)
+        });
+        renderer.add_instance(CellInstance::new(Vec3::new(
+            origin[0], origin[1], origin[2],
+        )));
+
+        self.renderers[index] = Some(renderer);
+    }
+
+    // Checks every chunk for changes next time, for when something other
+    // than the game changed how they should look, like the palette or the
+    // clipping.
+    pub fn invalidate(&mut self) {
+        self.revision = None;
+    }
+
+    // Draws the chunks with the main shader, which has to be in use already.
+    pub fn render(&mut self, game: &GameOfLife, palette: &Palette, clipping: &Clipping) {
+        if self.revision != Some(game.revision()) {
+            for chunk in self.tracker.dirty_chunks(game, palette, clipping) {
+                self.rebuild_chunk(game, chunk, palette, clipping);
+            }
+
+            self.revision = Some(game.revision());
+        }
+
+        for renderer in self.renderers.iter_mut().flatten() {
+            if !renderer.is_empty() {
+                renderer.render_many();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{mesh_chunk, ChunkTracker, CHUNK_SIZE};
+    use crate::{
+        clipping::Clipping,
+        colors::{ColorMode, Palette, AGE_BANDS},
+        game::{Cell, GameOfLife, Rule},
+        renderer::{occlusion_brightness, Mesh},
+    };
+
+    // Meshes a chunk with nothing clipped, one unit per cell.
+    fn plain_mesh(game: &GameOfLife, chunk: [usize; 3]) -> Mesh {
+        mesh_chunk(game, chunk, 1.0, &Palette::default(), &Clipping::default())
+    }
+
+    // The smallest and largest vertex positions of a mesh.
+    fn extent(mesh: &Mesh) -> ([f32; 3], [f32; 3]) {
+        mesh.positions()
+            .fold(([f32::MAX; 3], [f32::MIN; 3]), |(min, max), p| {
+                (
+                    [min[0].min(p.x), min[1].min(p.y), min[2].min(p.z)],
+                    [max[0].max(p.x), max[1].max(p.y), max[2].max(p.z)],
+                )
+            })
+    }
+
+    #[test]
+    fn single_cell() {
+        let mut game = Box::new(GameOfLife::new());
+        game.set_cell(1, 2, 3, Cell::Alive);
+
+        let mesh = plain_mesh(&game, [0, 0, 0]);
+        assert_eq!(mesh.face_count(), 6);
+        assert_eq!(extent(&mesh), ([0.5, 1.5, 2.5], [1.5, 2.5, 3.5]));
+    }
+
+    #[test]
+    fn buried_faces_are_culled_and_runs_merged() {
+        let mut game = Box::new(GameOfLife::new());
+
+        // A 3x2x2 block still only needs one rectangle per side.
+        for x in 4..7 {
+            for y in 0..2 {
+                for z in 0..2 {
+                    game.set_cell(x, y, z, Cell::Alive);
+                }
+            }
+        }
+
+        let mesh = plain_mesh(&game, [0, 0, 0]);
+        assert_eq!(mesh.face_count(), 6);
+        assert_eq!(extent(&mesh), ([3.5, -0.5, -0.5], [6.5, 1.5, 1.5]));
+    }
+
+    #[test]
+    fn greedy_merge_stays_inside_the_shape() {
+        let mut game = Box::new(GameOfLife::new());
+
+        // An L in the xz plane. Its top can't be one rectangle, and
+        // neither can its sides facing -z and +x.
+        game.set_cell(0, 0, 0, Cell::Alive);
+        game.set_cell(1, 0, 0, Cell::Alive);
+        game.set_cell(0, 0, 1, Cell::Alive);
+
+        let mesh = plain_mesh(&game, [0, 0, 0]);
+        // Top and bottom: 2 each. -x, -z: 1 each. The +x and +z sides are
+        // stepped: 2 each.
+        assert_eq!(mesh.face_count(), 10);
+    }
+
+    #[test]
+    fn faces_between_chunks_are_culled() {
+        let mut game = Box::new(GameOfLife::new());
+        game.set_cell(CHUNK_SIZE - 1, 0, 0, Cell::Alive);
+        game.set_cell(CHUNK_SIZE, 0, 0, Cell::Alive);
+
+        assert_eq!(plain_mesh(&game, [0, 0, 0]).face_count(), 5);
+        assert_eq!(plain_mesh(&game, [1, 0, 0]).face_count(), 5);
+    }
+
+    #[test]
+    fn only_changed_chunks_are_dirty() {
+        let mut game = Box::new(GameOfLife::new());
+        let mut tracker = ChunkTracker::new(game.size());
+        let mut palette = Palette::default();
+        let clipping = Clipping::default();
+        assert!(tracker.dirty_chunks(&game, &palette, &clipping).is_empty());
+
+        game.set_cell(20, 5, 40, Cell::Alive);
+        assert_eq!(
+            tracker.dirty_chunks(&game, &palette, &clipping),
+            vec![[1, 0, 2]]
+        );
+        assert!(tracker.dirty_chunks(&game, &palette, &clipping).is_empty());
+
+        // On the edge of a chunk, the neighbour's faces against it change
+        // too.
+        game.set_cell(20, 5, 47, Cell::Alive);
+        assert_eq!(
+            tracker.dirty_chunks(&game, &palette, &clipping),
+            vec![[1, 0, 2], [1, 0, 3]]
+        );
+
+        // Unless the neighbour is past the end of the arena.
+        game.set_cell(0, 0, 0, Cell::Alive);
+        assert_eq!(
+            tracker.dirty_chunks(&game, &palette, &clipping),
+            vec![[0, 0, 0]]
+        );
+
+        // Cells in chunks further up are tracked too.
+        game.set_cell(40, 20, 40, Cell::Alive);
+        assert_eq!(
+            tracker.dirty_chunks(&game, &palette, &clipping),
+            vec![[2, 1, 2]]
+        );
+
+        // Recolouring the cells changes only the chunks they are in.
+        palette.mode = ColorMode::Layer;
+        assert_eq!(
+            tracker.dirty_chunks(&game, &palette, &clipping),
+            vec![[0, 0, 0], [1, 0, 2], [2, 1, 2]]
+        );
+    }
+
+    #[test]
+    fn chunks_across_edges_and_corners_are_dirty() {
+        let mut game = GameOfLife::with_size(48);
+        let mut tracker = ChunkTracker::new(game.size());
+        let palette = Palette::default();
+        let clipping = Clipping::default();
+
+        // Occlusion is baked from diagonally across, so the chunk past an
+        // edge changes too...
+        game.set_cell(15, 1, 15, Cell::Alive);
+        assert_eq!(
+            tracker.dirty_chunks(&game, &palette, &clipping),
+            vec![[0, 0, 0], [0, 0, 1], [1, 0, 0], [1, 0, 1]]
+        );
+
+        // ...and so does the one past a corner.
+        game.set_cell(31, 31, 31, Cell::Alive);
+        assert_eq!(
+            tracker.dirty_chunks(&game, &palette, &clipping),
+            vec![
+                [1, 1, 1],
+                [1, 1, 2],
+                [2, 1, 1],
+                [2, 1, 2],
+                [1, 2, 1],
+                [1, 2, 2],
+                [2, 2, 1],
+                [2, 2, 2]
+            ]
+        );
+    }
+
+    #[test]
+    fn stable_aged_cells_are_not_rebuilt() {
+        let mut game = GameOfLife::with_size(16);
+        // Every cell of a 2x2x2 block has 7 neighbours, so it stays as it
+        // is.
+        game.set_rule(Rule::new(&[], &[7]));
+        for x in 4..6 {
+            for y in 4..6 {
+                for z in 4..6 {
+                    game.set_cell(x, y, z, Cell::Alive);
+                }
+            }
+        }
+
+        let mut tracker = ChunkTracker::new(game.size());
+        let palette = Palette::default();
+        let clipping = Clipping::default();
+        assert_eq!(palette.mode, ColorMode::Age);
+        assert_eq!(
+            tracker.dirty_chunks(&game, &palette, &clipping),
+            vec![[0, 0, 0]]
+        );
+
+        // The block changes colour only a few times on its way to old...
+        let mut rebuilds = 0;
+        for _ in 0..palette.old_age {
+            game.update_game();
+            rebuilds += tracker.dirty_chunks(&game, &palette, &clipping).len();
+        }
+        assert_eq!(rebuilds, AGE_BANDS as usize);
+
+        // ...and never once it's there.
+        for _ in 0..50 {
+            game.update_game();
+            assert!(tracker.dirty_chunks(&game, &palette, &clipping).is_empty());
+        }
+    }
+
+    #[test]
+    fn occlusion_is_baked_and_splits_faces() {
+        let mut game = Box::new(GameOfLife::new());
+
+        // A 3x3 floor with a cell standing in the middle of it.
+        for x in 0..3 {
+            for z in 0..3 {
+                game.set_cell(x, 0, z, Cell::Alive);
+            }
+        }
+        game.set_cell(1, 1, 1, Cell::Alive);
+
+        let mesh = plain_mesh(&game, [0, 0, 0]);
+        // The bottom corners of the standing cell's sides have the floor on
+        // one side and diagonally.
+        let darkest = mesh.occlusion().fold(1.0, f32::min);
+        assert_eq!(darkest, occlusion_brightness(2));
+
+        // Without occlusion, the top of the floor would be merged into 4
+        // faces. The 8 visible cells of it are all shaded differently, so
+        // none of them can be merged. The standing cell has 5 faces, and the
+        // rest of the floor 1 face each way.
+        assert_eq!(mesh.face_count(), 8 + 5 + 5);
+    }
+
+    #[test]
+    fn only_faces_of_the_same_colour_merge() {
+        let mut game = Box::new(GameOfLife::new());
+        game.set_cell(0, 0, 0, Cell::Alive);
+        game.set_cell(1, 0, 0, Cell::Alive);
+
+        let mut palette = Palette::default();
+        let clipping = Clipping::default();
+        assert_eq!(
+            mesh_chunk(&game, [0, 0, 0], 1.0, &palette, &clipping).face_count(),
+            6
+        );
+
+        // The two cells are the same age, but not the same height.
+        game.set_cell(1, 0, 0, Cell::Dead);
+        game.set_cell(0, 1, 0, Cell::Alive);
+        palette.mode = ColorMode::Layer;
+        assert_eq!(
+            mesh_chunk(&game, [0, 0, 0], 1.0, &palette, &clipping).face_count(),
+            10
+        );
+    }
+
+    #[test]
+    fn clipped_cells_are_closed_off() {
+        let mut game = Box::new(GameOfLife::new());
+        for x in 0..4 {
+            game.set_cell(x, 0, 0, Cell::Alive);
+        }
+
+        let palette = Palette::default();
+        let mut clipping = Clipping::default();
+        clipping.cycle_plane(0, 1);
+
+        let mesh = mesh_chunk(&game, [0, 0, 0], 1.0, &palette, &clipping);
+        assert_eq!(mesh.face_count(), 6);
+        assert_eq!(extent(&mesh), ([-0.5, -0.5, -0.5], [1.5, 0.5, 0.5]));
+    }
+}
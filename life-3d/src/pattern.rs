@@ -0,0 +1,151 @@
+// Patterns of live cells read from a file, to start the arena off with. Each
+// line of the file has the x, y and z of a live cell, relative to the middle
+// of the arena, separated by spaces. Empty lines and lines starting with #
+// are skipped.
+
+use std::{fmt, fs, io, path::Path};
+
+use crate::game::{Cell, GameOfLife};
+
+#[derive(Debug)]
+pub enum PatternError {
+    Io(io::Error),
+    // A line that isn't three whole numbers, counting lines from 1.
+    Parse { line: usize, text: String },
+    // A cell that doesn't fit in an arena `arena_size` cells across.
+    OutsideArena { cell: [i32; 3], arena_size: usize },
+}
+
+impl fmt::Display for PatternError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PatternError::Io(error) => write!(f, "{}", error),
+            PatternError::Parse { line, text } => write!(
+                f,
+                "line {} should be the x, y and z of a cell, like \"0 1 -2\", but it is {:?}",
+                line, text
+            ),
+            PatternError::OutsideArena { cell, arena_size } => write!(
+                f,
+                "the cell at {:?} is outside of the arena, which goes from {} to {} along each \
+                 axis",
+                cell,
+                -((arena_size / 2) as i32),
+                arena_size - arena_size / 2 - 1
+            ),
+        }
+    }
+}
+
+impl std::error::Error for PatternError {}
+
+impl From<io::Error> for PatternError {
+    fn from(error: io::Error) -> Self {
+        PatternError::Io(error)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Pattern {
+    pub cells: Vec<[i32; 3]>,
+}
+
+impl Pattern {
+    pub fn load(path: &Path) -> Result<Pattern, PatternError> {
+        Pattern::parse(&fs::read_to_string(path)?)
+    }
+
+    pub fn parse(contents: &str) -> Result<Pattern, PatternError> {
+        let mut cells = Vec::new();
+
+        for (i, line) in contents.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let error = || PatternError::Parse {
+                line: i + 1,
+                text: line.to_string(),
+            };
+
+            let coordinates = line
+                .split_whitespace()
+                .map(|coordinate| coordinate.parse().map_err(|_| error()))
+                .collect::<Result<Vec<i32>, _>>()?;
+            cells.push(coordinates.try_into().map_err(|_| error())?);
+        }
+
+        Ok(Pattern { cells })
+    }
+
+    // Brings the pattern's cells to life in the middle of `game`. Nothing is
+    // changed if any of them wouldn't fit.
+    pub fn place(&self, game: &mut GameOfLife) -> Result<(), PatternError> {
+        let middle = (game.size() / 2) as i32;
+        let to_arena = |cell: &[i32; 3]| {
+            let position = cell.map(|c| c + middle);
+            position
+                .iter()
+                .all(|&c| c >= 0 && c < game.size() as i32)
+                .then_some(position.map(|c| c as usize))
+                .ok_or(PatternError::OutsideArena {
+                    cell: *cell,
+                    arena_size: game.size(),
+                })
+        };
+
+        let positions = self
+            .cells
+            .iter()
+            .map(to_arena)
+            .collect::<Result<Vec<_>, _>>()?;
+        for [x, y, z] in positions {
+            game.set_cell(x, y, z, Cell::Alive);
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Pattern, PatternError};
+    use crate::game::GameOfLife;
+
+    #[test]
+    fn parse_patterns() {
+        let pattern = Pattern::parse("# A line\n\n0 0 0\n  1 -2 3\n").unwrap();
+        assert_eq!(pattern.cells, vec![[0, 0, 0], [1, -2, 3]]);
+
+        for (contents, bad_line) in [("0 0 0\n1 2\n", 2), ("0 0 x", 1), ("\n\n1 2 3 4", 3)] {
+            match Pattern::parse(contents) {
+                Err(PatternError::Parse { line, .. }) => assert_eq!(line, bad_line),
+                result => panic!("{:?} gave {:?}", contents, result),
+            }
+        }
+    }
+
+    #[test]
+    fn placed_in_the_middle() {
+        let mut game = GameOfLife::with_size(16);
+        Pattern::parse("0 0 0\n-8 7 1")
+            .unwrap()
+            .place(&mut game)
+            .unwrap();
+
+        assert!(game.cell(8, 8, 8).is_alive());
+        assert!(game.cell(0, 15, 9).is_alive());
+        assert_eq!(game.population(), 2);
+
+        let too_far = Pattern::parse("1 1 1\n0 8 0").unwrap();
+        assert!(matches!(
+            too_far.place(&mut game),
+            Err(PatternError::OutsideArena {
+                cell: [0, 8, 0],
+                arena_size: 16
+            })
+        ));
+        assert_eq!(game.population(), 2);
+    }
+}
@@ -1,8 +1,12 @@
 use glad_gl::gl::{self, GLsizei};
 
 use crate::{
-    buffers::{Buffer, BufferAttributes, BufferType, VertexArray},
-    math::{Vec2, Vec3},
+    buffers::{
+        std430_mismatch, Buffer, BufferAttributes, BufferType, GlslType, InstanceData,
+        InstanceField, Texture, VertexArray,
+    },
+    font::{self, FontAtlas, GLYPH_HEIGHT, GLYPH_WIDTH},
+    math::{Mat4, Vec2, Vec3},
 };
 
 use std::{
@@ -15,6 +19,10 @@ struct Vertex {
     position: Vec3,
     normal: Vec3,
     uv: Vec2,
+    // How much of the ambient light reaches the vertex, see
+    // `occlusion_brightness`.
+    occlusion: f32,
+    color: [f32; 4],
 }
 
 unsafe impl BufferAttributes for Vertex {
@@ -48,6 +56,26 @@ unsafe impl BufferAttributes for Vertex {
             offset_of!(Vertex, uv) as *const c_void,
         );
         gl::EnableVertexAttribArray(2);
+
+        gl::VertexAttribPointer(
+            3,
+            1,
+            gl::FLOAT,
+            gl::FALSE,
+            size_of::<Vertex>() as i32,
+            offset_of!(Vertex, occlusion) as *const c_void,
+        );
+        gl::EnableVertexAttribArray(3);
+
+        gl::VertexAttribPointer(
+            4,
+            4,
+            gl::FLOAT,
+            gl::FALSE,
+            size_of::<Vertex>() as i32,
+            offset_of!(Vertex, color) as *const c_void,
+        );
+        gl::EnableVertexAttribArray(4);
     }
 }
 
@@ -86,6 +114,36 @@ impl BarsMesh {
                 position: *pos + offset,
                 normal: Vec3::new(0.0, 0.0, 0.0),
                 uv: Vec2::new(0.0, 0.0),
+                occlusion: 1.0,
+                color: [1.0; 4],
+            })
+        });
+
+        [0, 1, 2, 0, 3, 2]
+            .iter()
+            .for_each(|i| self.mesh.indices.push(i + vertex_offset));
+
+        self.bar_count += 1;
+    }
+
+    // A rectangle between two opposite corners, counted as a bar of its own.
+    pub fn append_rect(&mut self, min: Vec2, max: Vec2, color: [f32; 4]) {
+        let vertex_offset: u32 = self.mesh.vertices.len().try_into().unwrap();
+
+        [
+            Vec3::new(max.x, min.y, 0.0),
+            Vec3::new(max.x, max.y, 0.0),
+            Vec3::new(min.x, max.y, 0.0),
+            Vec3::new(min.x, min.y, 0.0),
+        ]
+        .into_iter()
+        .for_each(|position| {
+            self.mesh.vertices.push(Vertex {
+                position,
+                normal: Vec3::new(0.0, 0.0, 0.0),
+                uv: Vec2::new(0.0, 0.0),
+                occlusion: 1.0,
+                color,
             })
         });
 
@@ -95,6 +153,183 @@ impl BarsMesh {
 
         self.bar_count += 1;
     }
+
+    pub fn clear(&mut self) {
+        self.mesh.vertices.clear();
+        self.mesh.indices.clear();
+        self.bar_count = 0;
+    }
+
+    pub fn bar_count(&self) -> u32 {
+        self.bar_count
+    }
+}
+
+// Pairs of vertices, each pair drawn as a line, with `LineRenderer`.
+pub struct LineMesh {
+    mesh: Mesh,
+}
+
+impl LineMesh {
+    pub fn new() -> LineMesh {
+        LineMesh { mesh: Mesh::new() }
+    }
+
+    pub fn append_line(&mut self, from: Vec3, to: Vec3, color: [f32; 4]) {
+        let vertex_offset: u32 = self.mesh.vertices.len().try_into().unwrap();
+
+        for position in [from, to] {
+            self.mesh.vertices.push(Vertex {
+                position,
+                normal: Vec3::new(0.0, 0.0, 0.0),
+                uv: Vec2::new(0.0, 0.0),
+                occlusion: 1.0,
+                color,
+            });
+        }
+
+        self.mesh
+            .indices
+            .extend_from_slice(&[vertex_offset, vertex_offset + 1]);
+    }
+
+    // The twelve edges of the box between two opposite corners.
+    pub fn append_box(&mut self, min: Vec3, max: Vec3, color: [f32; 4]) {
+        let corner = |i: usize| {
+            Vec3::new(
+                if i & 1 == 0 { min.x } else { max.x },
+                if i & 2 == 0 { min.y } else { max.y },
+                if i & 4 == 0 { min.z } else { max.z },
+            )
+        };
+
+        for i in 0..8 {
+            // Each edge goes from the corner without a bit to the one with it.
+            for bit in [1, 2, 4] {
+                if i & bit == 0 {
+                    self.append_line(corner(i), corner(i | bit), color);
+                }
+            }
+        }
+    }
+
+    pub fn line_count(&self) -> usize {
+        self.mesh.indices.len() / 2
+    }
+
+    pub fn positions(&self) -> impl Iterator<Item = Vec3> + '_ {
+        self.mesh.vertices.iter().map(|vertex| vertex.position)
+    }
+}
+
+// The projection for drawing on top of everything in pixels, with (0, 0) in
+// the top left corner and y going down.
+pub fn screen_projection(width: f32, height: f32) -> Mat4 {
+    Mat4::orthographic(0.0, width, 0.0, height, 1.0, -1.0)
+}
+
+// Strings laid out as a quad per character, in pixels for
+// `screen_projection`, to be drawn with `TextRenderer`.
+pub struct TextMesh {
+    mesh: Mesh,
+    atlas: FontAtlas,
+}
+
+impl TextMesh {
+    pub fn new() -> TextMesh {
+        TextMesh {
+            mesh: Mesh::new(),
+            atlas: FontAtlas::build(),
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.mesh.vertices.clear();
+        self.mesh.indices.clear();
+    }
+
+    // Lays out `text` with its top left corner at `position`, with each
+    // pixel of the font `pixel_size` pixels on the screen. Each line goes
+    // under the one before it.
+    pub fn append_text(&mut self, text: &str, position: Vec2, pixel_size: f32, color: [f32; 4]) {
+        let (width, height) = (
+            GLYPH_WIDTH as f32 * pixel_size,
+            GLYPH_HEIGHT as f32 * pixel_size,
+        );
+
+        for (row, line) in text.lines().enumerate() {
+            for (column, character) in line.chars().enumerate() {
+                if character == ' ' {
+                    continue;
+                }
+
+                let left = position.x + column as f32 * (width + pixel_size);
+                let top = position.y + row as f32 * (height + 2.0 * pixel_size);
+                let (uv_min, uv_max) = self.atlas.glyph_uv(font::glyph_index(character));
+
+                let vertex_offset: u32 = self.mesh.vertices.len().try_into().unwrap();
+                [
+                    (
+                        Vec3::new(left + width, top, 0.0),
+                        Vec2::new(uv_max.x, uv_min.y),
+                    ),
+                    (Vec3::new(left + width, top + height, 0.0), uv_max),
+                    (
+                        Vec3::new(left, top + height, 0.0),
+                        Vec2::new(uv_min.x, uv_max.y),
+                    ),
+                    (Vec3::new(left, top, 0.0), uv_min),
+                ]
+                .into_iter()
+                .for_each(|(position, uv)| {
+                    self.mesh.vertices.push(Vertex {
+                        position,
+                        normal: Vec3::new(0.0, 0.0, 0.0),
+                        uv,
+                        occlusion: 1.0,
+                        color,
+                    })
+                });
+
+                [0, 1, 2, 0, 3, 2]
+                    .iter()
+                    .for_each(|i| self.mesh.indices.push(i + vertex_offset));
+            }
+        }
+    }
+
+    pub fn glyph_count(&self) -> usize {
+        self.mesh.indices.len() / 6
+    }
+
+    pub fn positions(&self) -> impl Iterator<Item = Vec3> + '_ {
+        self.mesh.vertices.iter().map(|vertex| vertex.position)
+    }
+}
+
+impl Default for TextMesh {
+    fn default() -> TextMesh {
+        TextMesh::new()
+    }
+}
+
+// What a face looks like, besides the lighting.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FaceShading {
+    // The brightness of each corner, from `occlusion_brightness`. The corners
+    // go (+x, +y), (+x, -y), (-x, -y), (-x, +y), with x and y as they are for
+    // the size of the face.
+    pub occlusion: [f32; 4],
+    pub color: [f32; 4],
+}
+
+impl Default for FaceShading {
+    fn default() -> FaceShading {
+        FaceShading {
+            occlusion: [1.0; 4],
+            color: [1.0; 4],
+        }
+    }
 }
 
 pub enum Axis {
@@ -128,70 +363,225 @@ impl Mesh {
     }
 
     pub fn append_cube_face(&mut self, size: f32, axis: Axis, positive: bool, depth: f32) {
-        let size = size * 0.5;
-        let values = [
+        self.append_face(
+            axis,
+            positive,
+            depth,
+            Vec3::new(0.0, 0.0, 0.0),
             Vec2::new(size, size),
-            Vec2::new(size, -size),
-            Vec2::new(-size, -size),
-            Vec2::new(-size, size),
+        );
+    }
+
+    // Appends a `size.x` by `size.y` rectangle facing along `axis`, `depth`
+    // away from `centre` on the positive or negative side. `size.x` runs
+    // along z for X faces and along x otherwise, `size.y` along the
+    // remaining axis.
+    pub fn append_face(
+        &mut self,
+        axis: Axis,
+        positive: bool,
+        depth: f32,
+        centre: Vec3,
+        size: Vec2,
+    ) {
+        self.append_shaded_face(axis, positive, depth, centre, size, FaceShading::default());
+    }
+
+    // Like `append_face`, with a colour and ambient occlusion.
+    pub fn append_shaded_face(
+        &mut self,
+        axis: Axis,
+        positive: bool,
+        depth: f32,
+        centre: Vec3,
+        size: Vec2,
+        shading: FaceShading,
+    ) {
+        let FaceShading { occlusion, color } = shading;
+        let (half_width, half_height) = (size.x * 0.5, size.y * 0.5);
+        let values = [
+            Vec2::new(half_width, half_height),
+            Vec2::new(half_width, -half_height),
+            Vec2::new(-half_width, -half_height),
+            Vec2::new(-half_width, half_height),
         ];
 
-        let depth_value = if positive { depth } else { -depth };
+        let sign = if positive { 1.0 } else { -1.0 };
+        let depth_value = depth * sign;
         // Save it here, as we will be appending stuff to the vertices vector
         // later on.
         let vertex_offset: u32 = self.vertices.len().try_into().unwrap();
 
         match axis {
             Axis::X => {
-                for value in values.as_ref() {
+                for (value, occlusion) in values.iter().zip(occlusion) {
                     self.vertices.push(Vertex {
-                        position: Vec3::new(depth_value, value.y, value.x),
-                        normal: Vec3::new(1.0, 0.0, 0.0),
+                        position: centre + Vec3::new(depth_value, value.y, value.x),
+                        normal: Vec3::new(sign, 0.0, 0.0),
                         uv: Vec2::new(0.0, 0.0), // TODO: Add the shader coordinates later.
+                        occlusion,
+                        color,
                     })
                 }
             }
             Axis::Y => {
-                for value in values.as_ref() {
+                for (value, occlusion) in values.iter().zip(occlusion) {
                     self.vertices.push(Vertex {
-                        position: Vec3::new(value.x, depth_value, value.y),
-                        normal: Vec3::new(0.0, 1.0, 0.0),
+                        position: centre + Vec3::new(value.x, depth_value, value.y),
+                        normal: Vec3::new(0.0, sign, 0.0),
                         uv: Vec2::new(0.0, 0.0), // TODO: Add the shader coordinates later.
+                        occlusion,
+                        color,
                     })
                 }
             }
             Axis::Z => {
-                for value in values.as_ref() {
+                for (value, occlusion) in values.iter().zip(occlusion) {
                     self.vertices.push(Vertex {
-                        position: Vec3::new(value.x, value.y, depth_value),
-                        normal: Vec3::new(0.0, 0.0, 1.0),
+                        position: centre + Vec3::new(value.x, value.y, depth_value),
+                        normal: Vec3::new(0.0, 0.0, sign),
                         uv: Vec2::new(0.0, 0.0), // TODO: Add the shader coordinates later.
+                        occlusion,
+                        color,
                     })
                 }
             }
         }
 
-        let indices = [0, 1, 2, 0, 3, 2];
+        // Split the quad along the diagonal with the brighter corners, or the
+        // occlusion gets smeared across the face unevenly.
+        let indices = if occlusion[0] + occlusion[2] < occlusion[1] + occlusion[3] {
+            [1, 2, 3, 1, 0, 3]
+        } else {
+            [0, 1, 2, 0, 3, 2]
+        };
 
         for index in indices {
             self.indices.push(index + vertex_offset);
         }
     }
+
+    pub fn is_empty(&self) -> bool {
+        self.indices.is_empty()
+    }
+
+    pub fn face_count(&self) -> usize {
+        self.indices.len() / 6
+    }
+
+    pub fn positions(&self) -> impl Iterator<Item = Vec3> + '_ {
+        self.vertices.iter().map(|vertex| vertex.position)
+    }
+
+    pub fn occlusion(&self) -> impl Iterator<Item = f32> + '_ {
+        self.vertices.iter().map(|vertex| vertex.occlusion)
+    }
 }
 
-pub struct Renderer {
-    _vertex_buffer: Buffer,
+// The classic voxel ambient occlusion test for a corner of a face: how many
+// of the three cells touching the corner, in front of the face, are alive.
+// With both sides taken, the corner is as dark as it gets whatever the cell
+// diagonal to it is.
+pub fn occlusion_level(side1: bool, side2: bool, corner: bool) -> u8 {
+    if side1 && side2 {
+        3
+    } else {
+        side1 as u8 + side2 as u8 + corner as u8
+    }
+}
+
+// Must match `occlusion_brightness` in main.vert.
+pub fn occlusion_brightness(level: u8) -> f32 {
+    1.0 - 0.2 * level as f32
+}
+
+// What the main shader gets for every cell it draws. `vec3`s and `vec4`s are
+// 16-byte aligned under std430, so the scale sits in the offset's padding,
+// and the struct itself is aligned to 16 to give it the right array stride.
+// (`Vec3` is 16 bytes long itself, so it can't be used for the offset.)
+#[repr(C, align(16))]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CellInstance {
+    pub offset: [f32; 3],
+    pub scale: f32,
+    pub color: [f32; 4],
+    // How many generations the cell has been alive for.
+    pub age: u32,
+    // Free for the colour modes to use, 0 for a plain live cell.
+    pub state: u32,
+    // Which of the 26 surrounding cells are alive, for ambient occlusion, as
+    // from `GameOfLife::neighbour_mask`.
+    pub neighbours: u32,
+}
+
+impl CellInstance {
+    pub fn new(offset: Vec3) -> CellInstance {
+        CellInstance {
+            offset: [offset.x, offset.y, offset.z],
+            scale: 1.0,
+            color: [1.0, 1.0, 1.0, 1.0],
+            age: 0,
+            state: 0,
+            neighbours: 0,
+        }
+    }
+}
+
+unsafe impl InstanceData for CellInstance {
+    const GLSL_NAME: &'static str = "CellInstance";
+    const FIELDS: &'static [InstanceField] = &[
+        InstanceField {
+            name: "offset",
+            glsl_type: GlslType::Vec3,
+            offset: offset_of!(CellInstance, offset),
+        },
+        InstanceField {
+            name: "scale",
+            glsl_type: GlslType::Float,
+            offset: offset_of!(CellInstance, scale),
+        },
+        InstanceField {
+            name: "color",
+            glsl_type: GlslType::Vec4,
+            offset: offset_of!(CellInstance, color),
+        },
+        InstanceField {
+            name: "age",
+            glsl_type: GlslType::Uint,
+            offset: offset_of!(CellInstance, age),
+        },
+        InstanceField {
+            name: "state",
+            glsl_type: GlslType::Uint,
+            offset: offset_of!(CellInstance, state),
+        },
+        InstanceField {
+            name: "neighbours",
+            glsl_type: GlslType::Uint,
+            offset: offset_of!(CellInstance, neighbours),
+        },
+    ];
+}
+
+pub struct Renderer<I: InstanceData = CellInstance> {
+    vertex_buffer: Buffer,
     element_buffer: Buffer,
     vertex_array: VertexArray,
 
-    instance_offsets: Vec<Vec3>,
+    instances: Vec<I>,
     shader_storage_buffer: Buffer,
+    // Whether `instances` changed since they were last uploaded.
+    instances_dirty: bool,
+    // Whatever the instances were last built from, see `set_revision`.
+    revision: Option<u64>,
 
     indices_count: GLsizei,
 }
 
-impl Renderer {
-    pub fn new(target_mesh: &Mesh) -> Renderer {
+impl<I: InstanceData> Renderer<I> {
+    pub fn new(target_mesh: &Mesh) -> Renderer<I> {
+        debug_assert_eq!(std430_mismatch::<I>(), None);
+
         let vertex_buffer = Buffer::with_data(BufferType::Vertex, target_mesh.vertices.as_slice());
         let element_buffer = Buffer::with_data(BufferType::Index, target_mesh.indices.as_slice());
         let ssbo = Buffer::new(BufferType::ShaderStorage);
@@ -204,73 +594,110 @@ impl Renderer {
         }
 
         Renderer {
-            _vertex_buffer: vertex_buffer,
-            instance_offsets: Vec::new(),
+            vertex_buffer,
+            instances: Vec::new(),
             element_buffer,
             shader_storage_buffer: ssbo,
+            instances_dirty: true,
+            revision: None,
             vertex_array,
             indices_count: target_mesh.indices.len() as i32,
         }
     }
 
-    pub fn add_instance(&mut self, offset: Vec3) {
-        self.instance_offsets.push(offset);
+    // Replaces the mesh that gets drawn, reusing the buffers.
+    pub fn replace_mesh(&mut self, mesh: &Mesh) {
+        self.vertex_array.bind();
+        self.vertex_buffer.upload_dynamic(&mesh.vertices);
+        self.element_buffer.upload_dynamic(&mesh.indices);
+        VertexArray::unbind();
+
+        self.indices_count = mesh.indices.len() as GLsizei;
+    }
+
+    // Whether the mesh has nothing in it to draw.
+    pub fn is_empty(&self) -> bool {
+        self.indices_count == 0
+    }
+
+    pub fn add_instance(&mut self, instance: I) {
+        self.instances.push(instance);
+        self.instances_dirty = true;
     }
 
     pub fn remove_all_instances(&mut self) {
-        self.instance_offsets.clear();
+        self.instances.clear();
+        self.instances_dirty = true;
+        self.revision = None;
     }
 
-    pub fn render_many(&self) {
-        self.vertex_array.bind();
-        self.element_buffer.bind();
-        self.shader_storage_buffer.bind();
+    // The revision of whatever the instances were built from. Callers bump
+    // their own revision whenever the instances would come out differently,
+    // and skip rebuilding them (and uploading them) while it matches.
+    pub fn revision(&self) -> Option<u64> {
+        self.revision
+    }
 
-        unsafe {
-            gl::BufferData(
-                gl::SHADER_STORAGE_BUFFER,
-                (self.instance_offsets.capacity() * size_of::<Vec3>())
-                    .try_into()
-                    .unwrap(),
-                self.instance_offsets.as_ptr() as *const c_void,
-                gl::STATIC_DRAW,
-            );
+    pub fn set_revision(&mut self, revision: u64) {
+        self.revision = Some(revision);
+    }
 
-            self.shader_storage_buffer.bind_base(0);
+    // Forces the instances to be rebuilt next time, for when something other
+    // than the revision changed how they should look.
+    pub fn invalidate_instances(&mut self) {
+        self.revision = None;
+    }
 
-            /* gl::DrawElements(
-                gl::TRIANGLES,
-                self.indices_count,
-                gl::UNSIGNED_INT,
-                std::ptr::null(),
-            ); */
+    fn upload_instances(&mut self) {
+        if self.instances_dirty {
+            self.shader_storage_buffer
+                .upload_dynamic(self.instances.as_slice());
+            self.instances_dirty = false;
+        }
+
+        self.shader_storage_buffer.bind_base(0);
+    }
 
+    pub fn render_many(&mut self) {
+        self.vertex_array.bind();
+        self.element_buffer.bind();
+        self.upload_instances();
+
+        unsafe {
             gl::DrawElementsInstanced(
                 gl::TRIANGLES,
                 self.indices_count,
                 gl::UNSIGNED_INT,
                 std::ptr::null(),
-                self.instance_offsets.len().try_into().unwrap(),
+                self.instances.len().try_into().unwrap(),
             );
         }
     }
 
+    // Like `render_many`, but blends the instances over what is already
+    // drawn without hiding anything behind them, for see-through instances.
+    pub fn render_many_blended(&mut self) {
+        unsafe {
+            gl::Enable(gl::BLEND);
+            gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
+            gl::DepthMask(gl::FALSE);
+        }
+
+        self.render_many();
+
+        unsafe {
+            gl::DepthMask(gl::TRUE);
+            gl::Disable(gl::BLEND);
+        }
+    }
+
+    // Draws the mesh once. With `use_ssbo`, the instances from the last
+    // `render_many` stay bound for the shader to use.
     pub fn render_one(&self, use_ssbo: bool) {
         self.vertex_array.bind();
         self.element_buffer.bind();
 
         if use_ssbo {
-            unsafe {
-                gl::BufferData(
-                    gl::SHADER_STORAGE_BUFFER,
-                    (self.instance_offsets.capacity() * size_of::<Vec3>())
-                        .try_into()
-                        .unwrap(),
-                    self.instance_offsets.as_ptr() as *const c_void,
-                    gl::STATIC_DRAW,
-                )
-            };
-
             self.shader_storage_buffer.bind_base(0);
         }
 
@@ -298,6 +725,16 @@ impl BarRenderer {
         }
     }
 
+    // Swaps the bars for the ones in `mesh`, for bars that change.
+    pub fn upload(&mut self, mesh: &BarsMesh) {
+        self.renderer.replace_mesh(&mesh.mesh);
+        self.bar_count = mesh.bar_count;
+    }
+
+    pub fn bar_count(&self) -> u32 {
+        self.bar_count
+    }
+
     pub fn render_bars(&self, bar_count: u32) {
         assert!(self.bar_count >= bar_count);
 
@@ -315,10 +752,145 @@ impl BarRenderer {
     }
 }
 
+impl Default for LineMesh {
+    fn default() -> LineMesh {
+        LineMesh::new()
+    }
+}
+
+pub struct LineRenderer {
+    renderer: Renderer,
+}
+
+impl LineRenderer {
+    pub fn new(mesh: &LineMesh) -> LineRenderer {
+        LineRenderer {
+            renderer: Renderer::new(&mesh.mesh),
+        }
+    }
+
+    pub fn render_lines(&self) {
+        self.renderer.vertex_array.bind();
+        self.renderer.element_buffer.bind();
+
+        unsafe {
+            gl::DrawElements(
+                gl::LINES,
+                self.renderer.indices_count,
+                gl::UNSIGNED_INT,
+                std::ptr::null(),
+            );
+        }
+    }
+}
+
+// Draws a `TextMesh`, which can change every frame, with the font atlas bound
+// to texture unit 0.
+pub struct TextRenderer {
+    vertex_buffer: Buffer,
+    element_buffer: Buffer,
+    vertex_array: VertexArray,
+    font: Texture,
+    indices_count: GLsizei,
+}
+
+impl TextRenderer {
+    pub fn new(mesh: &TextMesh) -> TextRenderer {
+        let vertex_buffer = Buffer::new(BufferType::Vertex);
+        let vertex_array = VertexArray::new();
+        vertex_array.bind_buffer_and_attributes::<Vertex>(&vertex_buffer);
+
+        TextRenderer {
+            vertex_buffer,
+            element_buffer: Buffer::new(BufferType::Index),
+            vertex_array,
+            font: Texture::with_r8(mesh.atlas.width, mesh.atlas.height, &mesh.atlas.pixels),
+            indices_count: 0,
+        }
+    }
+
+    pub fn upload(&mut self, mesh: &TextMesh) {
+        self.vertex_array.bind();
+        self.vertex_buffer.upload_dynamic(&mesh.mesh.vertices);
+        self.element_buffer.upload_dynamic(&mesh.mesh.indices);
+        VertexArray::unbind();
+
+        self.indices_count = mesh.mesh.indices.len() as GLsizei;
+    }
+
+    pub fn render_text(&self) {
+        self.vertex_array.bind();
+        self.element_buffer.bind();
+        self.font.bind(0);
+
+        unsafe {
+            gl::DrawElements(
+                gl::TRIANGLES,
+                self.indices_count,
+                gl::UNSIGNED_INT,
+                std::ptr::null(),
+            );
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use crate::renderer::*;
 
+    #[test]
+    fn cell_instance_matches_std430() {
+        assert_eq!(std430_mismatch::<CellInstance>(), None);
+
+        // Consecutive instances in a slice must land on the array stride.
+        let instances = [
+            CellInstance::new(Vec3::new(1.0, 2.0, 3.0)),
+            CellInstance::new(Vec3::new(4.0, 5.0, 6.0)),
+        ];
+        let base = instances.as_ptr() as usize;
+        assert_eq!(&instances[1] as *const _ as usize - base, 48);
+    }
+
+    #[test]
+    fn cell_instance_declaration() {
+        assert_eq!(
+            CellInstance::glsl_declaration(),
+            "struct CellInstance {\n    vec3 offset;\n    float scale;\n    vec4 color;\n    uint age;\n    uint state;\n    uint neighbours;\n};\n"
+        );
+    }
+
+    // Somebody forgetting that vec4s are 16-byte aligned.
+    #[repr(C)]
+    #[derive(Clone, Copy)]
+    struct Misaligned {
+        scale: f32,
+        color: [f32; 4],
+    }
+
+    unsafe impl InstanceData for Misaligned {
+        const GLSL_NAME: &'static str = "Misaligned";
+        const FIELDS: &'static [InstanceField] = &[
+            InstanceField {
+                name: "scale",
+                glsl_type: GlslType::Float,
+                offset: offset_of!(Misaligned, scale),
+            },
+            InstanceField {
+                name: "color",
+                glsl_type: GlslType::Vec4,
+                offset: offset_of!(Misaligned, color),
+            },
+        ];
+    }
+
+    #[test]
+    fn misaligned_instance_is_caught() {
+        assert_eq!(
+            std430_mismatch::<Misaligned>().unwrap(),
+            "Misaligned.color is at byte 4, but std430 puts it at byte 16"
+        );
+    }
+
     #[test]
     fn cube_face_tests() {
         let mut mesh = Mesh::new();
@@ -350,7 +922,7 @@ mod tests {
         ];
 
         for (vertex, expected_position) in mesh.vertices.iter().zip(expected_positions.iter()) {
-            assert_eq!(vertex.normal, Vec3::new(0.0, 0.0, 1.0));
+            assert_eq!(vertex.normal, Vec3::new(0.0, 0.0, -1.0));
             assert_eq!(vertex.position, expected_position.clone());
         }
     }
@@ -368,8 +940,81 @@ mod tests {
         ];
 
         for (vertex, expected_position) in mesh.vertices.iter().zip(expected_positions.iter()) {
-            assert_eq!(vertex.normal, Vec3::new(1.0, 0.0, 0.0));
+            assert_eq!(vertex.normal, Vec3::new(-1.0, 0.0, 0.0));
             assert_eq!(vertex.position, expected_position.clone());
         }
     }
+
+    #[test]
+    fn cube_normals_point_outwards() {
+        let mesh = Mesh::cube(1.0);
+
+        for vertex in &mesh.vertices {
+            assert_eq!(vertex.normal.len(), 1.0);
+            // Every corner of a face is on the side the normal points to.
+            assert!(vertex.position.dot(&vertex.normal) > 0.0);
+        }
+    }
+
+    #[test]
+    fn text_layout() {
+        let mut text = TextMesh::new();
+        text.append_text("Hi there\n:)", Vec2::new(10.0, 20.0), 2.0, [1.0; 4]);
+
+        // Spaces don't get a quad.
+        assert_eq!(text.glyph_count(), 9);
+
+        let positions: Vec<Vec3> = text.positions().collect();
+        let bounds = |quad: &[Vec3]| {
+            let xs = quad.iter().map(|p| p.x);
+            let ys = quad.iter().map(|p| p.y);
+            (
+                xs.clone().fold(f32::MAX, f32::min),
+                ys.clone().fold(f32::MAX, f32::min),
+                xs.fold(f32::MIN, f32::max),
+                ys.fold(f32::MIN, f32::max),
+            )
+        };
+
+        assert_eq!(bounds(&positions[0..4]), (10.0, 20.0, 20.0, 34.0));
+        // "t" is the fourth character, after the space.
+        assert_eq!(bounds(&positions[8..12]).0, 10.0 + 3.0 * 12.0);
+        // ":" starts the second line.
+        assert_eq!(bounds(&positions[28..32]).0, 10.0);
+        assert_eq!(bounds(&positions[28..32]).1, 20.0 + 18.0);
+
+        text.clear();
+        assert_eq!(text.glyph_count(), 0);
+    }
+
+    #[test]
+    fn box_edges_follow_the_axes() {
+        let mut mesh = LineMesh::new();
+        mesh.append_box(
+            Vec3::new(-1.0, -2.0, -3.0),
+            Vec3::new(1.0, 2.0, 3.0),
+            [1.0; 4],
+        );
+        assert_eq!(mesh.line_count(), 12);
+
+        let positions: Vec<Vec3> = mesh.positions().collect();
+        for line in positions.chunks(2) {
+            let difference = line[1] - line[0];
+            let changed = [difference.x, difference.y, difference.z]
+                .iter()
+                .filter(|d| **d != 0.0)
+                .count();
+            assert_eq!(changed, 1);
+        }
+    }
+
+    #[test]
+    fn occlusion_levels() {
+        assert_eq!(occlusion_level(false, false, false), 0);
+        assert_eq!(occlusion_level(false, false, true), 1);
+        assert_eq!(occlusion_level(true, false, true), 2);
+        assert_eq!(occlusion_level(true, true, false), 3);
+        assert_eq!(occlusion_brightness(0), 1.0);
+        assert!(occlusion_brightness(3) > 0.0);
+    }
 }
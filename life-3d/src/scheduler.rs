@@ -0,0 +1,297 @@
+// Decides how many generations to run each frame. The rate is in generations
+// per second of real time, whatever the frame rate is, so slow rates run a
+// generation every few frames and fast ones run several in a frame, for as
+// long as the frame's time budget lasts.
+
+use std::{fmt, time::Instant};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Rate {
+    PerSecond(f64),
+    // As many generations as fit in each frame.
+    Unlimited,
+}
+
+impl fmt::Display for Rate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Rate::PerSecond(rate) => write!(f, "{} gen/s", rate),
+            Rate::Unlimited => write!(f, "full speed"),
+        }
+    }
+}
+
+// The rates that the speed keys and the speed slider go through, slowest
+// first.
+pub const SPEEDS: &[Rate] = &[
+    Rate::PerSecond(0.5),
+    Rate::PerSecond(1.0),
+    Rate::PerSecond(2.0),
+    Rate::PerSecond(4.0),
+    Rate::PerSecond(8.0),
+    Rate::PerSecond(16.0),
+    Rate::PerSecond(32.0),
+    Rate::PerSecond(64.0),
+    Rate::Unlimited,
+];
+const DEFAULT_SPEED: usize = 3;
+
+// How many generations a batch step runs.
+pub const STEP_BATCH: u32 = 10;
+
+// How long a frame may spend running generations, in seconds. At least one
+// generation is always run when one is due, however long it takes.
+pub const FRAME_BUDGET: f64 = 0.01;
+
+// When generations take longer to run than the rate allows, the ones that
+// didn't fit are only kept for this long, in seconds, so that the game
+// doesn't fall further and further behind.
+const MAX_BACKLOG: f64 = 0.25;
+
+#[derive(Debug, Clone)]
+pub struct Scheduler {
+    rate: Rate,
+    // Which of `SPEEDS` the rate is, or is closest to.
+    speed: usize,
+    paused: bool,
+    // How many generations are due, counting fractions of one.
+    due: f64,
+    // Generations asked for with `step`, which run even while paused.
+    steps: u32,
+    budget: f64,
+}
+
+impl Default for Scheduler {
+    fn default() -> Scheduler {
+        Scheduler {
+            rate: SPEEDS[DEFAULT_SPEED],
+            speed: DEFAULT_SPEED,
+            paused: true,
+            due: 0.0,
+            steps: 0,
+            budget: FRAME_BUDGET,
+        }
+    }
+}
+
+impl Scheduler {
+    pub fn rate(&self) -> Rate {
+        self.rate
+    }
+
+    pub fn set_rate(&mut self, rate: Rate) {
+        let distance = |speed: &Rate| match (rate, *speed) {
+            (Rate::Unlimited, Rate::Unlimited) => 0.0,
+            (Rate::PerSecond(a), Rate::PerSecond(b)) => (a / b).ln().abs(),
+            _ => f64::INFINITY,
+        };
+
+        self.rate = rate;
+        self.speed = (0..SPEEDS.len())
+            .min_by(|a, b| distance(&SPEEDS[*a]).total_cmp(&distance(&SPEEDS[*b])))
+            .unwrap();
+        self.due = 0.0;
+    }
+
+    // Which of `SPEEDS` it is running at.
+    pub fn speed(&self) -> usize {
+        self.speed
+    }
+
+    pub fn set_speed(&mut self, speed: usize) {
+        self.speed = speed.min(SPEEDS.len() - 1);
+        self.rate = SPEEDS[self.speed];
+        self.due = 0.0;
+    }
+
+    pub fn faster(&mut self) {
+        self.set_speed(self.speed + 1);
+    }
+
+    pub fn slower(&mut self) {
+        self.set_speed(self.speed.saturating_sub(1));
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    pub fn set_paused(&mut self, paused: bool) {
+        self.paused = paused;
+        self.due = 0.0;
+    }
+
+    pub fn toggle_pause(&mut self) {
+        self.set_paused(!self.paused);
+    }
+
+    // Runs `generations` more generations, paused or not.
+    pub fn step(&mut self, generations: u32) {
+        self.steps = self.steps.saturating_add(generations);
+    }
+
+    // How many seconds until the next generation is due, or `None` if none
+    // will be until the scheduler is changed.
+    pub fn wait_time(&self) -> Option<f64> {
+        if self.steps > 0 {
+            return Some(0.0);
+        }
+
+        match self.rate {
+            _ if self.paused => None,
+            Rate::PerSecond(rate) => Some(((1.0 - self.due) / rate).max(0.0)),
+            Rate::Unlimited => Some(0.0),
+        }
+    }
+
+    pub fn set_budget(&mut self, budget: f64) {
+        self.budget = budget;
+    }
+
+    // Calls `update` once for every generation due after `delta_time`
+    // seconds, or as many of them as fit in the budget, and returns how many
+    // that was.
+    pub fn run(&mut self, delta_time: f64, update: impl FnMut()) -> u32 {
+        let started = Instant::now();
+        self.run_with_clock(delta_time, || started.elapsed().as_secs_f64(), update)
+    }
+
+    // `run`, with `clock` giving the seconds since the frame's generations
+    // started.
+    pub fn run_with_clock(
+        &mut self,
+        delta_time: f64,
+        mut clock: impl FnMut() -> f64,
+        mut update: impl FnMut(),
+    ) -> u32 {
+        if !self.paused {
+            if let Rate::PerSecond(rate) = self.rate {
+                self.due = (self.due + delta_time * rate).min((rate * MAX_BACKLOG).max(1.0));
+            }
+        }
+
+        let budget = self.budget;
+        let mut within_budget = |ran| ran == 0 || clock() < budget;
+        let mut ran = 0;
+
+        while self.steps > 0 && within_budget(ran) {
+            update();
+            self.steps -= 1;
+            ran += 1;
+        }
+
+        while !self.paused && within_budget(ran) {
+            match self.rate {
+                Rate::PerSecond(_) if self.due >= 1.0 => self.due -= 1.0,
+                Rate::PerSecond(_) => break,
+                Rate::Unlimited => {}
+            }
+
+            update();
+            ran += 1;
+        }
+
+        ran
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Rate, Scheduler, SPEEDS};
+
+    // A clock that says `per_call` more seconds went by every time it's
+    // asked.
+    fn clock(per_call: f64) -> impl FnMut() -> f64 {
+        let mut time = 0.0;
+        move || {
+            time += per_call;
+            time
+        }
+    }
+
+    fn run(scheduler: &mut Scheduler, delta_time: f64) -> u32 {
+        scheduler.run_with_clock(delta_time, clock(0.0), || {})
+    }
+
+    #[test]
+    fn nothing_runs_while_paused() {
+        let mut scheduler = Scheduler::default();
+        assert!(scheduler.is_paused());
+        assert_eq!(run(&mut scheduler, 10.0), 0);
+        assert_eq!(scheduler.wait_time(), None);
+
+        scheduler.step(1);
+        assert_eq!(scheduler.wait_time(), Some(0.0));
+        assert_eq!(run(&mut scheduler, 0.0), 1);
+        assert_eq!(run(&mut scheduler, 0.0), 0);
+    }
+
+    #[test]
+    fn fractional_rates_carry_over() {
+        let mut scheduler = Scheduler::default();
+        scheduler.set_rate(Rate::PerSecond(0.5));
+        scheduler.set_paused(false);
+
+        assert_eq!(scheduler.wait_time(), Some(2.0));
+        let mut total = 0;
+        for _ in 0..16 {
+            total += run(&mut scheduler, 0.25);
+        }
+        // Four seconds at half a generation a second.
+        assert_eq!(total, 2);
+    }
+
+    #[test]
+    fn fast_rates_run_several_per_frame() {
+        let mut scheduler = Scheduler::default();
+        scheduler.set_rate(Rate::PerSecond(128.0));
+        scheduler.set_paused(false);
+
+        assert_eq!(run(&mut scheduler, 1.0 / 16.0), 8);
+
+        // A long frame doesn't build up more than the backlog allows.
+        assert_eq!(run(&mut scheduler, 10.0), 32);
+    }
+
+    #[test]
+    fn budget_limits_generations() {
+        let mut scheduler = Scheduler::default();
+        scheduler.set_rate(Rate::Unlimited);
+        scheduler.set_paused(false);
+        scheduler.set_budget(0.01);
+
+        // Each generation takes 3ms, so the budget runs out after the fourth.
+        assert_eq!(scheduler.run_with_clock(0.0, clock(0.003), || {}), 4);
+
+        // Even a slow generation runs once.
+        assert_eq!(scheduler.run_with_clock(0.0, clock(1.0), || {}), 1);
+
+        // Steps that don't fit wait for the next frame.
+        scheduler.set_paused(true);
+        scheduler.step(10);
+        assert_eq!(scheduler.run_with_clock(0.0, clock(0.003), || {}), 4);
+        assert_eq!(scheduler.run_with_clock(0.0, clock(0.003), || {}), 4);
+        assert_eq!(scheduler.run_with_clock(0.0, clock(0.003), || {}), 2);
+    }
+
+    #[test]
+    fn speeds() {
+        let mut scheduler = Scheduler::default();
+        scheduler.set_rate(Rate::PerSecond(3.0));
+        assert_eq!(SPEEDS[scheduler.speed()], Rate::PerSecond(4.0));
+        assert_eq!(scheduler.rate(), Rate::PerSecond(3.0));
+
+        scheduler.faster();
+        assert_eq!(scheduler.rate(), Rate::PerSecond(8.0));
+
+        for _ in 0..20 {
+            scheduler.faster();
+        }
+        assert_eq!(scheduler.rate(), Rate::Unlimited);
+
+        for _ in 0..20 {
+            scheduler.slower();
+        }
+        assert_eq!(scheduler.rate(), SPEEDS[0]);
+    }
+}
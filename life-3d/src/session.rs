@@ -0,0 +1,247 @@
+// Everything needed to carry on from where the game was left: the arena, how
+// it was being run, and where the camera and the cursor were. It is saved
+// when the game is closed and restored when it starts again, and saved to a
+// recovery file every so often in case the game never gets to close.
+
+use std::{fmt, fs, io, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    bookmarks::Bookmark,
+    camera::ActiveCamera,
+    config::MAX_ARENA_SIZE,
+    game::{Boundary, Cell, GameOfLife, Rule},
+    mesher::CHUNK_SIZE,
+    scheduler::{Scheduler, SPEEDS},
+};
+
+#[derive(Debug)]
+pub enum SessionError {
+    Io(io::Error),
+    Parse(toml::de::Error),
+    Serialize(toml::ser::Error),
+    // The file was read fine, but doesn't make sense, like a cell outside of
+    // the arena.
+    Invalid(String),
+}
+
+impl fmt::Display for SessionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SessionError::Io(error) => write!(f, "{}", error),
+            SessionError::Parse(error) => write!(f, "invalid session file: {}", error),
+            SessionError::Serialize(error) => write!(f, "failed to save the session: {}", error),
+            SessionError::Invalid(reason) => write!(f, "invalid session file: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for SessionError {}
+
+impl From<io::Error> for SessionError {
+    fn from(error: io::Error) -> Self {
+        SessionError::Io(error)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Session {
+    pub arena_size: usize,
+    pub rule: Rule,
+    pub boundary: Boundary,
+    pub generation: u64,
+    // The x, y and z of every live cell, and then how many generations it
+    // has been alive for.
+    pub cells: Vec<[usize; 4]>,
+    // Which of `SPEEDS` the game was running at.
+    pub speed: usize,
+    pub paused: bool,
+    pub cursor: [u32; 3],
+    pub camera: Bookmark,
+}
+
+impl Session {
+    pub fn capture(
+        game: &GameOfLife,
+        scheduler: &Scheduler,
+        cursor: (u32, u32, u32),
+        camera: &ActiveCamera,
+        fov: f32,
+    ) -> Session {
+        Session {
+            arena_size: game.size(),
+            rule: game.rule(),
+            boundary: game.boundary(),
+            generation: game.generation(),
+            cells: game
+                .live_cells()
+                .into_iter()
+                .map(|[x, y, z]| [x, y, z, game.age(x, y, z) as usize])
+                .collect(),
+            speed: scheduler.speed(),
+            paused: scheduler.is_paused(),
+            cursor: [cursor.0, cursor.1, cursor.2],
+            camera: Bookmark::capture("session", camera, fov),
+        }
+    }
+
+    // Loads the session from `path`, or `None` if there is no such file.
+    pub fn load(path: &Path) -> Result<Option<Session>, SessionError> {
+        match fs::read_to_string(path) {
+            Ok(contents) => Session::from_toml(&contents).map(Some),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(error) => Err(error.into()),
+        }
+    }
+
+    // Writes the session next to `path` first and then moves it over, so
+    // that stopping halfway through doesn't leave a broken file behind.
+    pub fn save(&self, path: &Path) -> Result<(), SessionError> {
+        let partial = path.with_extension("partial");
+        fs::write(&partial, self.to_toml()?)?;
+        fs::rename(&partial, path)?;
+        Ok(())
+    }
+
+    pub fn from_toml(contents: &str) -> Result<Session, SessionError> {
+        toml::from_str(contents).map_err(SessionError::Parse)
+    }
+
+    pub fn to_toml(&self) -> Result<String, SessionError> {
+        toml::to_string(self).map_err(SessionError::Serialize)
+    }
+
+    // The arena as it was saved.
+    pub fn game(&self) -> Result<GameOfLife, SessionError> {
+        let size = self.arena_size;
+        // The same sizes the config allows, so that a broken file can't ask
+        // for more memory than there is.
+        if !size.is_multiple_of(CHUNK_SIZE) || !(CHUNK_SIZE..=MAX_ARENA_SIZE).contains(&size) {
+            return Err(SessionError::Invalid(format!(
+                "the arena can't be {} cells across",
+                size
+            )));
+        }
+
+        let mut game = GameOfLife::with_size(size);
+        game.set_rule(self.rule);
+        game.set_boundary(self.boundary);
+
+        for &[x, y, z, age] in &self.cells {
+            if x >= size || y >= size || z >= size {
+                return Err(SessionError::Invalid(format!(
+                    "the cell at ({}, {}, {}) is outside of the arena",
+                    x, y, z
+                )));
+            }
+
+            game.set_cell(x, y, z, Cell::Alive);
+            game.set_age(x, y, z, age.min(u16::MAX as usize) as u16);
+        }
+
+        game.set_generation(self.generation);
+        Ok(game)
+    }
+
+    pub fn scheduler(&self) -> Scheduler {
+        let mut scheduler = Scheduler::default();
+        scheduler.set_speed(self.speed.min(SPEEDS.len() - 1));
+        scheduler.set_paused(self.paused);
+        scheduler
+    }
+
+    // Where the cursor was, moved back inside the arena if it had wandered
+    // off.
+    pub fn cursor(&self) -> (u32, u32, u32) {
+        let last = self.arena_size.saturating_sub(1) as u32;
+        let [x, y, z] = self.cursor.map(|c| c.min(last));
+        (x, y, z)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Session, SessionError};
+    use crate::{
+        camera::{ActiveCamera, ThirdPersonCamera},
+        game::{Boundary, Cell, GameOfLife, RULES},
+        math::Vec3,
+        scheduler::Scheduler,
+    };
+
+    fn session() -> Session {
+        let mut game = GameOfLife::with_size(32);
+        game.set_rule(RULES[1]);
+        game.set_boundary(Boundary::Dead);
+        for (x, z) in [(4, 4), (4, 5), (5, 4), (5, 5)] {
+            game.set_cell(x, 10, z, Cell::Alive);
+        }
+        game.update_game();
+        game.set_cell(20, 1, 2, Cell::Alive);
+
+        let mut scheduler = Scheduler::default();
+        scheduler.set_speed(6);
+        scheduler.set_paused(false);
+
+        let camera = ActiveCamera::ThirdPerson(ThirdPersonCamera::new(
+            Vec3::new(1.0, 2.0, 3.0),
+            4.0,
+            30.0,
+            -20.0,
+        ));
+
+        Session::capture(&game, &scheduler, (3, 4, 5), &camera, 50.0)
+    }
+
+    #[test]
+    fn survives_a_round_trip_through_toml() {
+        let saved = session();
+        let loaded = Session::from_toml(&saved.to_toml().unwrap()).unwrap();
+        assert_eq!(loaded, saved);
+
+        let game = loaded.game().unwrap();
+        assert_eq!(game.size(), 32);
+        assert_eq!(game.rule(), RULES[1]);
+        assert_eq!(game.boundary(), Boundary::Dead);
+        assert_eq!(game.generation(), 1);
+        assert_eq!(game.population(), saved.cells.len());
+        for &[x, y, z, age] in &saved.cells {
+            assert!(game.cell(x, y, z).is_alive());
+            assert_eq!(game.age(x, y, z) as usize, age);
+        }
+        assert!(saved.cells.contains(&[20, 1, 2, 0]));
+
+        let scheduler = loaded.scheduler();
+        assert_eq!(scheduler.speed(), 6);
+        assert!(!scheduler.is_paused());
+
+        assert_eq!(loaded.cursor(), (3, 4, 5));
+        assert_eq!(loaded.camera.fov, 50.0);
+        assert_eq!(loaded.camera.target, Vec3::new(1.0, 2.0, 3.0));
+    }
+
+    #[test]
+    fn broken_sessions() {
+        let mut outside = session();
+        outside.cells.push([32, 0, 0, 0]);
+        assert!(matches!(outside.game(), Err(SessionError::Invalid(_))));
+
+        let mut odd_size = session();
+        odd_size.arena_size = 20;
+        assert!(matches!(odd_size.game(), Err(SessionError::Invalid(_))));
+
+        let mut huge = session();
+        huge.arena_size = 1 << 20;
+        assert!(matches!(huge.game(), Err(SessionError::Invalid(_))));
+
+        let mut lost_cursor = session();
+        lost_cursor.cursor = [3, 400, 5];
+        assert_eq!(lost_cursor.cursor(), (3, 31, 5));
+
+        assert!(matches!(
+            Session::from_toml("arena_size = 32"),
+            Err(SessionError::Parse(_))
+        ));
+    }
+}
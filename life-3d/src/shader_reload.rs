@@ -0,0 +1,191 @@
+// Reads a shader program's files from `src/shaders/` while the game runs,
+// and builds it again whenever one of them changes, so that the shaders can
+// be worked on without building the game again.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::{Duration, Instant, SystemTime},
+};
+
+use crate::shaders::{ShaderProgram, SHADER_DIR};
+
+// How often the files are looked at, as doing it every frame is a waste.
+const CHECK_INTERVAL: Duration = Duration::from_millis(250);
+
+// A file and when it was last seen to change.
+#[derive(Debug)]
+struct WatchedFile {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+}
+
+impl WatchedFile {
+    fn new(path: PathBuf) -> WatchedFile {
+        WatchedFile {
+            path,
+            modified: None,
+        }
+    }
+
+    // Whether the file has changed since the last time this was asked. A
+    // file that can't be found hasn't changed, as it's probably being saved.
+    fn changed(&mut self) -> bool {
+        let Ok(modified) = fs::metadata(&self.path).and_then(|metadata| metadata.modified()) else {
+            return false;
+        };
+
+        let changed = self.modified != Some(modified);
+        self.modified = Some(modified);
+        changed
+    }
+
+    fn read(&self) -> Result<String, String> {
+        fs::read_to_string(&self.path)
+            .map_err(|error| format!("Failed to read {}: {}", self.path.display(), error))
+    }
+
+    fn name(&self) -> String {
+        self.path.display().to_string()
+    }
+}
+
+pub struct ShaderWatcher {
+    vertex: WatchedFile,
+    fragment: WatchedFile,
+    // Done to the vertex shader before it's compiled, like
+    // `shaders::with_instance_data`.
+    prepare_vertex: Option<fn(&str) -> String>,
+    last_check: Option<Instant>,
+    // What was wrong with the files the last time they were built, if
+    // anything.
+    error: Option<String>,
+}
+
+impl ShaderWatcher {
+    // Watches the files with these names in `src/shaders/`.
+    pub fn new(vertex_file: &str, fragment_file: &str) -> ShaderWatcher {
+        let dir = Path::new(SHADER_DIR);
+
+        ShaderWatcher {
+            vertex: WatchedFile::new(dir.join(vertex_file)),
+            fragment: WatchedFile::new(dir.join(fragment_file)),
+            prepare_vertex: None,
+            last_check: None,
+            error: None,
+        }
+    }
+
+    pub fn preparing_vertex(mut self, prepare: fn(&str) -> String) -> ShaderWatcher {
+        self.prepare_vertex = Some(prepare);
+        self
+    }
+
+    // Whether either of the files has changed since the last check. The
+    // first check always says they have, so the files take over from the
+    // embedded shaders straight away.
+    fn changed(&mut self) -> bool {
+        let now = Instant::now();
+        if self
+            .last_check
+            .is_some_and(|last_check| now - last_check < CHECK_INTERVAL)
+        {
+            return false;
+        }
+        self.last_check = Some(now);
+
+        // Both are checked, so that a change to one doesn't hide the other
+        // until the next time.
+        let vertex = self.vertex.changed();
+        let fragment = self.fragment.changed();
+        vertex || fragment
+    }
+
+    // The program as the files have it now.
+    pub fn build(&self) -> Result<ShaderProgram, String> {
+        let mut vertex_source = self.vertex.read()?;
+        if let Some(prepare) = self.prepare_vertex {
+            vertex_source = prepare(&vertex_source);
+        }
+
+        ShaderProgram::new(
+            &vertex_source,
+            Some(&self.vertex.name()),
+            &self.fragment.read()?,
+            Some(&self.fragment.name()),
+        )
+        .map_err(|error| error.to_string())
+    }
+
+    // A new program if the files have changed and it builds. If it doesn't,
+    // the old one should be kept, and the reason is printed and kept in
+    // `error` until the files are fixed.
+    pub fn poll(&mut self) -> Option<ShaderProgram> {
+        if !self.changed() {
+            return None;
+        }
+
+        match self.build() {
+            Ok(program) => {
+                if self.error.take().is_some() {
+                    eprintln!(
+                        "Rebuilt {} and {}",
+                        self.vertex.name(),
+                        self.fragment.name()
+                    );
+                }
+                Some(program)
+            }
+            Err(error) => {
+                eprintln!("{}", error);
+                self.error = Some(error);
+                None
+            }
+        }
+    }
+
+    // Swaps `program` for a new one if the files have changed and it
+    // builds. Returns whether the files are broken.
+    pub fn reload(&mut self, program: &mut ShaderProgram) -> bool {
+        if let Some(new_program) = self.poll() {
+            *program = new_program;
+        }
+
+        self.error.is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        fs::{self, File},
+        time::{Duration, SystemTime},
+    };
+
+    use super::WatchedFile;
+
+    #[test]
+    fn changes_are_noticed_once() {
+        let dir = std::env::temp_dir().join(format!("life-3d-reload-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("test.frag");
+
+        let mut file = WatchedFile::new(path.clone());
+        assert!(!file.changed());
+
+        fs::write(&path, "void main() {}").unwrap();
+        assert!(file.changed());
+        assert!(!file.changed());
+
+        File::options()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_modified(SystemTime::now() + Duration::from_secs(10))
+            .unwrap();
+        assert!(file.changed());
+        assert!(!file.changed());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
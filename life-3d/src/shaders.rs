@@ -1,55 +1,277 @@
-use std::ffi::{CStr, CString};
+use std::{
+    cell::RefCell,
+    collections::{HashMap, HashSet},
+    ffi::CString,
+    fmt,
+};
 
 use glad_gl::gl;
 
-pub struct ShaderProgram(gl::GLuint);
-pub struct UsedShaderProgram(gl::GLuint);
+use crate::buffers::InstanceData;
+
+pub struct ShaderProgram {
+    id: gl::GLuint,
+    // Which shaders it was linked from, for warnings.
+    name: String,
+    // The active uniforms, looked up once after linking.
+    uniforms: HashMap<String, Uniform>,
+    // The uniform names that have already been warned about, so that a
+    // mistake doesn't print a line every frame.
+    warned: RefCell<HashSet<String>>,
+}
+
+pub struct UsedShaderProgram<'a>(&'a ShaderProgram);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Uniform {
+    pub location: gl::GLint,
+    // Like `gl::FLOAT_VEC3`.
+    pub glsl_type: gl::GLenum,
+    // How many elements it has, or 1 if it isn't an array.
+    pub size: gl::GLint,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum UniformProblem {
+    Unknown,
+    WrongType { glsl_type: gl::GLenum },
+}
 
 pub const MAIN_VERT: &str = include_str!("shaders/main.vert");
 pub const MAIN_FRAG: &str = include_str!("shaders/main.frag");
 pub const CURSOR_VERT: &str = include_str!("shaders/cursor.vert");
 pub const CURSOR_FRAG: &str = include_str!("shaders/cursor.frag");
 pub const FLAT_FRAG: &str = include_str!("shaders/flat.frag");
+pub const EDITOR_VERT: &str = include_str!("shaders/editor.vert");
+pub const EDITOR_FRAG: &str = include_str!("shaders/editor.frag");
+pub const LINE_VERT: &str = include_str!("shaders/line.vert");
+pub const LINE_FRAG: &str = include_str!("shaders/line.frag");
+pub const TEXT_VERT: &str = include_str!("shaders/text.vert");
+pub const TEXT_FRAG: &str = include_str!("shaders/text.frag");
+
+// Where the shaders above are embedded from, for reading them again while the
+// game runs.
+pub const SHADER_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/src/shaders");
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShaderStage {
+    Vertex,
+    Fragment,
+}
+
+impl ShaderStage {
+    fn gl_type(self) -> gl::GLenum {
+        match self {
+            ShaderStage::Vertex => gl::VERTEX_SHADER,
+            ShaderStage::Fragment => gl::FRAGMENT_SHADER,
+        }
+    }
+}
+
+impl fmt::Display for ShaderStage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ShaderStage::Vertex => write!(f, "vertex"),
+            ShaderStage::Fragment => write!(f, "fragment"),
+        }
+    }
+}
+
+// One line of a shader's info log.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    // Counting from 1, if the driver said which line it was about.
+    pub line: Option<usize>,
+    pub message: String,
+    // The line of the source it was about.
+    pub source_line: Option<String>,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "line {}: {}", line, self.message)?,
+            None => write!(f, "{}", self.message)?,
+        }
+
+        if let (Some(line), Some(source_line)) = (self.line, &self.source_line) {
+            if !source_line.trim().is_empty() {
+                write!(f, "\n    {:>4} | {}", line, source_line)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub enum ShaderError {
+    Compile {
+        stage: ShaderStage,
+        name: String,
+        diagnostics: Vec<Diagnostic>,
+    },
+    // Linking doesn't say which lines are to blame, so the log is kept as it
+    // is.
+    Link {
+        vertex_name: String,
+        fragment_name: String,
+        log: String,
+    },
+}
+
+impl fmt::Display for ShaderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ShaderError::Compile {
+                stage,
+                name,
+                diagnostics,
+            } => {
+                write!(f, "Failed to compile the {} shader {}:", stage, name)?;
+                for diagnostic in diagnostics {
+                    write!(f, "\n  {}", diagnostic)?;
+                }
+                Ok(())
+            }
+            ShaderError::Link {
+                vertex_name,
+                fragment_name,
+                log,
+            } => write!(
+                f,
+                "Failed to link {} and {} into a program:\n{}",
+                vertex_name,
+                fragment_name,
+                log.trim_end()
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ShaderError {}
+
+// The line number at the start of an info log line, and the rest of it. The
+// drivers don't agree on how to write them, so this takes "0(12) : error",
+// "0:12(5): error" and "ERROR: 0:12: error", where the 0 is which of the
+// sources it's about, which is always the first here.
+fn split_line_number(log_line: &str) -> Option<(usize, String)> {
+    let (severity, rest) = [("ERROR: ", "error: "), ("WARNING: ", "warning: ")]
+        .iter()
+        .find_map(|(prefix, severity)| Some((*severity, log_line.strip_prefix(prefix)?)))
+        .unwrap_or(("", log_line));
 
-// Creates an OpenGL shader of the specified type. `shader_type` must be of a
-// valid shader type
+    let rest = rest.trim_start_matches(|c: char| c.is_ascii_digit());
+    let (line, rest) = if let Some(rest) = rest.strip_prefix('(') {
+        rest.split_once(')')?
+    } else {
+        let rest = rest.strip_prefix(':')?;
+        let end = rest.find(|c: char| !c.is_ascii_digit())?;
+        rest.split_at(end)
+    };
+
+    // Skip the column, if there is one.
+    let rest = match rest.strip_prefix('(') {
+        Some(rest) => rest.split_once(')')?.1,
+        None => rest,
+    };
+    let message = rest.trim_start_matches([' ', ':']).trim_end();
+
+    Some((line.parse().ok()?, format!("{}{}", severity, message)))
+}
+
+// Splits an info log into its lines, matching them up with the lines of the
+// source they are about.
+pub fn parse_info_log(log: &str, source: &str) -> Vec<Diagnostic> {
+    log.lines()
+        .map(str::trim)
+        .filter(|log_line| !log_line.is_empty())
+        .map(|log_line| match split_line_number(log_line) {
+            Some((line, message)) => Diagnostic {
+                line: Some(line),
+                message,
+                source_line: line
+                    .checked_sub(1)
+                    .and_then(|i| source.lines().nth(i))
+                    .map(|source_line| source_line.trim_end().to_string()),
+            },
+            None => Diagnostic {
+                line: None,
+                message: log_line.to_string(),
+                source_line: None,
+            },
+        })
+        .collect()
+}
+
+// Reads a string `length` bytes long, counting the nul at the end, like an
+// info log or a uniform's name, with `read`. It's given the room there is,
+// where to say how much it wrote, and where to write it.
+unsafe fn read_gl_string(
+    length: gl::GLint,
+    read: impl FnOnce(gl::GLsizei, *mut gl::GLsizei, *mut gl::GLchar),
+) -> String {
+    let mut log = vec![0u8; length.max(1) as usize];
+    let mut written = 0;
+    read(
+        log.len() as gl::GLsizei,
+        &mut written,
+        log.as_mut_ptr() as *mut gl::GLchar,
+    );
+    log.truncate(written.clamp(0, length) as usize);
+
+    String::from_utf8_lossy(&log).into_owned()
+}
+
+// Creates an OpenGL shader for the stage, or says what the driver didn't like
+// about it.
 unsafe fn create_shader(
     shader_source: &str,
-    shader_type: gl::GLenum,
-    shader_name: Option<&str>,
-) -> gl::GLuint {
-    let shader = gl::CreateShader(shader_type);
+    stage: ShaderStage,
+    shader_name: &str,
+) -> Result<gl::GLuint, ShaderError> {
+    let shader = gl::CreateShader(stage.gl_type());
 
-    let c_shader_source = CString::new(shader_source).expect("Shader source must be UTF-8");
-    let c_shader_source = c_shader_source.as_ptr();
+    // With the length given, the source doesn't need a nul at the end.
+    let source_ptr = shader_source.as_ptr() as *const gl::GLchar;
     let shader_source_len: gl::GLint = shader_source.len().try_into().unwrap();
-    gl::ShaderSource(shader, 1, &c_shader_source, &shader_source_len);
+    gl::ShaderSource(shader, 1, &source_ptr, &shader_source_len);
     gl::CompileShader(shader);
 
     let mut status = 0;
     gl::GetShaderiv(shader, gl::COMPILE_STATUS, &mut status);
-    let mut message_len = 0;
-    gl::GetShaderiv(shader, gl::INFO_LOG_LENGTH, &mut message_len);
 
     if status == 0 {
-        let mut message = Vec::with_capacity(message_len as usize);
-        gl::GetShaderInfoLog(
-            shader,
-            message_len,
-            std::ptr::null_mut(),
-            message.as_mut_ptr(),
-        );
-        message.set_len(message_len as usize);
+        let mut log_len = 0;
+        gl::GetShaderiv(shader, gl::INFO_LOG_LENGTH, &mut log_len);
+        let log = read_gl_string(log_len, |room, written, log| {
+            gl::GetShaderInfoLog(shader, room, written, log)
+        });
+        gl::DeleteShader(shader);
 
-        let message = CStr::from_ptr(message.as_ptr());
-        eprintln!(
-            "Failed to compile the shader {}:\n{}\n",
-            shader_name.unwrap_or("<no-name>"),
-            message.to_str().unwrap()
-        );
+        return Err(ShaderError::Compile {
+            stage,
+            name: shader_name.to_string(),
+            diagnostics: parse_info_log(&log, shader_source),
+        });
     }
 
-    shader
+    Ok(shader)
+}
+
+// Swaps the `#pragma instance_data` line in a shader for the GLSL declaration
+// of `I`, so the shader always agrees with the Rust side.
+pub fn with_instance_data<I: InstanceData>(shader_source: &str) -> String {
+    shader_source
+        .lines()
+        .map(|line| {
+            if line.trim() == "#pragma instance_data" {
+                I::glsl_declaration()
+            } else {
+                format!("{}\n", line)
+            }
+        })
+        .collect()
 }
 
 #[macro_export]
@@ -64,11 +286,99 @@ macro_rules! shader_program_from_resources {
     };
 }
 
+// How GLSL types are written in the shaders, for warnings.
+fn glsl_type_name(glsl_type: gl::GLenum) -> String {
+    match glsl_type {
+        gl::FLOAT => "float",
+        gl::FLOAT_VEC2 => "vec2",
+        gl::FLOAT_VEC3 => "vec3",
+        gl::FLOAT_VEC4 => "vec4",
+        gl::INT => "int",
+        gl::UNSIGNED_INT => "uint",
+        gl::BOOL => "bool",
+        gl::FLOAT_MAT3 => "mat3",
+        gl::FLOAT_MAT4 => "mat4",
+        gl::SAMPLER_2D => "sampler2D",
+        _ => return format!("type {:#x}", glsl_type),
+    }
+    .to_string()
+}
+
+// Adds a uniform as `GetActiveUniform` names it. Arrays are named after
+// their first element, so they can be found by the array's name too.
+fn insert_uniform(uniforms: &mut HashMap<String, Uniform>, name: String, uniform: Uniform) {
+    if let Some(array_name) = name.strip_suffix("[0]") {
+        uniforms.insert(array_name.to_string(), uniform);
+    }
+
+    uniforms.insert(name, uniform);
+}
+
+// Where to set the uniform called `name`, if there is one of `glsl_type`.
+fn find_uniform(
+    uniforms: &HashMap<String, Uniform>,
+    name: &str,
+    glsl_type: gl::GLenum,
+) -> Result<gl::GLint, UniformProblem> {
+    match uniforms.get(name) {
+        Some(uniform) if uniform.glsl_type == glsl_type => Ok(uniform.location),
+        Some(uniform) => Err(UniformProblem::WrongType {
+            glsl_type: uniform.glsl_type,
+        }),
+        None => Err(UniformProblem::Unknown),
+    }
+}
+
+// Every active uniform in a linked program. Uniforms in blocks don't have a
+// location of their own, so they are left out.
+unsafe fn active_uniforms(program: gl::GLuint) -> HashMap<String, Uniform> {
+    let mut count = 0;
+    gl::GetProgramiv(program, gl::ACTIVE_UNIFORMS, &mut count);
+    let mut max_name_len = 0;
+    gl::GetProgramiv(program, gl::ACTIVE_UNIFORM_MAX_LENGTH, &mut max_name_len);
+
+    let mut uniforms = HashMap::new();
+    for index in 0..count.max(0) as gl::GLuint {
+        let (mut size, mut glsl_type) = (0, 0);
+        let name = read_gl_string(max_name_len, |room, written, name| {
+            gl::GetActiveUniform(
+                program,
+                index,
+                room,
+                written,
+                &mut size,
+                &mut glsl_type,
+                name,
+            )
+        });
+
+        let Ok(c_name) = CString::new(name.as_str()) else {
+            continue;
+        };
+        let location = gl::GetUniformLocation(program, c_name.as_ptr());
+        if location >= 0 {
+            let uniform = Uniform {
+                location,
+                glsl_type,
+                size,
+            };
+            insert_uniform(&mut uniforms, name, uniform);
+        }
+    }
+
+    uniforms
+}
+
 pub unsafe trait ShaderUniform {
+    // The GLSL type of the uniforms it can be set on, like `gl::FLOAT_VEC3`.
+    const GLSL_TYPE: gl::GLenum;
+
     unsafe fn set_uniform(&self, location: gl::GLint);
 }
 
 unsafe impl ShaderUniform for f32 {
+    const GLSL_TYPE: gl::GLenum = gl::FLOAT;
+
     unsafe fn set_uniform(&self, location: gl::GLint) {
         gl::Uniform1f(location, *self);
     }
@@ -80,10 +390,20 @@ impl ShaderProgram {
         vertex_name: Option<&str>,
         fragment_source: &str,
         fragment_name: Option<&str>,
-    ) -> ShaderProgram {
+    ) -> Result<ShaderProgram, ShaderError> {
+        let vertex_name = vertex_name.unwrap_or("<unnamed vertex>");
+        let fragment_name = fragment_name.unwrap_or("<unnamed fragment>");
+
         unsafe {
-            let vertex = create_shader(vertex_source, gl::VERTEX_SHADER, vertex_name);
-            let fragment = create_shader(fragment_source, gl::FRAGMENT_SHADER, fragment_name);
+            let vertex = create_shader(vertex_source, ShaderStage::Vertex, vertex_name)?;
+            let fragment =
+                match create_shader(fragment_source, ShaderStage::Fragment, fragment_name) {
+                    Ok(fragment) => fragment,
+                    Err(error) => {
+                        gl::DeleteShader(vertex);
+                        return Err(error);
+                    }
+                };
 
             let program = gl::CreateProgram();
             gl::AttachShader(program, vertex);
@@ -93,50 +413,76 @@ impl ShaderProgram {
             let mut status = 0;
             gl::GetProgramiv(program, gl::LINK_STATUS, &mut status);
 
-            if status == 0 {
-                let mut message_len = 0;
-                gl::GetProgramiv(program, gl::INFO_LOG_LENGTH, &mut message_len);
-                let mut message = Vec::with_capacity(message_len as usize);
-                gl::GetProgramInfoLog(
-                    program,
-                    message_len,
-                    std::ptr::null_mut(),
-                    message.as_mut_ptr(),
-                );
-
-                let message = CStr::from_ptr(message.as_ptr());
-                eprintln!(
-                    "Failed to link {} and {} into a program:\n{}\n",
-                    vertex_name.unwrap_or("<unamed vertex>"),
-                    fragment_name.unwrap_or("<unamed fragment>"),
-                    message.to_str().unwrap()
-                );
-            }
-
             gl::DeleteShader(vertex);
             gl::DeleteShader(fragment);
 
-            ShaderProgram(program)
+            if status == 0 {
+                let mut log_len = 0;
+                gl::GetProgramiv(program, gl::INFO_LOG_LENGTH, &mut log_len);
+                let log = read_gl_string(log_len, |room, written, log| {
+                    gl::GetProgramInfoLog(program, room, written, log)
+                });
+                gl::DeleteProgram(program);
+
+                return Err(ShaderError::Link {
+                    vertex_name: vertex_name.to_string(),
+                    fragment_name: fragment_name.to_string(),
+                    log,
+                });
+            }
+
+            Ok(ShaderProgram {
+                id: program,
+                name: format!("{} and {}", vertex_name, fragment_name),
+                uniforms: active_uniforms(program),
+                warned: RefCell::new(HashSet::new()),
+            })
         }
     }
 
-    pub fn use_program(&self) -> UsedShaderProgram {
+    pub fn uniform(&self, name: &str) -> Option<Uniform> {
+        self.uniforms.get(name).copied()
+    }
+
+    pub fn use_program(&self) -> UsedShaderProgram<'_> {
         unsafe {
-            gl::UseProgram(self.0);
-            UsedShaderProgram(self.0)
+            gl::UseProgram(self.id);
+            UsedShaderProgram(self)
+        }
+    }
+
+    // Only in debug builds, and only the first time for each name.
+    fn warn_once(&self, name: &str, expected_type: gl::GLenum, problem: UniformProblem) {
+        if !cfg!(debug_assertions) || !self.warned.borrow_mut().insert(name.to_string()) {
+            return;
+        }
+
+        match problem {
+            UniformProblem::Unknown => eprintln!(
+                "{} have no uniform called {:?}, or don't use it",
+                self.name, name
+            ),
+            UniformProblem::WrongType { glsl_type } => eprintln!(
+                "The uniform {:?} in {} is a {}, but it was set to a {}",
+                name,
+                self.name,
+                glsl_type_name(glsl_type),
+                glsl_type_name(expected_type)
+            ),
         }
     }
 }
 
-impl UsedShaderProgram {
+impl UsedShaderProgram<'_> {
+    // Uniforms that aren't in the program, or are of a different type, are
+    // left alone.
     pub fn set_uniform<T>(&self, name: &str, value: T)
     where
         T: ShaderUniform,
     {
-        unsafe {
-            let name = CString::new(name).unwrap();
-            let location = gl::GetUniformLocation(self.0, name.as_ptr());
-            value.set_uniform(location);
+        match find_uniform(&self.0.uniforms, name, T::GLSL_TYPE) {
+            Ok(location) => unsafe { value.set_uniform(location) },
+            Err(problem) => self.0.warn_once(name, T::GLSL_TYPE, problem),
         }
     }
 }
@@ -144,15 +490,123 @@ impl UsedShaderProgram {
 impl Drop for ShaderProgram {
     fn drop(&mut self) {
         unsafe {
-            gl::DeleteProgram(self.0);
+            gl::DeleteProgram(self.id);
         }
     }
 }
 
-impl Drop for UsedShaderProgram {
+impl Drop for UsedShaderProgram<'_> {
     fn drop(&mut self) {
         unsafe {
             gl::UseProgram(0);
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use glad_gl::gl;
+
+    use super::{
+        find_uniform, glsl_type_name, insert_uniform, parse_info_log, Diagnostic, Uniform,
+        UniformProblem,
+    };
+
+    const SOURCE: &str = "#version 460 core\n\nvoid main() {\n    gl_Position = vec4(x);\n}\n";
+
+    #[test]
+    fn every_driver_log_format() {
+        for log in [
+            "0(4) : error C1008: undefined variable \"x\"",
+            "0:4(26): error: `x' undeclared",
+            "ERROR: 0:4: 'x' : undeclared identifier",
+        ] {
+            let diagnostics = parse_info_log(log, SOURCE);
+
+            assert_eq!(diagnostics.len(), 1, "{:?}", log);
+            assert_eq!(diagnostics[0].line, Some(4), "{:?}", log);
+            assert!(diagnostics[0].message.starts_with("error"), "{:?}", log);
+            assert_eq!(
+                diagnostics[0].source_line.as_deref(),
+                Some("    gl_Position = vec4(x);")
+            );
+        }
+    }
+
+    #[test]
+    fn lines_without_a_number() {
+        let diagnostics = parse_info_log(
+            "\n0:99(1): error: past the end\nerror: 1 compilation errors\n",
+            SOURCE,
+        );
+
+        assert_eq!(
+            diagnostics,
+            vec![
+                Diagnostic {
+                    line: Some(99),
+                    message: "error: past the end".to_string(),
+                    source_line: None,
+                },
+                Diagnostic {
+                    line: None,
+                    message: "error: 1 compilation errors".to_string(),
+                    source_line: None,
+                },
+            ]
+        );
+        assert_eq!(
+            parse_info_log("0(2) : warning: empty", SOURCE)[0].to_string(),
+            "line 2: warning: empty"
+        );
+        assert_eq!(
+            parse_info_log("0(4) : error: x", SOURCE)[0].to_string(),
+            "line 4: error: x\n       4 |     gl_Position = vec4(x);"
+        );
+    }
+
+    #[test]
+    fn uniforms_are_checked() {
+        let mut uniforms = HashMap::new();
+        let uniform = |location, glsl_type, size| Uniform {
+            location,
+            glsl_type,
+            size,
+        };
+        insert_uniform(
+            &mut uniforms,
+            "model".to_string(),
+            uniform(0, gl::FLOAT_MAT4, 1),
+        );
+        insert_uniform(
+            &mut uniforms,
+            "lights[0]".to_string(),
+            uniform(4, gl::FLOAT_VEC3, 3),
+        );
+
+        assert_eq!(find_uniform(&uniforms, "model", gl::FLOAT_MAT4), Ok(0));
+        assert_eq!(find_uniform(&uniforms, "lights", gl::FLOAT_VEC3), Ok(4));
+        assert_eq!(find_uniform(&uniforms, "lights[0]", gl::FLOAT_VEC3), Ok(4));
+        assert_eq!(uniforms["lights"].size, 3);
+
+        assert_eq!(
+            find_uniform(&uniforms, "modle", gl::FLOAT_MAT4),
+            Err(UniformProblem::Unknown)
+        );
+        assert_eq!(
+            find_uniform(&uniforms, "model", gl::FLOAT),
+            Err(UniformProblem::WrongType {
+                glsl_type: gl::FLOAT_MAT4
+            })
+        );
+    }
+
+    #[test]
+    fn type_names() {
+        assert_eq!(glsl_type_name(gl::FLOAT_VEC3), "vec3");
+        assert_eq!(glsl_type_name(gl::SAMPLER_2D), "sampler2D");
+        assert_eq!(glsl_type_name(0x1234), "type 0x1234");
+    }
+}
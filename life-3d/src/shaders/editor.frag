@@ -0,0 +1,9 @@
+#version 430 core
+
+out vec4 out_color;
+
+in vec4 cell_color;
+
+void main() {
+    out_color = cell_color;
+}
@@ -0,0 +1,22 @@
+#version 430 core
+
+layout (location = 0) in vec3 a_position;
+
+uniform mat4 projection;
+uniform mat4 model;
+
+// Replaced with the declaration of `CellInstance` from renderer.rs.
+#pragma instance_data
+
+layout (std430, binding = 0) readonly buffer instances {
+    CellInstance cells[];
+};
+
+out vec4 cell_color;
+
+void main() {
+    CellInstance cell = cells[gl_InstanceID];
+    vec3 position = a_position * cell.scale + cell.offset;
+    gl_Position = projection * model * vec4(position, 1.0);
+    cell_color = cell.color;
+}
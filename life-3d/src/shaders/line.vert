@@ -0,0 +1,15 @@
+#version 430 core
+
+layout (location = 0) in vec3 a_position;
+layout (location = 4) in vec4 a_color;
+
+uniform mat4 model;
+uniform mat4 view;
+uniform mat4 projection;
+
+out vec4 line_color;
+
+void main() {
+    gl_Position = projection * view * model * vec4(a_position, 1.0);
+    line_color = a_color;
+}
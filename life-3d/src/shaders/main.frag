@@ -2,10 +2,18 @@
 
 out vec4 out_color;
 
-in vec3 position;
+in vec3 normal;
+in vec4 cell_color;
+in float occlusion;
 
-uniform float cell_size;
+// The way the light travels, in world space.
+uniform vec3 light_direction;
+uniform float ambient;
+uniform float diffuse;
 
 void main() {
-    out_color = vec4(position / cell_size, 1.0);
+    float lambert = max(dot(normalize(normal), -light_direction), 0.0);
+    float light = ambient * occlusion + diffuse * lambert;
+
+    out_color = vec4(cell_color.rgb * light, cell_color.a);
 }
@@ -3,19 +3,64 @@
 layout (location = 0) in vec3 a_position;
 layout (location = 1) in vec3 a_normal;
 layout (location = 2) in vec2 a_uv;
+layout (location = 3) in float a_occlusion;
+layout (location = 4) in vec4 a_color;
 
 uniform mat4 projection;
 uniform mat4 view;
 uniform mat4 model;
 
-layout (std140, binding = 0) buffer instance_offsets {
-    vec3 offset[];
+// Replaced with the declaration of `CellInstance` from renderer.rs.
+#pragma instance_data
+
+layout (std430, binding = 0) readonly buffer instances {
+    CellInstance cells[];
 };
 
-out vec3 position;
+out vec3 normal;
+out vec4 cell_color;
+out float occlusion;
+
+// Must match `occlusion_brightness` in renderer.rs.
+float occlusion_brightness(uint level) {
+    return 1.0 - 0.2 * float(level);
+}
+
+bool is_neighbour_alive(uint neighbours, ivec3 offset) {
+    ivec3 bit = (offset + 1) * ivec3(9, 3, 1);
+    return (neighbours & (1u << (bit.x + bit.y + bit.z))) != 0u;
+}
+
+// Ambient occlusion for instanced cubes, worked out from which of the cells
+// around the cube are alive, the same way the mesher bakes it.
+float instance_occlusion(uint neighbours) {
+    ivec3 facing = ivec3(round(a_normal));
+    // Which way the corner is along the two axes of the face.
+    ivec3 corner = ivec3(sign(a_position)) * (1 - abs(facing));
+    ivec3 side1 = facing.x == 0 ? ivec3(corner.x, 0, 0) : ivec3(0, corner.y, 0);
+    ivec3 side2 = corner - side1;
+
+    bool has_side1 = is_neighbour_alive(neighbours, facing + side1);
+    bool has_side2 = is_neighbour_alive(neighbours, facing + side2);
+    bool has_corner = is_neighbour_alive(neighbours, facing + corner);
+
+    if (has_side1 && has_side2) {
+        return occlusion_brightness(3u);
+    }
+
+    return occlusion_brightness(uint(has_side1) + uint(has_side2) + uint(has_corner));
+}
 
 void main() {
-    const vec3 world_position = a_position + offset[gl_InstanceID];
+    CellInstance cell = cells[gl_InstanceID];
+    const vec3 world_position = a_position * cell.scale + cell.offset;
     gl_Position = projection * view * model * vec4(world_position, 1.0);
-    position = a_position;
+    // The cells are only ever moved and scaled evenly, so this is enough to
+    // get the normal into world space.
+    normal = mat3(model) * a_normal;
+    // Cubes are white and get their colour from the instance, and meshes
+    // are drawn as a single white instance with their colours baked in.
+    cell_color = cell.color * a_color;
+    // Meshes have it baked in, and cubes get it from their instance.
+    occlusion = a_occlusion * instance_occlusion(cell.neighbours);
 }
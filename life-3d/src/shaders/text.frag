@@ -0,0 +1,17 @@
+#version 430 core
+
+out vec4 out_color;
+
+in vec2 uv;
+in vec4 text_color;
+
+uniform sampler2D font;
+
+void main() {
+    // The font is either fully drawn or not at all.
+    if (texture(font, uv).r < 0.5) {
+        discard;
+    }
+
+    out_color = text_color;
+}
@@ -0,0 +1,16 @@
+#version 430 core
+
+layout (location = 0) in vec3 a_position;
+layout (location = 2) in vec2 a_uv;
+layout (location = 4) in vec4 a_color;
+
+uniform mat4 projection;
+
+out vec2 uv;
+out vec4 text_color;
+
+void main() {
+    gl_Position = projection * vec4(a_position, 1.0);
+    uv = a_uv;
+    text_color = a_color;
+}
@@ -0,0 +1,337 @@
+// Runs the game on a thread of its own, so that however long generations
+// take, drawing doesn't have to wait for them. The thread publishes a
+// snapshot of the game whenever it changes, and everything that changes the
+// game is sent to it as a `Command`.
+
+use std::{
+    sync::{
+        mpsc::{self, Receiver, RecvTimeoutError, Sender},
+        Arc, Mutex,
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+use crate::{
+    game::{Cell, GameOfLife, Rule},
+    scheduler::Scheduler,
+};
+
+// One snapshot being drawn, one waiting to be picked up, and one being
+// written.
+const SNAPSHOT_BUFFERS: usize = 3;
+
+// How long to wait before trying to publish again, when every buffer was in
+// use, in seconds.
+const PUBLISH_RETRY: f64 = 0.001;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    SetCell {
+        x: usize,
+        y: usize,
+        z: usize,
+        cell: Cell,
+    },
+    FlipCell {
+        x: usize,
+        y: usize,
+        z: usize,
+    },
+    SetRule(Rule),
+    Clear,
+    // Runs this many generations, even while paused.
+    Step(u32),
+    SetPaused(bool),
+    TogglePause,
+    // One of `SPEEDS`.
+    SetSpeed(usize),
+    Faster,
+    Slower,
+}
+
+impl Command {
+    fn apply(self, game: &mut GameOfLife, scheduler: &mut Scheduler) {
+        match self {
+            // The cursor can wander off the arena, and editing past its
+            // edges does nothing.
+            Command::SetCell { x, y, z, .. } | Command::FlipCell { x, y, z }
+                if !game.contains(x, y, z) => {}
+            Command::SetCell { x, y, z, cell } => game.set_cell(x, y, z, cell),
+            Command::FlipCell { x, y, z } => game.flip_cell(x, y, z),
+            Command::SetRule(rule) => game.set_rule(rule),
+            Command::Clear => game.clear(),
+            Command::Step(generations) => scheduler.step(generations),
+            Command::SetPaused(paused) => scheduler.set_paused(paused),
+            Command::TogglePause => scheduler.toggle_pause(),
+            Command::SetSpeed(speed) => scheduler.set_speed(speed),
+            Command::Faster => scheduler.faster(),
+            Command::Slower => scheduler.slower(),
+        }
+    }
+}
+
+// The game as it was at some point, along with how it was being run.
+#[derive(Clone)]
+pub struct Snapshot {
+    pub game: GameOfLife,
+    pub scheduler: Scheduler,
+}
+
+// The buffers snapshots get written to. A buffer is only written to once
+// nothing but the pool holds it anymore, so a snapshot never changes while
+// it is being drawn.
+struct SnapshotPool {
+    buffers: Vec<Arc<Snapshot>>,
+}
+
+impl SnapshotPool {
+    fn new(snapshot: &Snapshot) -> SnapshotPool {
+        SnapshotPool {
+            buffers: (0..SNAPSHOT_BUFFERS)
+                .map(|_| Arc::new(snapshot.clone()))
+                .collect(),
+        }
+    }
+
+    // Copies the game into a free buffer, or returns `None` if they are all
+    // still in use.
+    fn write(&mut self, game: &GameOfLife, scheduler: &Scheduler) -> Option<Arc<Snapshot>> {
+        let buffer = self
+            .buffers
+            .iter_mut()
+            .find(|buffer| Arc::strong_count(buffer) == 1)?;
+
+        let snapshot = Arc::get_mut(buffer).unwrap();
+        snapshot.game.copy_from(game);
+        snapshot.scheduler = scheduler.clone();
+
+        Some(buffer.clone())
+    }
+}
+
+fn run(
+    mut game: GameOfLife,
+    mut scheduler: Scheduler,
+    commands: Receiver<Command>,
+    latest: Arc<Mutex<Arc<Snapshot>>>,
+) -> Snapshot {
+    let mut pool = SnapshotPool::new(&latest.lock().unwrap());
+    let mut previous_time = Instant::now();
+    let mut unpublished = false;
+
+    loop {
+        // Sleep until there is a command, or a generation is due.
+        let wait = if unpublished {
+            Some(PUBLISH_RETRY)
+        } else {
+            scheduler.wait_time()
+        };
+
+        let first_command = match wait {
+            None => match commands.recv() {
+                Ok(command) => Some(command),
+                Err(_) => return Snapshot { game, scheduler },
+            },
+            Some(wait) => match commands.recv_timeout(Duration::from_secs_f64(wait)) {
+                Ok(command) => Some(command),
+                Err(RecvTimeoutError::Timeout) => None,
+                Err(RecvTimeoutError::Disconnected) => return Snapshot { game, scheduler },
+            },
+        };
+
+        for command in first_command.into_iter().chain(commands.try_iter()) {
+            command.apply(&mut game, &mut scheduler);
+            unpublished = true;
+        }
+
+        let current_time = Instant::now();
+        let delta_time = (current_time - previous_time).as_secs_f64();
+        previous_time = current_time;
+
+        if scheduler.run(delta_time, || game.update_game()) > 0 {
+            unpublished = true;
+        }
+
+        if unpublished {
+            if let Some(snapshot) = pool.write(&game, &scheduler) {
+                *latest.lock().unwrap() = snapshot;
+                unpublished = false;
+            }
+        }
+    }
+}
+
+pub struct Simulation {
+    // Dropped to tell the thread to stop.
+    commands: Option<Sender<Command>>,
+    latest: Arc<Mutex<Arc<Snapshot>>>,
+    thread: Option<JoinHandle<Snapshot>>,
+}
+
+impl Simulation {
+    pub fn spawn(game: GameOfLife, scheduler: Scheduler) -> Simulation {
+        let (sender, receiver) = mpsc::channel();
+        let latest = Arc::new(Mutex::new(Arc::new(Snapshot {
+            game: game.clone(),
+            scheduler: scheduler.clone(),
+        })));
+
+        let thread_latest = latest.clone();
+        let thread = thread::Builder::new()
+            .name("simulation".to_string())
+            .spawn(move || run(game, scheduler, receiver, thread_latest))
+            .expect("Failed to start the simulation thread.");
+
+        Simulation {
+            commands: Some(sender),
+            latest,
+            thread: Some(thread),
+        }
+    }
+
+    pub fn send(&self, command: Command) {
+        self.commands
+            .as_ref()
+            .unwrap()
+            .send(command)
+            .expect("The simulation thread stopped.");
+    }
+
+    // The most recently published snapshot. Holding on to it for longer
+    // than a frame keeps the thread from reusing its buffer.
+    pub fn latest(&self) -> Arc<Snapshot> {
+        self.latest.lock().unwrap().clone()
+    }
+
+    // Stops the thread once it has carried out every command already sent,
+    // and returns the game as it was left. Unlike `latest`, that includes
+    // commands sent too recently to have been published.
+    pub fn finish(mut self) -> Snapshot {
+        self.commands = None;
+
+        match self.thread.take().map(JoinHandle::join) {
+            Some(Ok(snapshot)) => snapshot,
+            _ => (*self.latest()).clone(),
+        }
+    }
+}
+
+impl Drop for Simulation {
+    fn drop(&mut self) {
+        self.commands = None;
+
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::Arc,
+        thread,
+        time::{Duration, Instant},
+    };
+
+    use super::{Command, Simulation, Snapshot, SnapshotPool, SNAPSHOT_BUFFERS};
+    use crate::{
+        game::{Cell, GameOfLife},
+        scheduler::Scheduler,
+    };
+
+    // Waits for a snapshot that `done` is happy with.
+    fn wait_for(simulation: &Simulation, done: impl Fn(&Snapshot) -> bool) -> Arc<Snapshot> {
+        let started = Instant::now();
+
+        loop {
+            let snapshot = simulation.latest();
+            if done(&snapshot) {
+                return snapshot;
+            }
+
+            assert!(started.elapsed() < Duration::from_secs(60), "timed out");
+            thread::sleep(Duration::from_millis(5));
+        }
+    }
+
+    #[test]
+    fn commands_reach_the_thread() {
+        let simulation = Simulation::spawn(GameOfLife::new(), Scheduler::default());
+
+        simulation.send(Command::SetCell {
+            x: 1,
+            y: 2,
+            z: 3,
+            cell: Cell::Alive,
+        });
+        simulation.send(Command::FlipCell { x: 4, y: 5, z: 6 });
+        let snapshot = wait_for(&simulation, |snapshot| snapshot.game.population() == 2);
+        assert!(snapshot.game.cell(4, 5, 6).is_alive());
+        assert!(snapshot.scheduler.is_paused());
+
+        // Stepping works while paused, and the lonely cells die.
+        simulation.send(Command::Step(1));
+        let snapshot = wait_for(&simulation, |snapshot| snapshot.game.generation() == 1);
+        assert_eq!(snapshot.game.population(), 0);
+
+        simulation.send(Command::TogglePause);
+        wait_for(&simulation, |snapshot| !snapshot.scheduler.is_paused());
+    }
+
+    #[test]
+    fn edits_outside_the_arena_are_ignored() {
+        let simulation = Simulation::spawn(GameOfLife::with_size(16), Scheduler::default());
+
+        simulation.send(Command::FlipCell { x: 16, y: 0, z: 0 });
+        simulation.send(Command::SetCell {
+            x: 0,
+            y: usize::MAX,
+            z: 0,
+            cell: Cell::Alive,
+        });
+        simulation.send(Command::FlipCell { x: 1, y: 2, z: 3 });
+
+        // The thread is still there to take the last one.
+        let snapshot = wait_for(&simulation, |snapshot| snapshot.game.population() > 0);
+        assert_eq!(snapshot.game.live_cells(), vec![[1, 2, 3]]);
+    }
+
+    #[test]
+    fn finishing_keeps_every_command() {
+        let simulation = Simulation::spawn(GameOfLife::with_size(16), Scheduler::default());
+
+        for x in 0..16 {
+            simulation.send(Command::FlipCell { x, y: 0, z: 0 });
+        }
+        simulation.send(Command::SetSpeed(3));
+
+        let snapshot = simulation.finish();
+        assert_eq!(snapshot.game.population(), 16);
+        assert_eq!(snapshot.scheduler.speed(), 3);
+    }
+
+    #[test]
+    fn snapshots_in_use_are_left_alone() {
+        let game = GameOfLife::new();
+        let scheduler = Scheduler::default();
+        let mut pool = SnapshotPool::new(&Snapshot {
+            game: game.clone(),
+            scheduler: scheduler.clone(),
+        });
+
+        let mut held: Vec<Arc<Snapshot>> = (0..SNAPSHOT_BUFFERS)
+            .map(|_| pool.write(&game, &scheduler).unwrap())
+            .collect();
+        assert!(pool.write(&game, &scheduler).is_none());
+
+        held.pop();
+        let mut changed = GameOfLife::new();
+        changed.set_cell(0, 0, 0, Cell::Alive);
+        let snapshot = pool.write(&changed, &scheduler).unwrap();
+
+        assert!(snapshot.game.cell(0, 0, 0).is_alive());
+        assert!(held.iter().all(|old| old.game.cell(0, 0, 0).is_dead()));
+    }
+}
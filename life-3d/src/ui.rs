@@ -0,0 +1,601 @@
+// A few widgets to click on with the mouse, stacked in a column on a panel.
+// Laying them out and working out what the mouse is on happens in
+// framebuffer pixels without touching OpenGL, and `UiRenderer` draws them
+// with the bars and the font.
+
+use crate::{
+    font::GLYPH_HEIGHT,
+    math::{Mat4, Vec2},
+    renderer::{BarRenderer, BarsMesh, TextMesh, TextRenderer},
+    shader_program_from_resources,
+    shader_reload::ShaderWatcher,
+    shaders::{self, ShaderError, ShaderProgram},
+};
+
+pub const PANEL_WIDTH: f32 = 240.0;
+const PANEL_PADDING: f32 = 8.0;
+const ROW_HEIGHT: f32 = 28.0;
+const ROW_GAP: f32 = 6.0;
+const LABEL_PIXEL_SIZE: f32 = 2.0;
+
+const PANEL_COLOR: [f32; 4] = [0.1, 0.1, 0.12, 1.0];
+const WIDGET_COLOR: [f32; 4] = [0.22, 0.22, 0.26, 1.0];
+const TRACK_COLOR: [f32; 4] = [0.15, 0.15, 0.18, 1.0];
+const ACTIVE_COLOR: [f32; 4] = [0.3, 0.55, 0.9, 1.0];
+const LABEL_COLOR: [f32; 4] = [0.9, 0.9, 0.9, 1.0];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Rect {
+    pub min: Vec2,
+    pub max: Vec2,
+}
+
+impl Rect {
+    pub fn new(min: Vec2, max: Vec2) -> Rect {
+        Rect { min, max }
+    }
+
+    pub fn width(&self) -> f32 {
+        self.max.x - self.min.x
+    }
+
+    // The top and left edges are inside, the bottom and right ones aren't,
+    // so that rectangles that touch never both contain a point.
+    pub fn contains(&self, point: Vec2) -> bool {
+        point.x >= self.min.x
+            && point.x < self.max.x
+            && point.y >= self.min.y
+            && point.y < self.max.y
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Widget {
+    Button {
+        label: String,
+    },
+    Toggle {
+        label: String,
+        on: bool,
+    },
+    // Dragged along to pick a value from `min` to `max`.
+    Slider {
+        label: String,
+        value: u32,
+        min: u32,
+        max: u32,
+    },
+    // A value with buttons to take one off it and to add one to it.
+    NumberField {
+        label: String,
+        value: u32,
+        min: u32,
+        max: u32,
+    },
+}
+
+impl Widget {
+    pub fn button(label: &str) -> Widget {
+        Widget::Button {
+            label: label.to_string(),
+        }
+    }
+
+    pub fn toggle(label: &str, on: bool) -> Widget {
+        Widget::Toggle {
+            label: label.to_string(),
+            on,
+        }
+    }
+
+    pub fn slider(label: &str, value: u32, min: u32, max: u32) -> Widget {
+        Widget::Slider {
+            label: label.to_string(),
+            value,
+            min,
+            max,
+        }
+    }
+
+    pub fn number_field(label: &str, value: u32, min: u32, max: u32) -> Widget {
+        Widget::NumberField {
+            label: label.to_string(),
+            value,
+            min,
+            max,
+        }
+    }
+
+    // What is written on the widget.
+    pub fn text(&self) -> String {
+        match self {
+            Widget::Button { label } | Widget::Toggle { label, .. } => label.clone(),
+            Widget::Slider { label, value, .. } | Widget::NumberField { label, value, .. } => {
+                format!("{} {}", label, value)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UiEvent {
+    Pressed,
+    Toggled(bool),
+    Changed(u32),
+}
+
+// The part of a slider's row that can be dragged along.
+fn slider_track(rect: Rect) -> Rect {
+    Rect::new(
+        Vec2::new(
+            rect.min.x + rect.width() / 2.0,
+            rect.min.y + ROW_HEIGHT / 3.0,
+        ),
+        Vec2::new(rect.max.x - PANEL_PADDING, rect.max.y - ROW_HEIGHT / 3.0),
+    )
+}
+
+fn slider_value(track: Rect, x: f32, min: u32, max: u32) -> u32 {
+    let t = ((x - track.min.x) / track.width()).clamp(0.0, 1.0);
+    min + (t * (max - min) as f32).round() as u32
+}
+
+// The minus and plus buttons at the end of a number field's row.
+fn number_buttons(rect: Rect) -> (Rect, Rect) {
+    let plus_left = rect.max.x - ROW_HEIGHT;
+    let minus_left = plus_left - ROW_HEIGHT;
+
+    (
+        Rect::new(
+            Vec2::new(minus_left, rect.min.y),
+            Vec2::new(plus_left, rect.max.y),
+        ),
+        Rect::new(Vec2::new(plus_left, rect.min.y), rect.max),
+    )
+}
+
+// Widgets with an id each, so that the caller can tell which one the mouse
+// did something to.
+pub struct Panel<Id> {
+    // The top left corner.
+    origin: Vec2,
+    widgets: Vec<(Id, Widget)>,
+    // The slider being dragged, if any.
+    dragging: Option<usize>,
+}
+
+impl<Id: Copy + PartialEq> Panel<Id> {
+    pub fn new(origin: Vec2) -> Panel<Id> {
+        Panel {
+            origin,
+            widgets: Vec::new(),
+            dragging: None,
+        }
+    }
+
+    // Adds a widget under the ones already on the panel.
+    pub fn add(&mut self, id: Id, widget: Widget) {
+        self.widgets.push((id, widget));
+    }
+
+    pub fn bounds(&self) -> Rect {
+        let rows = self.widgets.len() as f32;
+        let height = 2.0 * PANEL_PADDING + rows * ROW_HEIGHT + (rows - 1.0).max(0.0) * ROW_GAP;
+
+        Rect::new(self.origin, self.origin + Vec2::new(PANEL_WIDTH, height))
+    }
+
+    fn widget_rect(&self, index: usize) -> Rect {
+        let min = self.origin
+            + Vec2::new(
+                PANEL_PADDING,
+                PANEL_PADDING + index as f32 * (ROW_HEIGHT + ROW_GAP),
+            );
+
+        Rect::new(
+            min,
+            min + Vec2::new(PANEL_WIDTH - 2.0 * PANEL_PADDING, ROW_HEIGHT),
+        )
+    }
+
+    // Every widget along with where it is.
+    pub fn widgets(&self) -> impl Iterator<Item = (Rect, &Widget)> {
+        self.widgets
+            .iter()
+            .enumerate()
+            .map(|(index, (_, widget))| (self.widget_rect(index), widget))
+    }
+
+    pub fn widget(&self, id: Id) -> Option<&Widget> {
+        self.widgets
+            .iter()
+            .find(|(widget_id, _)| *widget_id == id)
+            .map(|(_, widget)| widget)
+    }
+
+    fn widget_mut(&mut self, id: Id) -> Option<&mut Widget> {
+        self.widgets
+            .iter_mut()
+            .find(|(widget_id, _)| *widget_id == id)
+            .map(|(_, widget)| widget)
+    }
+
+    // For keeping a toggle in step with something changed some other way,
+    // like from the keyboard.
+    pub fn set_on(&mut self, id: Id, new_on: bool) {
+        if let Some(Widget::Toggle { on, .. }) = self.widget_mut(id) {
+            *on = new_on;
+        }
+    }
+
+    // The same for sliders and number fields.
+    pub fn set_value(&mut self, id: Id, new_value: u32) {
+        if let Some(
+            Widget::Slider {
+                value, min, max, ..
+            }
+            | Widget::NumberField {
+                value, min, max, ..
+            },
+        ) = self.widget_mut(id)
+        {
+            *value = new_value.clamp(*min, *max);
+        }
+    }
+
+    // Whether the point is on the panel, so that a click there isn't meant
+    // for whatever is behind it.
+    pub fn contains(&self, point: Vec2) -> bool {
+        self.bounds().contains(point)
+    }
+
+    // The mouse button went down at `point`.
+    pub fn press(&mut self, point: Vec2) -> Option<(Id, UiEvent)> {
+        let index =
+            (0..self.widgets.len()).find(|&index| self.widget_rect(index).contains(point))?;
+        let rect = self.widget_rect(index);
+        let (id, widget) = &mut self.widgets[index];
+
+        let event = match widget {
+            Widget::Button { .. } => UiEvent::Pressed,
+            Widget::Toggle { on, .. } => {
+                *on = !*on;
+                UiEvent::Toggled(*on)
+            }
+            Widget::Slider {
+                value, min, max, ..
+            } => {
+                let track = slider_track(rect);
+                if point.x < track.min.x - PANEL_PADDING {
+                    return None;
+                }
+
+                self.dragging = Some(index);
+                *value = slider_value(track, point.x, *min, *max);
+                UiEvent::Changed(*value)
+            }
+            Widget::NumberField {
+                value, min, max, ..
+            } => {
+                let (minus, plus) = number_buttons(rect);
+                let new_value = if minus.contains(point) {
+                    value.saturating_sub(1).max(*min)
+                } else if plus.contains(point) {
+                    (*value + 1).min(*max)
+                } else {
+                    return None;
+                };
+
+                *value = new_value;
+                UiEvent::Changed(new_value)
+            }
+        };
+
+        Some((*id, event))
+    }
+
+    // The mouse moved to `point` with the button still down, which only
+    // matters while dragging a slider.
+    pub fn drag(&mut self, point: Vec2) -> Option<(Id, UiEvent)> {
+        let index = self.dragging?;
+        let track = slider_track(self.widget_rect(index));
+        let (id, widget) = &mut self.widgets[index];
+
+        if let Widget::Slider {
+            value, min, max, ..
+        } = widget
+        {
+            let new_value = slider_value(track, point.x, *min, *max);
+            if new_value != *value {
+                *value = new_value;
+                return Some((*id, UiEvent::Changed(new_value)));
+            }
+        }
+
+        None
+    }
+
+    pub fn release(&mut self) {
+        self.dragging = None;
+    }
+}
+
+// Turns the panel into rectangles and text.
+fn build<Id: Copy + PartialEq>(panel: &Panel<Id>, rects: &mut BarsMesh, text: &mut TextMesh) {
+    rects.clear();
+    text.clear();
+
+    let bounds = panel.bounds();
+    rects.append_rect(bounds.min, bounds.max, PANEL_COLOR);
+
+    for (rect, widget) in panel.widgets() {
+        rects.append_rect(rect.min, rect.max, WIDGET_COLOR);
+
+        match widget {
+            Widget::Button { .. } => {}
+            Widget::Toggle { on, .. } => {
+                let size = ROW_HEIGHT / 2.0;
+                let min = Vec2::new(rect.max.x - PANEL_PADDING - size, rect.min.y + size / 2.0);
+                let color = if *on { ACTIVE_COLOR } else { TRACK_COLOR };
+
+                rects.append_rect(min, min + Vec2::new(size, size), color);
+            }
+            Widget::Slider {
+                value, min, max, ..
+            } => {
+                let track = slider_track(rect);
+                let t = (value - min) as f32 / (*max - *min).max(1) as f32;
+                let filled = Vec2::new(track.min.x + t * track.width(), track.max.y);
+
+                rects.append_rect(track.min, track.max, TRACK_COLOR);
+                rects.append_rect(track.min, filled, ACTIVE_COLOR);
+            }
+            Widget::NumberField { .. } => {
+                let (minus, plus) = number_buttons(rect);
+
+                for (button, sign) in [(minus, "-"), (plus, "+")] {
+                    let inset = Vec2::new(2.0, 2.0);
+                    rects.append_rect(button.min + inset, button.max - inset, TRACK_COLOR);
+                    text.append_text(
+                        sign,
+                        button.min + label_offset(),
+                        LABEL_PIXEL_SIZE,
+                        LABEL_COLOR,
+                    );
+                }
+            }
+        }
+
+        text.append_text(
+            &widget.text(),
+            rect.min + label_offset(),
+            LABEL_PIXEL_SIZE,
+            LABEL_COLOR,
+        );
+    }
+}
+
+// Where a label goes in its row, so that it is centred vertically.
+fn label_offset() -> Vec2 {
+    let height = GLYPH_HEIGHT as f32 * LABEL_PIXEL_SIZE;
+    Vec2::new(PANEL_PADDING, (ROW_HEIGHT - height) / 2.0)
+}
+
+pub struct UiRenderer {
+    rects: BarsMesh,
+    bar_renderer: BarRenderer,
+    // The line shaders draw the vertex colours, which is all the rectangles
+    // need.
+    rect_shader: ShaderProgram,
+    text: TextMesh,
+    text_renderer: TextRenderer,
+    text_shader: ShaderProgram,
+    // For `rect_shader` and `text_shader`, while the shaders are being
+    // reloaded.
+    shader_watchers: Option<[ShaderWatcher; 2]>,
+    // The widgets as they were last uploaded.
+    drawn: Option<Vec<Widget>>,
+}
+
+impl UiRenderer {
+    pub fn new() -> Result<UiRenderer, ShaderError> {
+        let rects = BarsMesh::new();
+        let text = TextMesh::new();
+
+        Ok(UiRenderer {
+            bar_renderer: BarRenderer::new(&rects),
+            rects,
+            rect_shader: shader_program_from_resources!(shaders::LINE_VERT, shaders::LINE_FRAG)?,
+            text_renderer: TextRenderer::new(&text),
+            text,
+            text_shader: shader_program_from_resources!(shaders::TEXT_VERT, shaders::TEXT_FRAG)?,
+            shader_watchers: None,
+            drawn: None,
+        })
+    }
+
+    // Reads the shaders from their files from now on. See `ShaderWatcher`.
+    pub fn watch_shaders(&mut self) {
+        self.shader_watchers = Some([
+            ShaderWatcher::new("line.vert", "line.frag"),
+            ShaderWatcher::new("text.vert", "text.frag"),
+        ]);
+    }
+
+    // Returns whether the shader files are broken.
+    pub fn reload_shaders(&mut self) -> bool {
+        let Some([rect_watcher, text_watcher]) = &mut self.shader_watchers else {
+            return false;
+        };
+
+        // Both are reloaded, so that a broken one doesn't hold up the other.
+        let rects_broken = rect_watcher.reload(&mut self.rect_shader);
+        let text_broken = text_watcher.reload(&mut self.text_shader);
+        rects_broken || text_broken
+    }
+
+    pub fn render<Id: Copy + PartialEq>(&mut self, panel: &Panel<Id>, projection: Mat4) {
+        let widgets: Vec<Widget> = panel
+            .widgets
+            .iter()
+            .map(|(_, widget)| widget.clone())
+            .collect();
+        if self.drawn.as_ref() != Some(&widgets) {
+            build(panel, &mut self.rects, &mut self.text);
+            self.bar_renderer.upload(&self.rects);
+            self.text_renderer.upload(&self.text);
+            self.drawn = Some(widgets);
+        }
+
+        unsafe { glad_gl::gl::Disable(glad_gl::gl::DEPTH_TEST) };
+
+        {
+            let program = self.rect_shader.use_program();
+            program.set_uniform("model", Mat4::new(1.0));
+            program.set_uniform("view", Mat4::new(1.0));
+            program.set_uniform("projection", projection);
+            self.bar_renderer.render_bars(self.bar_renderer.bar_count());
+        }
+
+        {
+            let program = self.text_shader.use_program();
+            program.set_uniform("projection", projection);
+            self.text_renderer.render_text();
+        }
+
+        unsafe { glad_gl::gl::Enable(glad_gl::gl::DEPTH_TEST) };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{build, Panel, Rect, UiEvent, Widget, PANEL_WIDTH};
+    use crate::{
+        math::Vec2,
+        renderer::{BarsMesh, TextMesh},
+    };
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    enum Id {
+        Go,
+        Fast,
+        Speed,
+        Count,
+    }
+
+    fn panel() -> Panel<Id> {
+        let mut panel = Panel::new(Vec2::new(100.0, 50.0));
+        panel.add(Id::Go, Widget::button("Go"));
+        panel.add(Id::Fast, Widget::toggle("Fast", false));
+        panel.add(Id::Speed, Widget::slider("Speed", 1, 1, 5));
+        panel.add(Id::Count, Widget::number_field("Count", 2, 1, 3));
+        panel
+    }
+
+    // The middle of the row for the widget at `index`, and its right end.
+    fn row(panel: &Panel<Id>, index: usize) -> (Vec2, f32) {
+        let (rect, _) = panel.widgets().nth(index).unwrap();
+        (
+            Vec2::new(
+                (rect.min.x + rect.max.x) / 2.0,
+                (rect.min.y + rect.max.y) / 2.0,
+            ),
+            rect.max.x,
+        )
+    }
+
+    #[test]
+    fn layout() {
+        let panel = panel();
+        let rects: Vec<Rect> = panel.widgets().map(|(rect, _)| rect).collect();
+
+        assert_eq!(panel.bounds().min, Vec2::new(100.0, 50.0));
+        assert_eq!(panel.bounds().width(), PANEL_WIDTH);
+
+        for pair in rects.windows(2) {
+            assert!(pair[0].max.y < pair[1].min.y);
+        }
+        for rect in &rects {
+            assert!(panel.bounds().contains(rect.min));
+            assert!(rect.max.y < panel.bounds().max.y);
+        }
+    }
+
+    #[test]
+    fn buttons_and_toggles() {
+        let mut panel = panel();
+        let (go, _) = row(&panel, 0);
+        let (fast, _) = row(&panel, 1);
+
+        assert_eq!(panel.press(go), Some((Id::Go, UiEvent::Pressed)));
+        assert_eq!(panel.press(fast), Some((Id::Fast, UiEvent::Toggled(true))));
+        assert_eq!(panel.press(fast), Some((Id::Fast, UiEvent::Toggled(false))));
+
+        // Between the rows, and off the panel.
+        assert_eq!(panel.press(Vec2::new(go.x, (go.y + fast.y) / 2.0)), None);
+        assert!(panel.contains(Vec2::new(go.x, (go.y + fast.y) / 2.0)));
+        assert_eq!(panel.press(Vec2::new(0.0, 0.0)), None);
+        assert!(!panel.contains(Vec2::new(0.0, 0.0)));
+
+        panel.set_on(Id::Fast, true);
+        assert_eq!(panel.widget(Id::Fast), Some(&Widget::toggle("Fast", true)));
+    }
+
+    #[test]
+    fn sliders_drag() {
+        let mut panel = panel();
+        let (speed, right) = row(&panel, 2);
+
+        // Pressing the label doesn't move it.
+        assert_eq!(panel.press(Vec2::new(speed.x - 100.0, speed.y)), None);
+
+        assert_eq!(
+            panel.press(Vec2::new(right - 1.0, speed.y)),
+            Some((Id::Speed, UiEvent::Changed(5)))
+        );
+        // Dragging keeps going off the end of the track, and even off the
+        // panel.
+        assert_eq!(
+            panel.drag(Vec2::new(-1000.0, 0.0)),
+            Some((Id::Speed, UiEvent::Changed(1)))
+        );
+        assert_eq!(panel.drag(Vec2::new(-900.0, 0.0)), None);
+
+        panel.release();
+        assert_eq!(panel.drag(Vec2::new(right, speed.y)), None);
+
+        panel.set_value(Id::Speed, 100);
+        assert_eq!(panel.widget(Id::Speed).unwrap().text(), "Speed 5");
+    }
+
+    #[test]
+    fn number_fields_step() {
+        let mut panel = panel();
+        let (count, right) = row(&panel, 3);
+        let plus = Vec2::new(right - 5.0, count.y);
+        let minus = Vec2::new(right - 35.0, count.y);
+
+        assert_eq!(panel.press(plus), Some((Id::Count, UiEvent::Changed(3))));
+        assert_eq!(panel.press(plus), Some((Id::Count, UiEvent::Changed(3))));
+        assert_eq!(panel.press(minus), Some((Id::Count, UiEvent::Changed(2))));
+        assert_eq!(panel.press(minus), Some((Id::Count, UiEvent::Changed(1))));
+        assert_eq!(panel.press(minus), Some((Id::Count, UiEvent::Changed(1))));
+        assert_eq!(panel.press(Vec2::new(count.x - 50.0, count.y)), None);
+    }
+
+    #[test]
+    fn builds_a_rect_per_part() {
+        let panel = panel();
+        let mut rects = BarsMesh::new();
+        let mut text = TextMesh::new();
+        build(&panel, &mut rects, &mut text);
+
+        // The panel, a rect per widget, the toggle's light, the slider's
+        // track and fill, and the number field's buttons.
+        assert_eq!(rects.bar_count(), 1 + 4 + 1 + 2 + 2);
+        assert!(text.glyph_count() > 0);
+
+        build(&panel, &mut rects, &mut text);
+        assert_eq!(rects.bar_count(), 10);
+    }
+}